@@ -0,0 +1,52 @@
+/* C interface to the FRAT writer in frat-rs (libfrat_rs).
+ *
+ * The writer assigns clause IDs (starting at 1), remembers the literals of
+ * every live clause, and writes the `f` lines for all remaining clauses in
+ * frat_finish. Functions returning int return 0 on success and a frat-rs
+ * exit code otherwise; functions returning a clause ID return 0 on failure. */
+
+#ifndef FRAT_H
+#define FRAT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct frat_writer frat_writer;
+
+/* Open `path` for writing, in binary FRAT if `binary` is nonzero, else ASCII.
+ * Returns NULL on failure. */
+frat_writer *frat_writer_open(const char *path, int binary);
+
+/* Original clause `lits[0..n]`. Returns 0 if a literal is 0. */
+uint64_t frat_orig(frat_writer *w, const int64_t *lits, size_t n);
+
+/* Derived clause `lits[0..n]`, with optional LRAT hint `hint[0..nhint]` and
+ * optional PR witness `witness[0..nwit]` (pass NULL to omit). The witness must
+ * start with lits[0]. Returns 0 if a literal is 0, or the hint names a clause
+ * which is not live. */
+uint64_t frat_add(frat_writer *w, const int64_t *lits, size_t n,
+  const int64_t *hint, size_t nhint, const int64_t *witness, size_t nwit);
+
+/* Delete the live clause `id`. */
+int frat_del(frat_writer *w, uint64_t id);
+
+/* Rename clauses: `pairs` holds `n` pairs (from, to), 2 * n IDs in total. */
+int frat_reloc(frat_writer *w, const uint64_t *pairs, size_t n);
+
+/* Mark the next step as unjustified, with reason `i`. */
+int frat_todo(frat_writer *w, uint64_t i);
+
+int frat_comment(frat_writer *w, const char *s);
+
+/* Finalize all live clauses, close the file and free `w`. */
+int frat_finish(frat_writer *w);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,23 +1,28 @@
 use std::fs::File;
 use std::io::{self, Read, Seek, SeekFrom};
 use super::parser::*;
+use super::error::{FratError, ParseError, Result};
 pub use super::parser::{Proof, Step, ElabStep};
 
 pub struct VecBackParser(pub Vec<u8>);
 
 impl Iterator for VecBackParser {
-  type Item = Segment;
+  type Item = Result<Segment>;
 
-  fn next(&mut self) -> Option<Segment> {
+  fn next(&mut self) -> Option<Result<Segment>> {
     let (&n, most) = self.0.split_last()?;
-    if n != 0 { panic!("expected 0 byte") }
+    if n != 0 {
+      let pos = self.0.len() - 1;
+      self.0.clear();
+      return Some(Err(FratError::parse(pos, ParseError::MissingTerminator)))
+    }
     let i = most.iter().rposition(|&n| n == 0).map_or(0, |i| i + 1);
     Some(Bin.segment(|| i, self.0.drain(i..)))
   }
 }
 
-pub struct BackParser<M: Mode> {
-  file: File,
+pub struct BackParser<M: Mode, R = File> {
+  file: R,
   remaining: usize,
   pos: usize,
   last_read: usize,
@@ -27,9 +32,9 @@ pub struct BackParser<M: Mode> {
   scan: M::BackScanState,
 }
 
-impl<M: Mode> BackParser<M> {
-  pub fn new(mode: M, mut file: File) -> io::Result<BackParser<M>> {
-    let len = file.metadata()?.len() as usize;
+impl<M: Mode, R: Read + Seek> BackParser<M, R> {
+  pub fn new(mode: M, mut file: R) -> io::Result<Self> {
+    let len = file.seek(SeekFrom::End(0))? as usize;
     let pos = len.checked_sub(1).map_or(0, |l| l % BUFFER_SIZE + 1);
     file.seek(SeekFrom::End(-(pos as i64)))?;
     let mut buf = Box::new([0; BUFFER_SIZE]);
@@ -56,7 +61,7 @@ impl<M: Mode> BackParser<M> {
     Ok(Some(buf))
   }
 
-  fn parse_segment_from(&mut self, b: usize, i: usize) -> Segment {
+  fn parse_segment_from(&mut self, b: usize, i: usize) -> Result<Segment> {
     let seg_start = || (self.remaining + (self.buffers.len() - (b + 1))) * BUFFER_SIZE + i;
     if b == 0 {
       let res = self.mode.segment(seg_start, self.buffers[0][i..self.pos].iter().copied());
@@ -74,18 +79,24 @@ impl<M: Mode> BackParser<M> {
   }
 }
 
-impl<M: Mode> Iterator for BackParser<M> {
-  type Item = Segment;
+impl<M: Mode, R: Read + Seek> Iterator for BackParser<M, R> {
+  type Item = Result<Segment>;
 
-  fn next(&mut self) -> Option<Segment> {
+  fn next(&mut self) -> Option<Result<Segment>> {
     for b in 0.. {
       let buf: &[u8; BUFFER_SIZE] = match self.buffers.get(b) {
-        None => match self.read_chunk().expect("could not read from proof file") {
-          None => {
+        None => match self.read_chunk() {
+          Err(e) => {
+            self.remaining = 0;
+            self.buffers.clear();
+            self.pos = 0;
+            return Some(Err(e.into()))
+          }
+          Ok(None) => {
             if b == 1 && self.pos == 0 { break }
             return Some(self.parse_segment_from(b-1, 0))
           },
-          Some(buf) => { self.buffers.push(buf); self.buffers.last().unwrap() }
+          Ok(Some(buf)) => { self.buffers.push(buf); self.buffers.last().unwrap() }
         },
         Some(buf) => buf
       };
@@ -105,49 +116,93 @@ impl<M: Mode> Iterator for BackParser<M> {
 
 pub struct StepIter<I>(pub I);
 
-impl<I: Iterator<Item=Segment>> Iterator for StepIter<I> {
-  type Item = Step;
-
-  fn next(&mut self) -> Option<Step> {
-    match self.0.next() {
-      None => None,
-      Some(Segment::Comment(s)) => Some(Step::Comment(s)),
-      Some(Segment::Orig(idx, vec)) => Some(Step::Orig(idx, vec)),
-      Some(Segment::Add(idx, vec)) => Some(Step::Add(idx, AddStep(vec), None)),
-      Some(Segment::Del(idx, vec)) => Some(Step::Del(idx, vec)),
-      Some(Segment::Reloc(relocs)) => Some(Step::Reloc(relocs)),
-      Some(Segment::Final(idx, vec)) => Some(Step::Final(idx, vec)),
-      Some(Segment::LProof(steps)) => match self.0.next() {
-        Some(Segment::Add(idx, vec)) =>
-          Some(Step::Add(idx, AddStep(vec), Some(Proof::LRAT(steps)))),
-        _ => panic!("'l' step not preceded by 'a' step")
+fn l_without_a() -> FratError {
+  FratError::Malformed("'l' step not preceded by 'a' step".into())
+}
+
+impl<I: Iterator<Item=Result<Segment>>> Iterator for StepIter<I> {
+  type Item = Result<Step>;
+
+  fn next(&mut self) -> Option<Result<Step>> {
+    Some(Ok(match self.0.next()? {
+      Err(e) => return Some(Err(e)),
+      Ok(Segment::Comment(s)) => Step::Comment(s),
+      Ok(Segment::Orig(idx, vec)) => Step::Orig(idx, vec),
+      Ok(Segment::Add(idx, vec)) => Step::Add(idx, AddStep(vec), None),
+      Ok(Segment::Del(idx, vec)) => Step::Del(idx, vec),
+      Ok(Segment::Reloc(relocs)) => Step::Reloc(relocs),
+      Ok(Segment::Final(idx, vec)) => Step::Final(idx, vec),
+      Ok(Segment::LProof(steps)) => match self.0.next() {
+        Some(Ok(Segment::Add(idx, vec))) =>
+          Step::Add(idx, AddStep(vec), Some(Proof::LRAT(steps))),
+        Some(Err(e)) => return Some(Err(e)),
+        _ => return Some(Err(l_without_a()))
       },
-      Some(Segment::Todo(idx)) => Some(Step::Todo(idx)),
-    }
+      Ok(Segment::Todo(idx)) => Step::Todo(idx),
+    }))
+  }
+}
+
+/// Like [`StepIter`], but for segments in forward order, as produced by
+/// [`FwdParser`](crate::parser::FwdParser), where each `l` segment follows its `a` segment.
+pub struct FwdStepIter<I: Iterator>(pub std::iter::Peekable<I>);
+
+impl<I: Iterator<Item=Result<Segment>>> FwdStepIter<I> {
+  pub fn new(it: I) -> Self { FwdStepIter(it.peekable()) }
+}
+
+impl<I: Iterator<Item=Result<Segment>>> Iterator for FwdStepIter<I> {
+  type Item = Result<Step>;
+
+  fn next(&mut self) -> Option<Result<Step>> {
+    Some(Ok(match self.0.next()? {
+      Err(e) => return Some(Err(e)),
+      Ok(Segment::Comment(s)) => Step::Comment(s),
+      Ok(Segment::Orig(idx, vec)) => Step::Orig(idx, vec),
+      Ok(Segment::Add(idx, vec)) => {
+        let proof = match self.0.next_if(|s| matches!(s, Ok(Segment::LProof(_)))) {
+          Some(Ok(Segment::LProof(steps))) => Some(Proof::LRAT(steps)),
+          _ => None
+        };
+        Step::Add(idx, AddStep(vec), proof)
+      }
+      Ok(Segment::Del(idx, vec)) => Step::Del(idx, vec),
+      Ok(Segment::Reloc(relocs)) => Step::Reloc(relocs),
+      Ok(Segment::Final(idx, vec)) => Step::Final(idx, vec),
+      Ok(Segment::LProof(_)) => return Some(Err(l_without_a())),
+      Ok(Segment::Todo(idx)) => Step::Todo(idx),
+    }))
   }
 }
 
 pub struct ElabStepIter<I>(pub I);
 
-impl<I: Iterator<Item=Segment>> Iterator for ElabStepIter<I> {
-  type Item = ElabStep;
-
-  fn next(&mut self) -> Option<ElabStep> {
-    match self.0.next() {
-      None => None,
-      Some(Segment::Comment(s)) => Some(ElabStep::Comment(s)),
-      Some(Segment::Orig(idx, vec)) => Some(ElabStep::Orig(idx, vec)),
-      Some(Segment::Add(_, _)) => panic!("add step has no proof"),
-      Some(Segment::Del(idx, vec)) =>
-        {assert!(vec.is_empty()); Some(ElabStep::Del(idx))},
-      Some(Segment::Reloc(relocs)) => Some(ElabStep::Reloc(relocs)),
-      Some(Segment::LProof(steps)) => match self.0.next() {
-        Some(Segment::Add(idx, vec)) =>
-          Some(ElabStep::Add(idx, AddStep(vec), steps)),
-        _ => panic!("'l' step not preceded by 'a' step")
+impl<I: Iterator<Item=Result<Segment>>> Iterator for ElabStepIter<I> {
+  type Item = Result<ElabStep>;
+
+  fn next(&mut self) -> Option<Result<ElabStep>> {
+    Some(Ok(match self.0.next()? {
+      Err(e) => return Some(Err(e)),
+      Ok(Segment::Comment(s)) => ElabStep::Comment(s),
+      Ok(Segment::Orig(idx, vec)) => ElabStep::Orig(idx, vec),
+      Ok(Segment::Add(idx, _)) =>
+        return Some(Err(FratError::Malformed(format!("add step {} has no proof", idx)))),
+      Ok(Segment::Del(idx, vec)) => {
+        if !vec.is_empty() {
+          return Some(Err(FratError::Malformed(
+            format!("delete step {} in elaborated proof has literals", idx))))
+        }
+        ElabStep::Del(idx)
+      }
+      Ok(Segment::Reloc(relocs)) => ElabStep::Reloc(relocs),
+      Ok(Segment::LProof(steps)) => match self.0.next() {
+        Some(Ok(Segment::Add(idx, vec))) => ElabStep::Add(idx, AddStep(vec), steps),
+        Some(Err(e)) => return Some(Err(e)),
+        _ => return Some(Err(l_without_a()))
       },
-      Some(Segment::Final(_, _)) => panic!("unexpected 'f' segment"),
-      Some(Segment::Todo(_)) => self.next(),
-    }
+      Ok(Segment::Final(idx, _)) =>
+        return Some(Err(FratError::Malformed(format!("unexpected 'f' segment at {}", idx)))),
+      Ok(Segment::Todo(_)) => return self.next(),
+    }))
   }
 }
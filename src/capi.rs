@@ -0,0 +1,132 @@
+//! C bindings for [`FratWriter`], see `include/frat.h`.
+//!
+//! Functions returning `int` return 0 on success, and otherwise the exit code
+//! of the error class (see [`FratError::exit_code`](crate::error::FratError::exit_code)).
+//! Functions returning a clause ID return 0 on failure.
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::BufWriter;
+use std::os::raw::{c_char, c_int};
+use std::slice;
+use crate::error::{FratError, Result};
+use crate::parser::{Ascii, Bin};
+use crate::writer::FratWriter;
+
+pub enum FratWriterC {
+  Bin(FratWriter<Bin, BufWriter<File>>),
+  Ascii(FratWriter<Ascii, BufWriter<File>>),
+}
+
+macro_rules! with {($w:expr, |$v:ident| $e:expr) => {
+  match $w {
+    FratWriterC::Bin($v) => $e,
+    FratWriterC::Ascii($v) => $e,
+  }
+}}
+
+unsafe fn slice_or_empty<'a, T>(p: *const T, n: usize) -> &'a [T] {
+  if p.is_null() { &[] } else { slice::from_raw_parts(p, n) }
+}
+
+fn code(r: Result<()>) -> c_int {
+  match r {
+    Ok(()) => 0,
+    Err(e) => e.exit_code(),
+  }
+}
+
+fn id(r: Result<u64>) -> u64 { r.unwrap_or(0) }
+
+/// Open `path` for writing a FRAT proof, in binary mode if `binary` is nonzero.
+/// Returns null on failure.
+///
+/// # Safety
+/// `path` must be a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn frat_writer_open(path: *const c_char, binary: c_int) -> *mut FratWriterC {
+  let path = match CStr::from_ptr(path).to_str() { Ok(p) => p, Err(_) => return std::ptr::null_mut() };
+  let w = match File::create(path) { Ok(f) => BufWriter::new(f), Err(_) => return std::ptr::null_mut() };
+  Box::into_raw(Box::new(if binary != 0 {
+    FratWriterC::Bin(FratWriter::new(Bin, w))
+  } else {
+    FratWriterC::Ascii(FratWriter::new(Ascii, w))
+  }))
+}
+
+/// Write an original clause, returning its ID, or 0 if a literal is 0.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`, and `lits` must point to `n` literals.
+#[no_mangle]
+pub unsafe extern "C" fn frat_orig(w: *mut FratWriterC, lits: *const i64, n: usize) -> u64 {
+  let lits = slice_or_empty(lits, n);
+  id(with!(&mut *w, |w| w.orig(lits)))
+}
+
+/// Write a derived clause, returning its ID. `hint` (of length `nhint`) is
+/// an optional LRAT proof, and `witness` (of length `nwit`) an optional PR witness;
+/// pass null to omit them. Returns 0 if a literal is 0, or the hint names a clause
+/// which is not live.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`, and the arrays must have the given lengths.
+#[no_mangle]
+pub unsafe extern "C" fn frat_add(w: *mut FratWriterC, lits: *const i64, n: usize,
+  hint: *const i64, nhint: usize, witness: *const i64, nwit: usize
+) -> u64 {
+  let lits = slice_or_empty(lits, n);
+  let hint = if hint.is_null() { None } else { Some(slice::from_raw_parts(hint, nhint)) };
+  let wit = if witness.is_null() { None } else { Some(slice::from_raw_parts(witness, nwit)) };
+  id(with!(&mut *w, |w| w.add(lits, hint, wit)))
+}
+
+/// Delete the live clause `i`.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`.
+#[no_mangle]
+pub unsafe extern "C" fn frat_del(w: *mut FratWriterC, i: u64) -> c_int {
+  code(with!(&mut *w, |w| w.del(i)))
+}
+
+/// Rename clauses. `pairs` contains `n` pairs `from, to`, so it has length `2 * n`.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`, and `pairs` must point to `2 * n` IDs.
+#[no_mangle]
+pub unsafe extern "C" fn frat_reloc(w: *mut FratWriterC, pairs: *const u64, n: usize) -> c_int {
+  let relocs: Vec<_> = slice_or_empty(pairs, 2 * n).chunks(2).map(|p| (p[0], p[1])).collect();
+  code(with!(&mut *w, |w| w.reloc(&relocs)))
+}
+
+/// Mark the next step as unjustified, with reason `i`.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`.
+#[no_mangle]
+pub unsafe extern "C" fn frat_todo(w: *mut FratWriterC, i: u64) -> c_int {
+  code(with!(&mut *w, |w| w.todo(i)))
+}
+
+/// Write a comment.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`, and `s` must be a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn frat_comment(w: *mut FratWriterC, s: *const c_char) -> c_int {
+  let s = match CStr::from_ptr(s).to_str() {
+    Ok(s) => s,
+    Err(_) => return FratError::Malformed("non-utf8 comment".into()).exit_code()
+  };
+  code(with!(&mut *w, |w| w.comment(s)))
+}
+
+/// Finalize all live clauses, close the file and free the writer.
+///
+/// # Safety
+/// `w` must come from `frat_writer_open`, and is invalid after this call.
+#[no_mangle]
+pub unsafe extern "C" fn frat_finish(w: *mut FratWriterC) -> c_int {
+  code(with!(*Box::from_raw(w), |w| w.finish().map(drop)))
+}
@@ -0,0 +1,79 @@
+//! Parsing of the options of the subcommands. Options are accepted in the order
+//! given by the usage message of the subcommand; on a missing or invalid value
+//! a [`FratError::Usage`] with the usage message is returned.
+
+use std::iter::Peekable;
+use crate::error::{FratError, Result};
+
+/// The remaining arguments of a subcommand, and its usage message.
+pub struct Args<I: Iterator<Item=String>> {
+  it: Peekable<I>,
+  name: &'static str,
+  usage: &'static str,
+}
+
+impl<I: Iterator<Item=String>> Args<I> {
+  /// The arguments of `frat-rs NAME`, where `usage` is the expected form of `it`.
+  pub fn new(it: I, name: &'static str, usage: &'static str) -> Self {
+    Args { it: it.peekable(), name, usage }
+  }
+
+  /// The error for arguments that do not match the usage message.
+  pub fn usage(&self) -> FratError {
+    FratError::Usage(format!("Incorrect arguments to `frat-rs {0}`. Expected:\n\n\
+      frat-rs {0} {1}\n\n\
+      Note: options must appear in the specified order", self.name, self.usage))
+  }
+
+  pub fn peek(&mut self) -> Option<&str> { self.it.peek().map(|s| &**s) }
+
+  /// The next positional argument, which is required.
+  pub fn arg(&mut self) -> Result<String> {
+    self.it.next().ok_or_else(|| self.usage())
+  }
+
+  /// The next positional argument, if there is one.
+  pub fn opt_arg(&mut self) -> Option<String> { self.it.next() }
+
+  /// Consume the next argument if it is `name`.
+  pub fn flag(&mut self, name: &str) -> bool {
+    self.peek() == Some(name) && { self.it.next(); true }
+  }
+
+  /// Consume the next argument if it is one of `names`, returning its index.
+  pub fn choice(&mut self, names: &[&str]) -> Option<usize> {
+    let i = names.iter().position(|&s| self.peek() == Some(s))?;
+    self.it.next();
+    Some(i)
+  }
+
+  /// If the next argument is `name`, consume it and return the argument after it,
+  /// which is required.
+  pub fn value(&mut self, name: &str) -> Result<Option<String>> {
+    if self.flag(name) { Ok(Some(self.arg()?)) } else { Ok(None) }
+  }
+
+  /// Like [`Args::value`], but the value is parsed with `f`, and must be valid.
+  pub fn parsed<T>(&mut self, name: &str, f: impl FnOnce(&str) -> Option<T>) -> Result<Option<T>> {
+    match self.value(name)? {
+      Some(s) => f(&s).map(Some).ok_or_else(|| self.usage()),
+      None => Ok(None),
+    }
+  }
+
+  /// Check that all the arguments have been consumed.
+  pub fn finish(&mut self) -> Result<()> {
+    if self.it.peek().is_some() { Err(self.usage()) } else { Ok(()) }
+  }
+
+  /// The remaining arguments.
+  pub fn rest(self) -> impl Iterator<Item=String> { self.it }
+}
+
+/// Parse the value of a `--threads N` option: `N` threads, or one per CPU if `N` is 0.
+pub fn threads(s: &str) -> Option<usize> {
+  match s.parse().ok()? {
+    0 => Some(std::thread::available_parallelism().map_or(1, |n| n.get())),
+    n => Some(n),
+  }
+}
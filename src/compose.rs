@@ -0,0 +1,118 @@
+use std::io::{Read, BufReader, BufWriter};
+use std::fs::File;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, DefaultMode, DRATParser, DRATStep, FwdParser, Step, Proof, AddKind};
+use crate::backparser::FwdStepIter;
+use crate::perm_clause::PermClause;
+use crate::serialize::output_mode;
+use crate::cli::Args;
+use crate::writer::FratWriter;
+
+type Context = HashMap<PermClause, Vec<u64>>;
+
+/// Write the steps of the preprocessing proof `drat` of `cnf`, and return the live clauses.
+fn preprocess<W: std::io::Write>(w: &mut FratWriter<bool, W>, mode: impl Mode,
+  cnf: Vec<Box<[i64]>>, drat: impl Read
+) -> Result<Context> {
+  let mut ctx = Context::default();
+  for ls in cnf {
+    let i = w.orig(&ls)?;
+    ctx.entry(PermClause(ls.into())).or_default().push(i);
+  }
+  for s in DRATParser::from(mode, BufReader::new(drat).bytes()) {
+    match s? {
+      DRATStep::Comment(s) => w.comment(&s)?,
+      DRATStep::Add(add) => {
+        let (i, lemma) = add.parse_into(|add| w.add(add.lemma(), None, add.witness()));
+        ctx.entry(PermClause(lemma)).or_default().push(i?);
+      }
+      DRATStep::Del(ls) => {
+        let ls = PermClause(ls);
+        let i = match ctx.get_mut(&ls).and_then(|vec| vec.pop()) {
+          Some(i) => i,
+          None => return Err(FratError::DeletedNonexistent(ls.0)),
+        };
+        if ctx[&ls].is_empty() { ctx.remove(&ls); }
+        w.del(i)?;
+      }
+    }
+  }
+  Ok(ctx)
+}
+
+/// Write the steps of the solver proof `frat`, whose original clauses are taken from `ctx`.
+/// The clauses of `ctx` which are not original clauses of `frat` are deleted before its
+/// first other step, since they could block its RAT steps.
+fn solve<W: std::io::Write>(w: &mut FratWriter<bool, W>, mode: impl Mode,
+  ctx: Context, frat: impl Read
+) -> Result<()> {
+  let mut ids: HashMap<u64, u64> = HashMap::default();
+  let get = |ids: &HashMap<u64, u64>, step, i| ids.get(&i).copied()
+    .ok_or(FratError::MissingClause { step, id: i });
+  let mut ctx = Some(ctx);
+  for (n, s) in (1..).zip(FwdStepIter::new(FwdParser::new(mode, frat))) {
+    let s = s?;
+    if !matches!(s, Step::Orig(..) | Step::Comment(_)) {
+      if let Some(ctx) = ctx.take() {
+        let mut unused: Vec<_> = ctx.into_values().flatten().collect();
+        unused.sort_unstable();
+        for j in unused { w.del(j)? }
+      }
+    }
+    match s {
+      Step::Comment(s) => w.comment(&s)?,
+      Step::Orig(i, ls) => {
+        let ctx = ctx.as_mut().ok_or_else(|| FratError::Malformed(format!(
+          "original clause {} of the solver proof comes after a derived clause", i)))?;
+        let j = ctx.get_mut(&PermClause(ls)).and_then(|vec| vec.pop()).ok_or_else(||
+          FratError::Malformed(format!(
+            "original clause {} of the solver proof is not in the preprocessed CNF", i)))?;
+        if ids.insert(i, j).is_some() { return Err(FratError::DuplicateClause { step: n, id: i }) }
+      }
+      Step::Add(i, add, proof) => {
+        let hint = match proof {
+          None => None,
+          Some(Proof::LRAT(hint)) => Some(hint.iter().map(|&h| {
+            let j = get(&ids, n, h.unsigned_abs())? as i64;
+            Ok(if h < 0 { -j } else { j })
+          }).collect::<Result<Vec<_>>>()?),
+        };
+        let j = match add.parse() {
+          AddKind::RAT(ls) => w.add(ls, hint.as_deref(), None)?,
+          AddKind::PR(ls, wit) => w.add(ls, hint.as_deref(), Some(wit))?,
+        };
+        if ids.insert(i, j).is_some() { return Err(FratError::DuplicateClause { step: n, id: i }) }
+      }
+      Step::Del(i, _) => w.del(ids.remove(&i).ok_or(FratError::MissingClause { step: n, id: i })?)?,
+      Step::Reloc(relocs) => {
+        let moved = relocs.into_iter().map(|(from, to)|
+          Ok((to, ids.remove(&from).ok_or(FratError::MissingClause { step: n, id: from })?)))
+          .collect::<Result<Vec<_>>>()?;
+        for (to, j) in moved {
+          if ids.insert(to, j).is_some() { return Err(FratError::DuplicateClause { step: n, id: to }) }
+        }
+      }
+      Step::Todo(i) => w.todo(i)?,
+      // All live clauses are finalized at the end
+      Step::Final(..) => {}
+    }
+  }
+  Ok(())
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "compose", "DIMACSFILE DRATFILE FRATFILE NEWFRATFILE [--ascii|--binary]");
+  let (dimacs, drat_path, frat_path, out) = (args.arg()?, args.arg()?, args.arg()?, args.arg()?);
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let mut w = FratWriter::new(out_bin, BufWriter::new(File::create(out)?));
+  let (bin, drat) = compress::open_detect(drat_path)?;
+  let ctx = if bin { preprocess(&mut w, Bin, cnf, drat)? } else { preprocess(&mut w, Ascii, cnf, drat)? };
+  let (bin, frat) = compress::open_detect(frat_path)?;
+  if bin { solve(&mut w, Bin, ctx, frat)? } else { solve(&mut w, Ascii, ctx, frat)? }
+  w.finish()?;
+  Ok(())
+}
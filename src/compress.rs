@@ -0,0 +1,157 @@
+//! Transparent decompression of input files. The compression format is detected
+//! from the magic bytes at the start of the file, so the file name does not matter.
+//!
+//! Forward readers use [`open`], which decompresses on the fly. [`BackParser`]
+//! needs to seek, so [`open_seekable`] decompresses into an anonymous temporary
+//! file first (or returns the file itself if it is not compressed).
+//!
+//! The path `-` refers to standard input. Standard input and other special files
+//! (such as pipes) are read only once, and are spooled by [`open_spooled`] if they
+//! need to be read backwards.
+//!
+//! [`BackParser`]: crate::backparser::BackParser
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Cursor};
+use std::path::Path;
+use either::Either;
+use crate::parser::detect_binary;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression { None, Gzip, Xz, Zstd }
+
+impl Compression {
+  /// Detect the compression format from the magic bytes in `head`.
+  pub fn from_magic(head: &[u8]) -> Self {
+    if head.starts_with(&[0x1f, 0x8b]) { Compression::Gzip }
+    else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) { Compression::Xz }
+    else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) { Compression::Zstd }
+    else { Compression::None }
+  }
+
+  /// Detect the compression format of a file, leaving it positioned at the start.
+  pub fn detect(f: &mut File) -> io::Result<Self> {
+    let mut head = [0; 6];
+    let mut n = 0;
+    while n < head.len() {
+      match f.read(&mut head[n..])? { 0 => break, k => n += k }
+    }
+    f.seek(SeekFrom::Start(0))?;
+    Ok(Self::from_magic(&head[..n]))
+  }
+
+  /// Wrap a reader in a decoder for this format.
+  pub fn decoder<'a>(self, r: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match self {
+      Compression::None => Box::new(r),
+      Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(r)),
+      Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(r)),
+      Compression::Zstd => Box::new(zstd::Decoder::new(r)?),
+    })
+  }
+}
+
+/// A spooled input, either in a file or in memory.
+pub type Spool = Either<File, Cursor<Vec<u8>>>;
+
+fn is_stdin(path: &Path) -> bool { path == Path::new("-") }
+
+/// Returns true if `path` is a regular file, which can be read more than once and
+/// seeked. Standard input (`-`), pipes and other special files are not regular.
+pub fn is_regular(path: impl AsRef<Path>) -> io::Result<bool> {
+  let path = path.as_ref();
+  Ok(!is_stdin(path) && std::fs::metadata(path)?.is_file())
+}
+
+/// Open a stream which is not regular, detecting the compression from the first bytes.
+fn open_stream(path: &Path) -> io::Result<Box<dyn Read>> {
+  let mut r: Box<dyn Read> =
+    if is_stdin(path) { Box::new(io::stdin()) } else { Box::new(File::open(path)?) };
+  let mut head = vec![0; 6];
+  let mut n = 0;
+  while n < head.len() {
+    match r.read(&mut head[n..])? { 0 => break, k => n += k }
+  }
+  head.truncate(n);
+  Compression::from_magic(&head).decoder(Cursor::new(head).chain(r))
+}
+
+/// Open a file for reading, decompressing it on the fly if necessary.
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn Read>> {
+  let path = path.as_ref();
+  if !is_regular(path)? { return open_stream(path) }
+  let mut f = File::open(path)?;
+  Compression::detect(&mut f)?.decoder(f)
+}
+
+/// Open a file for reading, and also determine whether it is binary.
+/// For uncompressed files this is [`detect_binary`]; for compressed files and streams,
+/// which cannot cheaply be read from the end, it is [`sniff_binary`].
+/// The first few kilobytes of the (decompressed) contents are available as the head
+/// of the returned stream.
+pub fn open_detect(path: impl AsRef<Path>) -> io::Result<(bool, Sniffed<Box<dyn Read>>)> {
+  let path = path.as_ref();
+  if !is_regular(path)? { return sniff_binary(open_stream(path)?) }
+  let mut f = File::open(path)?;
+  match Compression::detect(&mut f)? {
+    Compression::None => {
+      let bin = detect_binary(&mut f)?;
+      f.seek(SeekFrom::Start(0))?;
+      let (_, r) = sniff_binary(Box::new(f) as Box<dyn Read>)?;
+      Ok((bin, r))
+    }
+    c => sniff_binary(c.decoder(f)?)
+  }
+}
+
+/// A stream with a prefix that has already been read put back in front.
+pub type Sniffed<R> = io::Chain<Cursor<Vec<u8>>, R>;
+
+/// The prefix of a [`Sniffed`] stream which was read ahead.
+pub fn head<R>(r: &Sniffed<R>) -> &[u8] { r.get_ref().0.get_ref() }
+
+/// Determine whether a stream is binary by looking at its first few kilobytes:
+/// ASCII proof files only contain printable characters and whitespace, while binary
+/// files contain 0 bytes (step terminators) and small varint bytes.
+/// Returns the stream with the inspected prefix put back.
+pub fn sniff_binary<R: Read>(mut r: R) -> io::Result<(bool, Sniffed<R>)> {
+  let mut head = vec![0; 4096];
+  let mut n = 0;
+  while n < head.len() {
+    match r.read(&mut head[n..])? { 0 => break, k => n += k }
+  }
+  head.truncate(n);
+  let bin = head.iter().any(|&c| !(c.is_ascii_graphic() || c.is_ascii_whitespace()));
+  Ok((bin, Cursor::new(head).chain(r)))
+}
+
+/// Open a file for random access. A regular uncompressed file is returned as is;
+/// anything else is decompressed and read to the end, into memory if `in_mem` is
+/// set and otherwise into an anonymous temporary file, which is removed when it is closed.
+pub fn open_spooled(path: impl AsRef<Path>, in_mem: bool) -> io::Result<Spool> {
+  let path = path.as_ref();
+  let mut r = if is_regular(path)? {
+    let mut f = File::open(path)?;
+    match Compression::detect(&mut f)? {
+      Compression::None => return Ok(Either::Left(f)),
+      c => c.decoder(f)?
+    }
+  } else {
+    open_stream(path)?
+  };
+  if in_mem {
+    let mut buf = vec![];
+    r.read_to_end(&mut buf)?;
+    Ok(Either::Right(Cursor::new(buf)))
+  } else {
+    let mut spool = tempfile::tempfile()?;
+    io::copy(&mut r, &mut spool)?;
+    spool.seek(SeekFrom::Start(0))?;
+    Ok(Either::Left(spool))
+  }
+}
+
+/// Open a file for random access, spooling it to a temporary file if necessary.
+pub fn open_seekable(path: impl AsRef<Path>) -> io::Result<File> {
+  Ok(open_spooled(path, false)?.left().expect("spooled to a file"))
+}
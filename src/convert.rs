@@ -0,0 +1,27 @@
+use std::io::{Read, Write, BufWriter};
+use std::fs::File;
+use crate::compress;
+use crate::error::Result;
+use crate::parser::{Mode, Bin, Ascii, FwdParser, StepRef};
+use crate::backparser::FwdStepIter;
+use crate::serialize::{ModeWrite, ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
+
+/// Copy the FRAT proof `frat` to `w`, step by step, so that the output is in the mode of `w`.
+pub fn convert<M>(mode: impl Mode, frat: impl Read, w: &mut impl ModeWrite<M>) -> Result<()>
+where for<'a> StepRef<'a>: Serialize<M> {
+  for s in FwdStepIter::new(FwdParser::new(mode, frat)) { s?.write(w)? }
+  Ok(())
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "convert", "FRATFILE NEWFRATFILE [--ascii|--binary]");
+  let (frat_path, out_path) = (args.arg()?, args.arg()?);
+  let (bin, frat) = compress::open_detect(frat_path)?;
+  // By default the output is in the other mode
+  let out_bin = output_mode(&mut args, !bin);
+  args.finish()?;
+  let w = &mut ModeWriter(out_bin, BufWriter::new(File::create(out_path)?));
+  if bin { convert(Bin, frat, w)? } else { convert(Ascii, frat, w)? }
+  Ok(w.flush()?)
+}
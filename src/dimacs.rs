@@ -1,4 +1,7 @@
 use std::convert::TryInto;
+use std::io::{BufReader, Read};
+use crate::error::{FratError, ParseError, Result};
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Token {
   Nat(i64),
@@ -22,6 +25,9 @@ struct Lexer<I> {
 
   /// the current character that is being dispatched upon
   peek: u8,
+
+  /// the number of characters read so far
+  pos: usize,
 }
 
 impl<I: Iterator<Item=u8>> Lexer<I> {
@@ -29,7 +35,8 @@ impl<I: Iterator<Item=u8>> Lexer<I> {
     let mut lex = Lexer {
       input,
       buffer: Vec::new(),
-      peek: 0
+      peek: 0,
+      pos: 0,
     };
     lex.bump();
     lex
@@ -38,6 +45,7 @@ impl<I: Iterator<Item=u8>> Lexer<I> {
   fn bump_opt(&mut self) -> Option<u8> {
     let peeked = self.input.next()?;
     self.peek = peeked;
+    self.pos += 1;
     Some(peeked)
   }
 
@@ -57,50 +65,55 @@ impl<I: Iterator<Item=u8>> Lexer<I> {
     Comment
   }
 
-  fn scan_keyword(&mut self) -> Ident {
+  fn err(&self, err: ParseError) -> FratError {
+    FratError::parse(self.pos.saturating_sub(1), err)
+  }
+
+  fn scan_keyword(&mut self) -> Result<Ident> {
     self.buffer.clear();
     self.buffer.push(self.peek);
     while (self.bump() as char).is_alphanumeric() {
       if self.buffer.len() < 3 {
         self.buffer.push(self.peek);
-      } else { panic!("unknown keyword") }
+      } else { return Err(self.err(ParseError::DimacsKeyword)) }
     }
-    match &*self.buffer {
+    Ok(match &*self.buffer {
       b"c"   => self.scan_comment(),
       b"p"   => Problem,
       b"cnf" => Cnf,
       b"d"   => Del,
-      _      => panic!("unknown keyword")
-    }
+      _      => return Err(self.err(ParseError::DimacsKeyword))
+    })
   }
 
-  fn scan_nat(&mut self) -> i64 {
+  fn scan_nat(&mut self) -> Result<i64> {
     let mut val = (self.peek as char).to_digit(10)
-      .expect("expected a digit to base 10: (0...9)") as i64;
+      .ok_or_else(|| self.err(ParseError::BadDigit(self.peek)))? as i64;
     while let Some(parsed) = (self.bump() as char).to_digit(10) {
-      val = val.checked_mul(10).unwrap().checked_add(parsed as i64).unwrap();
+      val = val.checked_mul(10).and_then(|val| val.checked_add(parsed as i64))
+        .ok_or_else(|| self.err(ParseError::Overflow))?;
     }
-    val
+    Ok(val)
   }
 }
 
 impl<I: Iterator<Item=u8>> Iterator for Lexer<I> {
-  type Item = Token;
+  type Item = Result<Token>;
 
   fn next(&mut self) -> Option<Self::Item> {
     while (self.peek as char).is_whitespace() {
       self.bump();
     }
     if self.peek == 0 { return None; }
-    match self.peek {
+    Some(match self.peek {
       b'a'..=b'z' => match self.scan_keyword() {
-        Comment => self.next(),
-        tk => Some(Ident(tk))
+        Ok(Comment) => return self.next(),
+        tk => tk.map(Ident)
       },
-      b'0'..=b'9' => Some(Nat(self.scan_nat())),
-      b'-' => { self.bump(); Some(Nat(-self.scan_nat())) },
-      _ => panic!("invalid token start")
-    }
+      b'0'..=b'9' => self.scan_nat().map(Nat),
+      b'-' => { self.bump(); self.scan_nat().map(|n| Nat(-n)) },
+      c => Err(self.err(ParseError::DimacsToken(c)))
+    })
   }
 }
 
@@ -109,38 +122,58 @@ pub type Clause = Box<[i64]>;
 pub struct DimacsIter<I>(Lexer<I>);
 
 impl<I: Iterator<Item=u8>> DimacsIter<I> {
-  pub fn from(input: I) -> (usize, usize, Self) {
+  pub fn from(input: I) -> Result<(usize, usize, Self)> {
     let mut lex = Lexer::from(input);
-    match (lex.next(), lex.next(), lex.next(), lex.next()) {
-      (Some(Ident(Problem)), Some(Ident(Cnf)), Some(Nat(vars)), Some(Nat(clauses))) =>
-        (vars.try_into().unwrap(), clauses.try_into().unwrap(), DimacsIter(lex)),
-      _ => panic!("parse DIMACS failed")
+    let mut header = || lex.next().transpose();
+    if let (Some(Ident(Problem)), Some(Ident(Cnf)), Some(Nat(vars)), Some(Nat(clauses))) =
+      (header()?, header()?, header()?, header()?) {
+      if let (Ok(vars), Ok(clauses)) = (vars.try_into(), clauses.try_into()) {
+        return Ok((vars, clauses, DimacsIter(lex)))
+      }
     }
+    Err(FratError::parse(0, ParseError::DimacsHeader))
   }
 }
 
 impl<I: Iterator<Item=u8>> Iterator for DimacsIter<I> {
-  type Item = Vec<i64>;
-  fn next(&mut self) -> Option<Vec<i64>> {
+  type Item = Result<Vec<i64>>;
+  fn next(&mut self) -> Option<Result<Vec<i64>>> {
     let mut clause = Vec::new();
     loop {
       match self.0.next()? {
-        Nat(0) => break,
-        Nat(lit) => clause.push(lit),
-        _ => panic!("parse DIMACS failed")
+        Ok(Nat(0)) => break,
+        Ok(Nat(lit)) => clause.push(lit),
+        Ok(Ident(_)) => return Some(Err(self.0.err(ParseError::DimacsKeyword))),
+        Err(e) => return Some(Err(e)),
       }
     }
-    Some(clause)
+    Some(Ok(clause))
   }
 }
 
-pub fn parse_dimacs_map<T>(input: impl Iterator<Item=u8>, f: impl FnMut(Vec<i64>) -> T) -> (usize, Vec<T>) {
-  let (vars, clauses, it) = DimacsIter::from(input);
+pub fn parse_dimacs_map<T>(input: impl Iterator<Item=u8>,
+  mut f: impl FnMut(Vec<i64>) -> T
+) -> Result<(usize, Vec<T>)> {
+  let (vars, clauses, it) = DimacsIter::from(input)?;
   let mut fmla = Vec::with_capacity(clauses);
-  fmla.extend(it.map(f));
-  (vars, fmla)
+  for c in it { fmla.push(f(c?)) }
+  Ok((vars, fmla))
 }
 
-pub fn parse_dimacs(input: impl Iterator<Item=u8>) -> (usize, Vec<Clause>) {
+pub fn parse_dimacs(input: impl Iterator<Item=u8>) -> Result<(usize, Vec<Clause>)> {
   parse_dimacs_map(input, |x| x.into())
 }
+
+/// Like [`parse_dimacs_map`], but reading from a stream (such as one returned by
+/// [`compress::open`](crate::compress::open)).
+pub fn read_dimacs_map<T>(input: impl Read, f: impl FnMut(Vec<i64>) -> T
+) -> Result<(usize, Vec<T>)> {
+  let mut err = None;
+  let res = parse_dimacs_map(BufReader::new(input).bytes()
+    .map_while(|c| c.map_err(|e| err = Some(e)).ok()), f);
+  match err { Some(e) => Err(e.into()), None => res }
+}
+
+pub fn read_dimacs(input: impl Read) -> Result<(usize, Vec<Clause>)> {
+  read_dimacs_map(input, |x| x.into())
+}
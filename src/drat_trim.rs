@@ -1,12 +1,12 @@
 use std::{fmt::{Display, Debug}, io::StdinLock};
-use std::io::{self, Read, Write, Seek, SeekFrom, BufReader};
+use std::io::{self, Read, Write, Seek, BufReader};
 use std::ops::{Deref, DerefMut};
 use std::fs::File;
 use std::mem;
 use std::time::Instant;
 use either::Either;
 use io::{BufRead, BufWriter, stdout};
-use crate::{dimacs, midvec::MidVec, parser::{DRATParser, DRATStep}};
+use crate::{dimacs, compress, midvec::MidVec, parser::{DRATParser, DRATStep}, error::{FratError, Result}};
 
 const TIMEOUT: u64 = 40000;
 const INIT: usize = 4;
@@ -35,17 +35,14 @@ enum Warning {
   Hard,
 }
 
-#[repr(u8)] #[derive(Copy, Clone, Debug)]
+#[repr(u8)] #[derive(Copy, Clone, Debug, Default)]
 enum Assign {
-  Unassigned,
+  #[default] Unassigned,
   Assigned,
   Assumed,
   Mark,
 }
 
-impl Default for Assign {
-  fn default() -> Self { Self::Unassigned }
-}
 impl Assign {
   #[inline] fn assigned(self) -> bool { !matches!(self, Assign::Unassigned) }
 }
@@ -321,7 +318,7 @@ fn creating(s: &Option<String>, f: impl FnOnce(File) -> io::Result<()>) -> io::R
   if let Some(s) = s { f(File::create(s)?) } else {Ok(())}
 }
 
-macro_rules! reason {($self:ident, $lit:expr) => { $self.reason[$lit.abs() as usize] }}
+macro_rules! reason {($self:ident, $lit:expr) => { $self.reason[$lit.unsigned_abs() as usize] }}
 
 macro_rules! assign {($self:ident, $lit:expr) => {{
   let lit = $lit;
@@ -657,7 +654,7 @@ impl Solver {
       }
       self.print_lrat_line(&mut f, self.count)?;
       f.flush()?;
-      let writes = f.seek(SeekFrom::Current(0))?;
+      let writes = f.stream_position()?;
       if writes != 0 {
         println!("c wrote optimized proof in LRAT format of {} bytes", writes);
       }
@@ -873,8 +870,7 @@ impl Solver {
     loop {
       let p_active = rat_set.iter().map(|&cl| &self.db[cl])
         .filter(|&pr_cls| pr_cls.active()).count();
-      if mem::replace(&mut last_active, Some(p_active))
-          .map_or(false, |last| last >= p_active) {
+      if last_active.replace(p_active).is_some_and(|last| last >= p_active) {
         self.rat_set = rat_set;
         self.witness[w] = witness;
         return true
@@ -1097,6 +1093,7 @@ impl Solver {
 
     let mut active = self.num_clauses;
     let mut adds = 0u64;
+    #[allow(clippy::never_loop)]
     let last_step = 'start_verification: loop {
       for step in 0..self.proof.len() {
         let ad = self.proof[step];
@@ -1372,10 +1369,11 @@ impl Solver {
     }
   }
 
-  fn parse(opts: SolverOpts, input_file: File, proof_file: impl BufRead) -> (bool, Self) {
+  fn parse(opts: SolverOpts, mut input_file: impl Read, proof_file: impl BufRead) -> Result<(bool, Self)> {
     let mut unsat = false;
-    let (num_vars, num_clauses, input_file) =
-      dimacs::DimacsIter::from(BufReader::new(input_file).bytes().map(|c| c.unwrap()));
+    let mut dimacs = vec![];
+    input_file.read_to_end(&mut dimacs)?;
+    let (num_vars, num_clauses, input_file) = dimacs::DimacsIter::from(dimacs.into_iter())?;
     let mut input_file = input_file.zip(1..);
     let mut formula = Vec::with_capacity(num_clauses);
     println!("c parsing input formula with {} variables and {} clauses", num_vars, num_clauses);
@@ -1385,7 +1383,7 @@ impl Solver {
     let mut witness = Vec::with_capacity(INIT);
     let mut file_switch_flag = false;
     let mut reader = TrackLen::new(proof_file);
-    let mut proof_file = DRATParser::from(opts.bin_mode, (&mut reader).bytes().map(|c| c.unwrap())).zip(1..);
+    let mut proof_file = DRATParser::from(opts.bin_mode, (&mut reader).bytes()).zip(1..);
     let mut active = 0;
     let mut max_var = 0;
     let mut count = 1;
@@ -1393,21 +1391,21 @@ impl Solver {
     loop {
       if !file_switch_flag { file_switch_flag |= formula.len() >= num_clauses }
       let (line, del, mut lits) = if !file_switch_flag {
-        let (clause, line) = if let Some(c) = input_file.next() {c} else {
+        let (clause, line) = if let Some((c, line)) = input_file.next() {(c?, line)} else {
           opts.warn(|| println!("\
             c WARNING: early EOF of the input formula\n\
             c WARNING: {} clauses less than expected", num_clauses - formula.len()));
           file_switch_flag = true;
           continue
         };
-        for &lit in &clause {
-          assert!(lit.abs() <= num_vars as i64,
-            "illegal literal {} due to max var {}", lit, num_vars);
+        if let Some(lit) = clause.iter().find(|lit| lit.unsigned_abs() > num_vars as u64) {
+          return Err(FratError::Malformed(
+            format!("illegal literal {} due to max var {}", lit, num_vars)))
         }
         (line, false, clause)
       } else {
         let (step, line) = if let Some(res) = proof_file.next() {res} else {break};
-        match step {
+        match step? {
           DRATStep::Comment(_) => continue,
           DRATStep::Add(lits) => (line, false, lits.0),
           DRATStep::Del(lits) => (line, true, lits)
@@ -1478,7 +1476,7 @@ impl Solver {
 
     println!("c finished parsing, read {} bytes from proof file", reader.bytes_read());
     let n = max_var as usize;
-    (unsat, Self {
+    Ok((unsat, Self {
       opts,
       count,
       db,
@@ -1509,7 +1507,7 @@ impl Solver {
       num_resolve: 0,
       time: ClauseId(0),
       current: 0,
-    })
+    }))
   }
 }
 
@@ -1546,14 +1544,14 @@ fn print_help() -> ! {
   std::process::exit(0);
 }
 
-pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
+pub fn main(mut args: impl Iterator<Item=String>) -> Result<()> {
   let mut opts = SolverOpts::new();
   let mut tmp = 0..;
   // input file in DIMACS format
-  let mut input_file: Option<File> = None;
+  let mut input_file: Option<Box<dyn Read>> = None;
   // proof file in DRAT format (stdin if no argument)
   let stdin = io::stdin();
-  let mut proof_file: Either<StdinLock, BufReader<File>> = Either::Left(stdin.lock());
+  let mut proof_file: Either<StdinLock, BufReader<Box<dyn Read>>> = Either::Left(stdin.lock());
   let mut proof_str = None;
   while let Some(arg) = args.next() {
     if let Some(opt) = arg.strip_prefix('-') {
@@ -1584,15 +1582,15 @@ pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
       }
     } else {
       match tmp.next().unwrap() {
-        0 => input_file = Some(File::open(arg)?),
+        0 => input_file = Some(compress::open(arg)?),
         1 => {
-          #[allow(clippy::manual_range_contains)]
+          #[allow(clippy::manual_range_contains, clippy::unbuffered_bytes)]
           fn detect_binary(file: &str) -> io::Result<bool> {
             fn ascii(c: u8) -> bool {
               c == b'\r' || c == b' ' || c == b'-' ||
-              (b'0'..=b'9').contains(&c) || c == b'c' || c == b'd'
+              c.is_ascii_digit() || c == b'c' || c == b'd'
             }
-            let mut file = File::open(file)?.bytes();
+            let mut file = compress::open(file)?.bytes();
             let c = if let Some(c) = file.next() {c?} else {return Ok(true)};
             if !ascii(c) { return Ok(true) }
             let mut comment = c == b'c';
@@ -1602,7 +1600,7 @@ pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
             for c in file.take(10) {
               let c = c?;
               if !(c == b'd' || c == b'\n' || c == b'\r' || c == b' ' || c == b'-' ||
-                (b'0'..=b'9').contains(&c) || !comment || (b'A'..=b'z').contains(&c)) {
+                c.is_ascii_digit() || !comment || (b'A'..=b'z').contains(&c)) {
                 return Ok(true)
               }
             }
@@ -1612,7 +1610,7 @@ pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
             println!("c turning on binary mode checking");
             opts.bin_mode = true;
           }
-          proof_file = Either::Right(BufReader::new(File::open(&arg)?));
+          proof_file = Either::Right(BufReader::new(compress::open(&arg)?));
           proof_str = Some(arg);
         }
         _ => {}
@@ -1626,7 +1624,7 @@ pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
   }
   opts.reduce &= opts.lrat_file.is_none() && !matches!(opts.mode, Mode::ForwardUnsat);
   opts.full |= matches!(opts.mode, Mode::ForwardUnsat);
-  let (mut unsat, mut s) = Solver::parse(opts, input_file, proof_file);
+  let (mut unsat, mut s) = Solver::parse(opts, input_file, proof_file)?;
   if let Some(proof) = proof_str.filter(|_| s.del_proof) {
     std::fs::remove_file(&proof)?;
     println!("c deleted proof {}", proof);
@@ -3,10 +3,11 @@ use std::mem;
 use std::fmt;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::fs::{File, read_to_string};
-use std::io::{self, *};
+use super::{compress, cli::Args};
+use std::io::*;
 use super::dimacs::{self, Clause};
 use super::parser::*;
+use super::error::FratError;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum StepKind { Add, Del }
@@ -14,19 +15,22 @@ pub enum StepKind { Add, Del }
 pub struct ProofIter<I: Iterator<Item=u8>>(pub I);
 
 impl<I: Iterator<Item=u8>> Iterator for ProofIter<I> {
-	type Item = (StepKind, Clause);
+	type Item = crate::error::Result<(StepKind, Clause)>;
 
 	fn next(&mut self) -> Option<Self::Item> {
     let k = match self.0.next() {
       None => return None,
       Some(b'a') => StepKind::Add,
       Some(b'd') => StepKind::Del,
-      k => panic!("incorrect step {:?}, is this not a binary file?", k) };
+      k => return Some(Err(FratError::Malformed(
+        format!("incorrect step {:?}, is this not a binary file?", k)))) };
     let mut vec = Vec::new();
     loop {
-      match Bin.num(&mut self.0).expect("expected literal") {
-        0 => return Some((k, vec.into())),
-        lit => vec.push(lit)
+      match Bin.num(&mut self.0) {
+        Ok(Some(0)) => return Some(Ok((k, vec.into()))),
+        Ok(Some(lit)) => vec.push(lit),
+        Ok(None) => return Some(Err(FratError::Malformed("expected literal".into()))),
+        Err(e) => return Some(Err(FratError::Malformed(e.to_string()))),
       }
     }
   }
@@ -66,6 +70,7 @@ struct Active {
 	hyp: bool
 }
 
+#[derive(Default)]
 pub struct Pass1 {
 	steps: Vec<StepKind2>,
 	active: Vec<Active>
@@ -80,9 +85,7 @@ fn is_permutation(cl1: &Clause, cl2: &Clause) -> bool {
 }
 
 impl Pass1 {
-	pub fn new() -> Pass1 {
-		Pass1 { steps: Vec::new(), active: Vec::new() }
-  }
+	pub fn new() -> Pass1 { Self::default() }
 
 	pub fn add(&mut self, cl: Clause, hyp: bool) {
 		self.steps.push(StepKind2::Add);
@@ -90,15 +93,15 @@ impl Pass1 {
 			step: StepToken::new(), cl, hyp });
   }
 
-	pub fn del(&mut self, cl: Clause) {
+	pub fn del(&mut self, cl: Clause) -> crate::error::Result<()> {
 		for (i, a) in self.active.iter().enumerate() {
 			if is_permutation(&a.cl, &cl) {
 				self.steps.push(StepKind2::Del(i, a.clone()));
 				self.active.swap_remove(i);
-				return
+				return Ok(())
       }
     }
-		panic!("could not find clause {:?}", cl)
+		Err(FratError::DeletedNonexistent(cl.to_vec()))
   }
 }
 
@@ -287,13 +290,16 @@ impl<F: Write> Serializer<F> {
   }
 }
 
-pub fn process_proof(vars: usize, fmla: &[Clause], drat: ProofIter<impl Iterator<Item=u8>>, frat: bool) {
+pub fn process_proof(vars: usize, fmla: &[Clause], drat: ProofIter<impl Iterator<Item=u8>>,
+  frat: bool
+) -> crate::error::Result<()> {
   let mut pass1 = Pass1::new();
   for cl in fmla { pass1.add(cl.clone(), true) }
-  for (k, cl) in drat {
+  for s in drat {
+    let (k, cl) = s?;
     match k {
       StepKind::Add => pass1.add(cl, false),
-      StepKind::Del => pass1.del(cl)
+      StepKind::Del => pass1.del(cl)?
     }
   }
 
@@ -325,17 +331,17 @@ pub fn process_proof(vars: usize, fmla: &[Clause], drat: ProofIter<impl Iterator
       println!("{:?}", (step, cl, r));
     }
   }
+  Ok(())
 }
 
-pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-	let arg1 = args.next().expect("missing input file");
-  let proof = File::open(args.next().expect("missing proof file"))?;
-	let (vars, fmla) = dimacs::parse_dimacs(read_to_string(arg1)?.bytes());
-	let drat = ProofIter(BufReader::new(proof).bytes().map(|r| r.expect("read failed")));
-	process_proof(vars, &fmla, drat, match args.next() {
-		None => false,
-		Some(ref s) if s == "-b" => true,
-		_ => panic!("unrecognized option")
-	});
-	Ok(())
+pub fn main(args: impl Iterator<Item=String>) -> crate::error::Result<()> {
+	let mut args = Args::new(args, "dratchk", "DIMACSFILE DRATFILE [-b]");
+	let (arg1, proof) = (args.arg()?, args.arg()?);
+	let frat = args.flag("-b");
+	args.finish()?;
+	let proof = compress::open(proof)?;
+	let (vars, fmla) = dimacs::read_dimacs(compress::open(arg1)?)?;
+	let mut buf = vec![];
+	BufReader::new(proof).read_to_end(&mut buf)?;
+	process_proof(vars, &fmla, ProofIter(buf.into_iter()), frat)
 }
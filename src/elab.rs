@@ -1,20 +1,28 @@
 #![allow(clippy::iter_with_drain)] // rust-clippy#8538
 
-use std::io::{self, Read, BufReader, Write, BufWriter};
-use std::fs::{File, read_to_string};
-use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Seek, SeekFrom, BufReader, Write, BufWriter};
+use std::fs::{File, OpenOptions};
+use std::convert::TryFrom;
 use std::mem;
+use std::sync::{Arc, Mutex, mpsc, atomic::{AtomicU64, Ordering}};
+use std::thread;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
 use slab::Slab;
 
 use crate::{HashMap, HashSet};
+use crate::error::{FratError, VerifyError, Result};
 use super::midvec::MidVec;
-use super::dimacs::{parse_dimacs, parse_dimacs_map};
-use super::serialize::{Serialize, ModeWrite, ModeWriter};
+use super::dimacs::{read_dimacs, read_dimacs_map};
+use super::compress;
+use super::cli::{self, Args};
+use super::serialize::{Serialize, ModeWrite, ModeWriter, output_mode};
 use super::parser::{detect_binary, Step, StepRef, ElabStep, ElabStepRef,
-  AddStep, AddStepRef, Segment, Proof, Mode, Ascii, Bin, DefaultMode, LRATParser, LRATStep};
+  AddStep, AddStepRef, AddKind, Segment, Proof, Mode, Ascii, Bin, DefaultMode, LRATParser, LRATStep, LRATStepRef};
 use super::backparser::{VecBackParser, BackParser, StepIter, ElabStepIter};
 use super::perm_clause::*;
+use super::writer::FratWriter;
+use super::resolution::{write_tracecheck, write_resolution};
+use super::grat::write_grat;
 
 // Set this to true to get an error log when unit propagation fails (assumes no RAT steps)
 const LOG_UNIT_PROP_ERROR: bool = false;
@@ -28,13 +36,10 @@ impl Reason {
   fn clause(self) -> Option<usize> { self.0.checked_sub(1) }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 #[repr(u8)]
-enum Assign { No = 0, Yes = 1, Mark = 2 }
+enum Assign { #[default] No = 0, Yes = 1, Mark = 2 }
 
-impl Default for Assign {
-  fn default() -> Self { Self::No }
-}
 impl Assign {
   #[inline] fn assigned(self) -> bool { self != Self::No }
 }
@@ -148,10 +153,9 @@ impl IndexMut<usize> for Clause {
 }
 
 impl Clause {
-  fn check_subsumed(&self, lits: &[i64], step: u64) {
-    assert!(lits.iter().all(|lit| self.contains(lit)),
-      "at {:?}: Clause {:?} added here will later be deleted as {:?}",
-      step, self, lits)
+  fn check_subsumed(&self, lits: &[i64], step: u64) -> Result<()> {
+    if lits.iter().all(|lit| self.contains(lit)) { return Ok(()) }
+    Err(FratError::ClauseMismatch { step, added: lits.into(), deleted: self.lits.to_vec() })
   }
 
   fn max_var(&self) -> i64 {
@@ -220,7 +224,7 @@ struct Context {
   full: bool,
 }
 
-fn dedup_vec<T: PartialEq>(vec: &mut Vec<T>) {
+pub fn dedup_vec<T: PartialEq>(vec: &mut Vec<T>) {
   let mut i = 0;
   while i < vec.len() {
     if vec[..i].contains(&vec[i]) {
@@ -232,7 +236,7 @@ fn dedup_vec<T: PartialEq>(vec: &mut Vec<T>) {
 }
 
 fn trim_cbm(cbm: &mut Vec<HashSet<usize>>) -> i64 {
-  while cbm.last().map_or(false, |set| set.is_empty()) { cbm.pop(); }
+  while cbm.last().is_some_and(|set| set.is_empty()) { cbm.pop(); }
   cbm.len() as i64
 }
 
@@ -272,16 +276,18 @@ impl Context {
     self.va.reserve_to(self.max_var);
   }
 
-  #[inline] fn insert(&mut self, name: u64, marked: bool, lits: Box<[i64]>) {
+  #[inline] fn insert(&mut self, name: u64, marked: bool, lits: Box<[i64]>) -> Result<()> {
     self.reserve(&lits);
-    self.insert_no_reserve(name, marked, lits);
+    self.insert_no_reserve(name, marked, lits)
   }
 
-  fn insert_no_reserve(&mut self, name: u64, marked: bool, mut lits: Box<[i64]>) {
+  fn insert_no_reserve(&mut self, name: u64, marked: bool, mut lits: Box<[i64]>) -> Result<()> {
+    if self.names.contains_key(&name) {
+      return Err(FratError::DuplicateClause { step: self.step, id: name })
+    }
     let unit = self.sort_unit(&mut lits);
     let i = self.clauses.insert(Clause {marked, name, lits});
-    assert!(self.names.insert(name, i).is_none(),
-      "at {:?}: Clause {} to be inserted already exists", self.step, name);
+    self.names.insert(name, i);
     if let Some(ref mut cbm) = self.clauses_by_maxvar {
       if let Some(maxvar) = usize::try_from(self.clauses[i].max_var()).unwrap().checked_sub(1) {
         while maxvar >= cbm.len() { cbm.push(Default::default()); }
@@ -301,11 +307,12 @@ impl Context {
     if !self.all_hints && unit && self.va.unsat().is_none() {
       self.va.add_unit(lits.first().copied().unwrap_or(0), i);
     }
+    Ok(())
   }
 
-  fn remove(&mut self, name: u64) -> Clause {
-    let i = self.names.remove(&name).unwrap_or_else(
-      || panic!("at {:?}: Clause {} to be removed does not exist", self.step, name));
+  fn remove(&mut self, name: u64) -> Result<Clause> {
+    let i = self.names.remove(&name)
+      .ok_or(FratError::MissingClause { step: self.step, id: name })?;
 
     let cl = &self.clauses[i];
     if let Some(ref mut cbm) = self.clauses_by_maxvar {
@@ -331,13 +338,14 @@ impl Context {
       }
     } else {
       self.va.unassign(cl.first().copied().unwrap_or(0));
-      self.units.remove(&i).expect("unit not found");
+      self.units.remove(&i).ok_or_else(|| FratError::Malformed(
+        format!("at {}: unit clause {} not found", self.step, name)))?;
     }
 
-    self.clauses.remove(i)
+    Ok(self.clauses.remove(i))
   }
 
-  fn reloc(&mut self, relocs: &mut Vec<(u64, u64)>) {
+  fn reloc(&mut self, relocs: &mut Vec<(u64, u64)>) -> Result<()> {
     let mut m = HashMap::default();
     let mut removed = Vec::new();
     relocs.retain(|&(from, to)| {
@@ -349,27 +357,42 @@ impl Context {
     });
     for (from, addr) in removed {
       self.clauses[addr].name = from;
-      assert!(self.names.insert(from, addr).is_none(),
-        "at {:?}: Clause {} to be inserted already exists", self.step, from);
+      if self.names.insert(from, addr).is_some() {
+        return Err(FratError::DuplicateClause { step: self.step, id: from })
+      }
+    }
+    Ok(())
+  }
+
+  /// Mark the clause `c` as needed, returning false if it already was.
+  fn mark(&mut self, c: usize) -> bool {
+    let cl = &mut self.clauses[c];
+    if cl.marked { return false }
+    cl.marked = true;
+    if let [a, b, ..] = *cl.lits {
+      self.watch.del(false, a, c);
+      self.watch.del(false, b, c);
+      self.watch.add(true, a, c);
+      self.watch.add(true, b, c);
     }
+    true
   }
 
-  fn get(&self, i: u64) -> usize {
-    *self.names.get(&i).unwrap_or_else(
-      || panic!("at {:?}: Clause {} to be accessed does not exist", self.step, i))
+  fn get(&self, i: u64) -> Result<usize> {
+    self.names.get(&i).copied().ok_or(FratError::MissingClause { step: self.step, id: i })
   }
 
   fn finalize_hint(&mut self, conflict: i64, hint: &mut Hint) {
     struct Finalize<'a> {
       va: &'a mut VAssign,
-      #[cfg(debug)] step: u64,
+      #[cfg(debug_assertions)] step: u64,
       clauses: &'a Slab<Clause>,
       hint: &'a mut Hint,
     }
 
     impl<'a> Finalize<'a> {
       fn mark(&mut self, lit: i64) {
-        #[cfg(debug)] {
+        #[cfg(debug_assertions)] {
           assert!(self.va.is_true(lit), "at {:?}: {} is unjustified", self.step, lit);
         }
         if let Some(c) = self.va.reasons[lit].clause() {
@@ -388,16 +411,14 @@ impl Context {
 
     let mut fin = Finalize {
       va: &mut self.va,
-      #[cfg(debug)] step: self.step,
+      #[cfg(debug_assertions)] step: self.step,
       clauses: &self.clauses,
       hint,
     };
 
     fin.mark(conflict);
     fin.mark(-conflict);
-
-    debug_assert!(!fin.hint.steps.is_empty(),
-      "at {}: empty hint or tautologous clause", self.step);
+    // The hint is empty if the lemma is a tautology
   }
 
   fn clear_marks(&mut self, hint: &mut Hint) {
@@ -407,7 +428,7 @@ impl Context {
   }
 
   #[allow(unused)]
-  fn self_test(&mut self) {
+  fn self_test(&mut self) -> Result<()> {
     let mut error = false;
     'a: for (_, c) in &self.clauses {
       let mut lits = 0;
@@ -433,8 +454,9 @@ impl Context {
     }
     if error {
       let _ = self.log_status("unit_prop_error.log", &[]);
-      panic!("self test failed");
+      return Err(FratError::Malformed(format!("at {}: self test failed", self.step)))
     }
+    Ok(())
   }
 
   fn propagate_core(&mut self) -> Option<i64> {
@@ -628,27 +650,27 @@ impl Context {
     log.flush()
   }
 
-  fn propagate_hint(&mut self, ls: &[i64], is: &[i64]) -> Option<i64> {
+  fn propagate_hint(&mut self, ls: &[i64], is: &[i64]) -> Result<Option<i64>> {
     // if verb {
     //   println!("propagate_hint {:?} {:?}", ls, is);
     //   let _ = self.log_status("unit_prop_before.log", ls);
     // }
 
-    if let Some(k) = self.va.unsat() { return Some(k) }
+    if let Some(k) = self.va.unsat() { return Ok(Some(k)) }
 
     if !self.all_hints && !self.va.units_processed {
       for (&c, &l) in &self.units {
         self.va.add_unit(l, c);
-        if let Some(k) = self.va.unsat() { return Some(k) }
+        if let Some(k) = self.va.unsat() { return Ok(Some(k)) }
       }
       self.va.units_processed = true;
     }
 
     for &x in ls {
-      if !self.va.assign(-x, Reason::NONE) { return Some(x) }
+      if !self.va.assign(-x, Reason::NONE) { return Ok(Some(x)) }
     }
 
-    let mut is: Vec<usize> = is.iter().map(|&i| self.get(i as u64)).collect();
+    let mut is: Vec<usize> = is.iter().map(|&i| self.get(i as u64)).collect::<Result<_>>()?;
     let Context {va, clauses, watch, ..} = self;
     let mut queue = vec![];
     loop {
@@ -662,7 +684,9 @@ impl Context {
         let unsat = if let Some(i) = (1..cl.len()).find(|&i| !va.is_false(cl[i])) {
           let l = cl[0];
           if !va.is_false(l) || cl.lits[i+1..].iter().any(|&l| !va.is_false(l)) {
-            assert!(!self.validate_hints, "at {:?}: clause {:?} is not unit", self.step, cl.name);
+            if self.validate_hints {
+              return Err(FratError::verify(self.step, ls, VerifyError::NotUnit(cl.name)))
+            }
             queue.push(c);
             continue
           }
@@ -678,47 +702,51 @@ impl Context {
         } else {
           k = 0; true
         };
-        assert!(va.assign(k, Reason::new(c)) != unsat);
-        if unsat { return Some(k) }
+        if va.assign(k, Reason::new(c)) == unsat {
+          return Err(FratError::verify(self.step, ls, VerifyError::NotUnit(cl.name)))
+        }
+        if unsat { return Ok(Some(k)) }
         progress = true;
       }
-      if !progress { return None }
+      if !progress { return Ok(None) }
       mem::swap(&mut is, &mut queue);
     }
   }
 
   fn build_step(&mut self, ls: &[i64], hint: Option<&[i64]>, out: &mut Hint,
     fallback: impl FnOnce(&mut Self) -> Option<()>,
-  ) -> bool {
+  ) -> Result<bool> {
     if let Some(is) = hint {
-      if let Some(k) = self.propagate_hint(ls, is) {
+      if let Some(k) = self.propagate_hint(ls, is)? {
         self.finalize_hint(k, out);
-        return true
-      } else if fallback(self).is_some() { return true }
-      if self.validate_hints { return false }
+        return Ok(true)
+      } else if fallback(self).is_some() { return Ok(true) }
+      if self.validate_hints { return Ok(false) }
+    }
+    if self.all_hints {
+      return Err(FratError::verify(self.step, ls, VerifyError::MissingProof))
     }
-    assert!(!self.all_hints, "step {} for {:?}: proof missing", self.step, ls);
     if let Some(k) = self.propagate(ls) {
       self.finalize_hint(k, out);
-      return true
+      return Ok(true)
     }
-    false
+    Ok(false)
   }
 
   #[allow(clippy::too_many_arguments)]
   fn pr_resolve_one(&mut self,
     ls: &[i64], c: usize, witness_va: &MidVec<bool>, depth: usize,
     hint: Option<&[i64]>, out: &mut Hint, pre_rat: &mut Vec<i64>
-  ) {
+  ) -> Result<()> {
     let cl = &self.clauses[c];
-    if !self.full && !cl.marked { return }
+    if !self.full && !cl.marked { return Ok(()) }
     let step_start = out.steps.len();
     let mark_start = out.temp.len();
     #[allow(clippy::never_loop)]
     'done: loop {
-      assert!(!self.all_hints || hint.is_some(),
-        "step {} for {:?}: RAT resolvent with {:?} missing",
-        self.step, ls, cl);
+      if self.all_hints && hint.is_none() {
+        return Err(FratError::verify(self.step, ls, VerifyError::MissingRatHint(cl.name)))
+      }
       out.steps.push(-(cl.name as i64));
       if let Some(k) = self.va.unsat() {
         self.finalize_hint(k, out);
@@ -730,9 +758,10 @@ impl Context {
           break 'done
         }
       }
-      assert!(self.build_step(&[], hint, out, |_| None),
-        "Step {}: Unit propagation stuck, cannot resolve clause {:?} with {:?}",
-        self.step, ls, self.clauses[c]);
+      if !self.build_step(&[], hint, out, |_| None)? {
+        return Err(FratError::verify(self.step, ls,
+          VerifyError::RatResolventStuck(self.clauses[c].name)))
+      }
       break
     }
 
@@ -748,16 +777,40 @@ impl Context {
         }
       } else { *lit = 0 }
     }
+    Ok(())
   }
 
-  fn run_step<'a>(&mut self, ls: &[i64], pivot: Option<&i64>,
-    in_wit: Option<&[i64]>, init: Option<&[i64]>,
-    mut rats: Option<(&'a i64, &'a [i64])>,
-    RatHint {hint: out, pre_rat, rat_set, witness, witness_va}: &mut RatHint
-  ) {
+  /// A context for checking LRAT proofs, where every step must come with a complete hint.
+  fn for_lrat() -> Self {
+    Context { validate_hints: true, all_hints: true, lrat: true, full: true, ..Default::default() }
+  }
+
+  /// A context for elaborating FRAT proofs, with the options of [`elab`].
+  fn for_elab(full: bool, validate: bool, all_hints: bool) -> Self {
+    Context { full, validate_hints: validate, all_hints, ..Default::default() }
+  }
+
+  /// Check an LRAT addition step, with hint `p` in the LRAT format.
+  fn run_lrat_step(&mut self, kind: AddKind<'_>, p: &[i64], hint: &mut RatHint) -> Result<()> {
+    let ls = kind.lemma();
+    self.reserve(ls);
+    // eprintln!("{}: {:?} {:?}", self.step, ls, p);
+    if let Some(start) = p.iter().position(|&i| i < 0).filter(|_| !ls.is_empty()) {
+      let (init, rest) = p.split_at(start);
+      self.run_step(ls, ls.first(), kind.witness(), Some(init), rest.split_first(), hint)
+    } else {
+      self.run_step(ls, ls.first(), kind.witness(), Some(p), None, hint)
+    }
+  }
+
+  /// The unit propagation part of `run_step`. Returns false if the step needs a RAT or PR
+  /// check, in which case the negated lemma and its consequences are left assigned.
+  fn run_rup_step(&mut self, ls: &[i64], pivot: Option<&i64>, init: Option<&[i64]>, has_rats: bool,
+    RatHint {hint: out, rat_set, witness, ..}: &mut RatHint
+  ) -> Result<bool> {
     out.steps.clear();
     witness.clear();
-    let success = if rats.is_none() {
+    let success = if !has_rats {
       self.build_step(ls, init, out, |this| {
         // Special case: A RAT step which introduces a fresh variable is indistinguishable
         // from a non-RAT step, because there are no negative numbers in the LRAT proof since no
@@ -767,30 +820,30 @@ impl Context {
         //
         // We assume that PR steps don't follow this path because any PR step with no touched
         // clauses can be expressed as a PR step with only one witness literal, which is a RAT step.
-        init?.is_empty().then(|| ())?;
+        init?.is_empty().then_some(())?;
         let pivot = *pivot?;
         if this.rat_set_lit == pivot {
-          rat_set.is_empty().then(|| ())?
+          rat_set.is_empty().then_some(())?
         } else if let Some(cbm) = &this.clauses_by_maxvar {
           let var = pivot.unsigned_abs() as usize - 1;
           if var < cbm.len() {
             for set in &cbm[var..] {
               if !set.is_empty() {
                 for &c in set {
-                  (!this.clauses[c].contains(&-pivot)).then(|| ())?
+                  (!this.clauses[c].contains(&-pivot)).then_some(())?
                 }
               }
             }
           }
         } else {
           for (_, cl) in &this.clauses {
-            (!cl.contains(&-pivot)).then(|| ())?
+            (!cl.contains(&-pivot)).then_some(())?
           }
         }
         witness.push(pivot);
         Some(())
-      })
-    } else if let Some(k) = self.propagate_hint(ls, init.unwrap_or(&[])) {
+      })?
+    } else if let Some(k) = self.propagate_hint(ls, init.unwrap_or(&[]))? {
       self.finalize_hint(k, out);
       true
     } else { false };
@@ -798,25 +851,36 @@ impl Context {
     if success {
       self.clear_marks(out);
       self.va.clear_hyps();
-      return
+      return Ok(true)
     }
 
     // A RAT step with no resolvents has no need for pre-RAT hint steps.
     // So if there are such steps then we assume it was just a failed RUP proof
-    assert!(!self.validate_hints || rats.is_some() || init.map_or(true, |init| init.is_empty()),
-      "step {}: Unit propagation stuck, failed to prove empty clause", self.step);
+    if self.validate_hints && !has_rats && init.is_some_and(|init| !init.is_empty()) {
+      return Err(FratError::verify(self.step, ls, VerifyError::UnitPropStuck))
+    }
+    Ok(false)
+  }
+
+  fn run_step<'a>(&mut self, ls: &[i64], pivot: Option<&i64>,
+    in_wit: Option<&[i64]>, init: Option<&[i64]>,
+    mut rats: Option<(&'a i64, &'a [i64])>,
+    hint: &mut RatHint
+  ) -> Result<()> {
+    if self.run_rup_step(ls, pivot, init, rats.is_some(), hint)? { return Ok(()) }
+    let RatHint {hint: out, pre_rat, rat_set, witness, witness_va} = hint;
 
     if let Some(w) = in_wit {
       for &lit in w {
-        assert!(!self.va.is_false(lit) ||
-            self.va.tru_stack.iter().rposition(|&l| l == -lit).unwrap() >= self.va.first_hyp,
-          "step {} failed, witness literal {} is complement of clause {:?}",
-          self.step, lit, self.clauses[self.va.reasons[-lit].clause().unwrap()]);
+        if self.va.is_false(lit) &&
+            self.va.tru_stack.iter().rposition(|&l| l == -lit).is_none_or(|k| k < self.va.first_hyp) {
+          return Err(FratError::verify(self.step, ls, VerifyError::WitnessConflict(lit)))
+        }
         if !self.va.is_true(lit) { witness.push(lit) }
       }
     } else {
-      witness.push(*pivot.unwrap_or_else(||
-        panic!("step {}: Unit propagation stuck, failed to prove empty clause", self.step)))
+      witness.push(*pivot.ok_or_else(||
+        FratError::verify(self.step, ls, VerifyError::UnitPropStuck))?)
     }
 
     let depth = self.va.tru_stack.len();
@@ -867,10 +931,12 @@ impl Context {
     while let Some((&s, rest)) = rats {
       let c = -s as u64;
       if self.lrat {
-        assert!(last.map_or(true, |l| l < c), "RAT steps must be sorted");
+        if last.is_some_and(|l| l >= c) {
+          return Err(FratError::verify(self.step, ls, VerifyError::UnsortedRat))
+        }
         last = Some(c);
       }
-      let c = self.get(c);
+      let c = self.get(c)?;
       let hint = if let Some(i) = rest.iter().position(|&i| i < 0) {
         let (chain, r) = rest.split_at(i);
         rats = r.split_first();
@@ -880,15 +946,19 @@ impl Context {
         rest
       };
       if let Some(seen @ &mut false) = rat_set.get_mut(&c) {
-        self.pr_resolve_one(ls, c, witness_va, depth, Some(hint), out, pre_rat);
+        self.pr_resolve_one(ls, c, witness_va, depth, Some(hint), out, pre_rat)?;
         *seen = true;
         unseen -= 1;
       }
     }
 
     if unseen != 0 {
-      for (&c, _) in rat_set.iter().filter(|(_, &seen)| !seen) {
-        self.pr_resolve_one(ls, c, witness_va, depth, None, out, pre_rat);
+      // Go by clause ID, so that a missing RAT hint is reported independently of
+      // where the clauses are stored
+      let mut unseen: Vec<_> = rat_set.iter().filter(|(_, &seen)| !seen).map(|(&c, _)| c).collect();
+      unseen.sort_unstable_by_key(|&c| self.clauses[c].name);
+      for c in unseen {
+        self.pr_resolve_one(ls, c, witness_va, depth, None, out, pre_rat)?;
       }
     }
 
@@ -901,41 +971,63 @@ impl Context {
     out.steps.extend(pre_rat.drain(..).filter(|&l| l != 0));
     self.clear_marks(out);
     self.va.clear_hyps();
-    witness.iter().for_each(|&w| witness_va[w] = false)
+    witness.iter().for_each(|&w| witness_va[w] = false);
+    Ok(())
   }
 }
 
-fn as_add_step<'a>(lits: &'a mut [i64], witness: &'a [i64]) -> AddStepRef<'a> {
+fn as_add_step<'a>(lits: &'a mut [i64], witness: &'a [i64]) -> Result<AddStepRef<'a>> {
   if let Some(&lit) = witness.first() {
-    let k = lits.iter().position(|&lit2| lit == lit2).unwrap();
+    let k = lits.iter().position(|&lit2| lit == lit2).ok_or_else(|| FratError::Malformed(
+      format!("witness literal {} is not in clause {:?}", lit, lits)))?;
     lits.swap(0, k);
   }
-  if witness.len() <= 1 { AddStepRef::One(lits) }
-  else { AddStepRef::Two(lits, witness) }
+  Ok(if witness.len() <= 1 { AddStepRef::One(lits) }
+  else { AddStepRef::Two(lits, witness) })
 }
 
-fn elab<M: Mode>(
-  mode: M, full: bool, validate: bool, all_hints: bool, frat: File, w: &mut impl ModeWrite<Bin>
-) -> io::Result<()> {
-  let mut origs = Vec::new();
-  let ctx = &mut Context::default();
-  ctx.full = full;
-  ctx.validate_hints = validate;
-  ctx.all_hints = all_hints;
-  let hint = &mut RatHint::default();
-  let mut last_non_finalize = None;
-  let mut finalized_empty_clause = false;
-  for s in StepIter(BackParser::new(mode, frat)?) {
+/// The hint and witness of an `a` step, found ahead of time by a worker of [`elab_par`].
+type FoundHint = (Vec<i64>, Vec<i64>);
+
+/// The state of the backward pass of elaboration.
+struct Elaborator {
+  ctx: Context,
+  hint: RatHint,
+  origs: Vec<(u64, Box<[i64]>)>,
+  last_non_finalize: Option<u64>,
+  finalized_empty_clause: bool,
+  /// The IDs of the clauses marked by the last step, if they are being tracked
+  marked: Option<Vec<u64>>,
+}
+
+impl Elaborator {
+  fn new(full: bool, validate: bool, all_hints: bool) -> Self {
+    Elaborator {
+      ctx: Context::for_elab(full, validate, all_hints),
+      hint: RatHint::default(),
+      origs: Vec::new(),
+      last_non_finalize: None,
+      finalized_empty_clause: false,
+      marked: None,
+    }
+  }
+
+  /// Elaborate the step `s`. For an `a` step, `found` is told whether the step is needed,
+  /// and returns its hint if it has already been found.
+  fn step(&mut self, s: Step, found: impl FnOnce(bool) -> Option<Result<FoundHint>>,
+    w: &mut impl ModeWrite<Bin>
+  ) -> Result<()> {
+    let Elaborator {ctx, hint, origs, last_non_finalize, finalized_empty_clause, marked} = self;
     // eprintln!("<- {:?}", s);
     match s {
       Step::Comment(s) => ElabStep::Comment(s).write(w)?,
 
       Step::Orig(i, ls) => {
         ctx.step = i;
-        last_non_finalize = Some(i);
-        let c = ctx.remove(i);
-        c.check_subsumed(&ls, ctx.step);
-        if full || c.marked {  // If the original clause is marked
+        *last_non_finalize = Some(i);
+        let c = ctx.remove(i)?;
+        c.check_subsumed(&ls, ctx.step)?;
+        if ctx.full || c.marked {  // If the original clause is marked
           origs.push((i, c.lits)); // delay origs to the end
         }
         // else { eprintln!("delete {}", i); }
@@ -943,107 +1035,341 @@ fn elab<M: Mode>(
 
       Step::Add(i, step, p) => {
         ctx.step = i;
-        let mut c = ctx.remove(i);
+        let mut c = ctx.remove(i)?;
         let kind = step.parse();
         let ls = kind.lemma();
-        c.check_subsumed(ls, ctx.step);
-        last_non_finalize = Some(i);
-        if full || c.marked {
-          let wit = kind.witness();
-          if let Some(Proof::LRAT(is)) = p {
-            if let Some(start) = is.iter().position(|&i| i < 0).filter(|_| !ls.is_empty()) {
-              let (init, rest) = is.split_at(start);
-              ctx.run_step(&c, ls.first(), wit, Some(init), rest.split_first(), hint)
-            } else {
-              ctx.run_step(&c, ls.first(), wit, Some(&is), None, hint)
-            }
-          } else {
-            ctx.run_step(&c, ls.first(), wit, None, None, hint)
-          };
-          let steps = &*hint.hint.steps;
-          for &i in steps {
-            let i = i.unsigned_abs();
-            let c = ctx.get(i);
-            let cl = &mut ctx.clauses[c];
-            // let v = cs.get_mut(&i).unwrap();
-            if !cl.marked { // If the necessary clause is not active yet
-              cl.marked = true; // Make it active
-              if let [a, b, ..] = *cl.lits {
-                ctx.watch.del(false, a, c);
-                ctx.watch.del(false, b, c);
-                ctx.watch.add(true, a, c);
-                ctx.watch.add(true, b, c);
+        c.check_subsumed(ls, ctx.step)?;
+        *last_non_finalize = Some(i);
+        let needed = ctx.full || c.marked;
+        match found(needed) {
+          Some(res) => (hint.hint.steps, hint.witness) = res?,
+          None if needed => {
+            let wit = kind.witness();
+            if let Some(Proof::LRAT(is)) = p {
+              if let Some(start) = is.iter().position(|&i| i < 0).filter(|_| !ls.is_empty()) {
+                let (init, rest) = is.split_at(start);
+                ctx.run_step(&c, ls.first(), wit, Some(init), rest.split_first(), hint)?
+              } else {
+                ctx.run_step(&c, ls.first(), wit, Some(&is), None, hint)?
               }
-              if !full { ElabStep::Del(i).write(w)? }
+            } else {
+              ctx.run_step(&c, ls.first(), wit, None, None, hint)?
             }
           }
-          ElabStepRef::Add(i, as_add_step(&mut c.lits, &hint.witness), steps).write(w)?
+          // else { eprintln!("delete {}", i); }
+          None => return Ok(())
         }
-        // else { eprintln!("delete {}", i); }
+        let steps = &*hint.hint.steps;
+        for &i in steps {
+          let i = i.unsigned_abs();
+          // If the necessary clause is not active yet, make it active
+          if ctx.mark(ctx.get(i)?) {
+            if let Some(marked) = marked { marked.push(i) }
+            if !ctx.full { ElabStep::Del(i).write(w)? }
+          }
+        }
+        ElabStepRef::Add(i, as_add_step(&mut c.lits, &hint.witness)?, steps).write(w)?
       }
 
       Step::Reloc(mut relocs) => {
-        ctx.reloc(&mut relocs);
+        ctx.reloc(&mut relocs)?;
         if !relocs.is_empty() { ElabStep::Reloc(relocs).write(w)? }
       }
 
       Step::Del(i, mut ls) => {
         ctx.step = i;
-        last_non_finalize = Some(i);
+        *last_non_finalize = Some(i);
         dedup_vec(&mut ls);
-        ctx.insert(i, false, ls.into());
-        if full { ElabStep::Del(i).write(w)? }
+        ctx.insert(i, false, ls.into())?;
+        if ctx.full { ElabStep::Del(i).write(w)? }
       }
 
       Step::Final(i, mut ls) => {
         ctx.step = i;
-        if let Some(j) = last_non_finalize {
-          panic!("step {}: \
-            'f' steps should only appear at the end of the proof (step {} appears later).", i, j);
+        if let Some(j) = *last_non_finalize {
+          return Err(FratError::FinalNotAtEnd { step: i, later: j })
         }
         // Identical to the Del case, except that the clause should be marked if empty
         dedup_vec(&mut ls);
-        finalized_empty_clause |= ls.is_empty();
-        ctx.insert(i, ls.is_empty(), ls.into());
+        *finalized_empty_clause |= ls.is_empty();
+        ctx.insert(i, ls.is_empty(), ls.into())?;
       }
 
       Step::Todo(_) => ()
     }
+    Ok(())
   }
 
-  for (i, ls) in origs { ElabStep::Orig(i, ls.into()).write(w)? }
+  fn finish(self, w: &mut impl ModeWrite<Bin>) -> Result<()> {
+    for (i, ls) in self.origs { ElabStep::Orig(i, ls.into()).write(w)? }
 
-  assert!(finalized_empty_clause, "empty clause never finalized");
+    if !self.finalized_empty_clause { return Err(FratError::EmptyClauseNotFinalized) }
+    Ok(())
+  }
+}
+
+pub fn elab<M: Mode>(
+  mode: M, full: bool, validate: bool, all_hints: bool, frat: impl Read + Seek, w: &mut impl ModeWrite<Bin>
+) -> Result<()> {
+  let mut el = Elaborator::new(full, validate, all_hints);
+  for s in StepIter(BackParser::new(mode, frat)?) {
+    el.step(s?, |_| None, w)?
+  }
+  el.finish(w)
+}
+
+/// The number of steps per worker thread that [`elab_par`] reads ahead of the step it
+/// is elaborating.
+const ELAB_LOOKAHEAD: usize = 1024;
+
+/// The number of messages to a worker of [`elab_par`] that are sent together.
+const REPLICA_BATCH: usize = 1024;
+
+/// A change to the clause database of [`elab_par`], or an `a` step whose hint is wanted,
+/// sent to a worker thread.
+enum ReplicaMsg {
+  Insert(u64, bool, Arc<[i64]>),
+  Remove(u64),
+  Reloc(Vec<(u64, u64)>),
+  Mark(u64),
+  /// Remove the clause of the `a` step, and search for its hint
+  Job(u64, AddStep, Option<Vec<i64>>),
+}
+
+/// Search for the hint of the `a` step `i` by unit propagation alone. Returns `None` if the
+/// step needs a RAT or PR check, which depends on exactly which clauses are marked, so it
+/// is left to the main thread.
+fn find_hint(ctx: &mut Context, hint: &mut RatHint, i: u64, add: &AddStep, p: Option<&[i64]>
+) -> Option<Result<FoundHint>> {
+  ctx.step = i;
+  // The main thread reports the missing clause
+  let c = ctx.remove(i).ok()?;
+  let kind = add.parse();
+  let ls = kind.lemma();
+  let res = match p {
+    Some(is) => match is.iter().position(|&i| i < 0).filter(|_| !ls.is_empty()) {
+      Some(start) => ctx.run_rup_step(&c, ls.first(), Some(&is[..start]), true, hint),
+      None => ctx.run_rup_step(&c, ls.first(), Some(is), false, hint),
+    },
+    None => ctx.run_rup_step(&c, ls.first(), None, false, hint),
+  };
+  match res {
+    Ok(true) => Some(Ok((hint.hint.steps.clone(), hint.witness.clone()))),
+    Ok(false) => { ctx.va.clear_hyps(); None }
+    Err(e) => { ctx.va.clear_hyps(); Some(Err(e)) }
+  }
+}
+
+/// The result of a job of [`elab_replica`]: an error if the database of the worker is
+/// broken, and otherwise the hint if it was found.
+type ReplicaResult = Result<Option<Result<FoundHint>>>;
+
+/// A worker of [`elab_par`], which keeps its own copy of the clause database.
+/// If a change to the database fails, the error is sent as the result of the next job,
+/// and the worker stops.
+fn elab_replica(mut ctx: Context, rx: mpsc::Receiver<Vec<ReplicaMsg>>,
+  tx: mpsc::Sender<ReplicaResult>
+) {
+  let hint = &mut RatHint::default();
+  let mut broken = None;
+  for batch in rx {
+    for msg in batch {
+      let res = match msg {
+        ReplicaMsg::Insert(i, marked, ls) => ctx.insert(i, marked, ls.to_vec().into()),
+        ReplicaMsg::Remove(i) => ctx.remove(i).map(drop),
+        ReplicaMsg::Reloc(mut relocs) => ctx.reloc(&mut relocs),
+        ReplicaMsg::Mark(i) => { if let Some(&c) = ctx.names.get(&i) { ctx.mark(c); } Ok(()) }
+        ReplicaMsg::Job(i, add, p) => {
+          let res = match broken.take() {
+            Some(e) => Err(e),
+            None => Ok(find_hint(&mut ctx, hint, i, &add, p.as_deref())),
+          };
+          let stop = res.is_err();
+          if tx.send(res).is_err() || stop { return }
+          Ok(())
+        }
+      };
+      if let Err(e) = res { broken.get_or_insert(e); }
+    }
+  }
+}
+
+/// A worker of [`elab_par`], as seen from the main thread.
+struct Replica<'scope> {
+  tx: mpsc::Sender<Vec<ReplicaMsg>>,
+  /// The results of the jobs, in the order they were sent
+  results: mpsc::Receiver<ReplicaResult>,
+  /// The messages which have not been sent yet
+  batch: Vec<ReplicaMsg>,
+  thread: Option<thread::ScopedJoinHandle<'scope, ()>>,
+}
+
+impl Replica<'_> {
+  /// Wait for the result of the next job.
+  fn recv(&mut self) -> Result<Option<Result<FoundHint>>> {
+    match self.results.recv() {
+      Ok(res) => res,
+      // The worker only stops early after sending an error, so it panicked
+      Err(_) => {
+        if let Some(thread) = self.thread.take() { join_replica(thread)? }
+        Err(FratError::Worker("worker stopped".into()))
+      }
+    }
+  }
+
+  fn send(&mut self, msg: ReplicaMsg) {
+    self.batch.push(msg);
+    // A job is sent right away, because the main thread will wait for it
+    if self.batch.len() >= REPLICA_BATCH || matches!(self.batch.last(), Some(ReplicaMsg::Job(..))) {
+      // If the worker is gone, the main thread notices when it waits for a result
+      let _ = self.tx.send(mem::take(&mut self.batch));
+    }
+  }
+}
+
+/// Wait for a worker of [`elab_par`] to finish, and report a panic as an error.
+fn join_replica(thread: thread::ScopedJoinHandle<'_, ()>) -> Result<()> {
+  thread.join().map_err(|e| FratError::Worker(
+    e.downcast_ref::<&str>().map(|s| s.to_string())
+      .or_else(|| e.downcast_ref::<String>().cloned())
+      .unwrap_or_else(|| "unknown panic".into())))
+}
+
+fn broadcast(rs: &mut [Replica<'_>], skip: Option<usize>, msg: impl Fn() -> ReplicaMsg) {
+  for (k, r) in rs.iter_mut().enumerate() {
+    if Some(k) != skip { r.send(msg()) }
+  }
+}
+
+/// A multi-threaded version of [`elab`].
+///
+/// The main thread runs the backward pass as [`elab`] does, and decides which steps are
+/// needed. Each of the `threads` workers keeps a copy of the clause database, following
+/// the main thread as it reads ahead of the step it is elaborating, and the `a` steps
+/// that are already known to be needed when they are read (every step, with `full`) are
+/// handed out to them in turn. A worker searches for the hint of the step by unit
+/// propagation against its database, in which only the clauses marked by the time the
+/// step was read are marked. The main thread picks up the hints in order, and checks
+/// the other needed steps itself, including RAT and PR steps, which depend on exactly
+/// which clauses are marked.
+///
+/// The output is a valid elaboration, but the hints may differ from those of [`elab`],
+/// since fewer clauses are marked when they are found. Without `full`, this also changes
+/// the clauses that RAT and PR steps are checked against. For a given number of threads
+/// the output is deterministic. With the copy of the main thread, there are
+/// `threads + 1` copies of the clause database in memory.
+pub fn elab_par<M: Mode>(
+  mode: M, full: bool, validate: bool, all_hints: bool, threads: usize,
+  frat: impl Read + Seek, w: &mut impl ModeWrite<Bin>
+) -> Result<()> {
+  let threads = threads.max(1);
+  let mut el = Elaborator::new(full, validate, all_hints);
+  el.marked = Some(vec![]);
+  let mut it = StepIter(BackParser::new(mode, frat)?);
+  thread::scope(|s| -> Result<()> {
+    let mut rs = (0..threads).map(|_| {
+      let (tx, rx) = mpsc::channel();
+      let (res_tx, results) = mpsc::channel();
+      let thread = s.spawn(move ||
+        elab_replica(Context::for_elab(full, validate, all_hints), rx, res_tx));
+      Replica { tx, results, batch: vec![], thread: Some(thread) }
+    }).collect::<Vec<_>>();
+    let res = elab_par_main(&mut el, &mut it, full, threads, &mut rs, w);
+    // Stop the workers, and report any panic which the main thread has not seen
+    let threads = rs.into_iter().filter_map(|r| r.thread).collect::<Vec<_>>();
+    let joined = threads.into_iter().map(join_replica).collect::<Vec<_>>();
+    res?;
+    joined.into_iter().collect()
+  })?;
+  el.finish(w)
+}
+
+/// The main thread of [`elab_par`], which elaborates the proof with the workers `rs`.
+fn elab_par_main(
+  el: &mut Elaborator, it: &mut impl Iterator<Item=Result<Step>>, full: bool, threads: usize,
+  rs: &mut [Replica<'_>], w: &mut impl ModeWrite<Bin>
+) -> Result<()> {
+  // The steps which have been read but not elaborated, with the workers
+  // searching for their hints
+  let mut ahead = std::collections::VecDeque::new();
+  let mut relocs_ahead = 0;
+  let mut jobs = 0;
+  let mut done = false;
+  loop {
+    while !done && ahead.len() < ELAB_LOOKAHEAD * threads {
+      let mut s = match it.next() {
+        Some(Ok(s)) => s,
+        // An error is reported after the steps before it are elaborated
+        Some(Err(e)) => { done = true; ahead.push_back((Err(e), None)); break }
+        None => { done = true; break }
+      };
+      let mut worker = None;
+      match &mut s {
+        Step::Comment(_) | Step::Todo(_) => {}
+        Step::Orig(i, _) => { let i = *i; broadcast(rs, None, || ReplicaMsg::Remove(i)) }
+        Step::Add(i, add, p) => {
+          let i = *i;
+          // After a relocation the clause could have a different name in the database
+          let ctx = &el.ctx;
+          if full || relocs_ahead == 0 && ctx.names.get(&i).is_some_and(|&c| ctx.clauses[c].marked) {
+            let k = jobs % threads;
+            jobs += 1;
+            let p = p.as_ref().map(|Proof::LRAT(is)| is.clone());
+            rs[k].send(ReplicaMsg::Job(i, add.clone(), p));
+            worker = Some(k);
+          }
+          broadcast(rs, worker, || ReplicaMsg::Remove(i))
+        }
+        Step::Reloc(relocs) => {
+          relocs_ahead += 1;
+          broadcast(rs, None, || ReplicaMsg::Reloc(relocs.clone()))
+        }
+        Step::Del(i, ls) | Step::Final(i, ls) => {
+          dedup_vec(ls);
+          let (i, ls): (_, Arc<[i64]>) = (*i, ls[..].into());
+          let marked = ls.is_empty() && matches!(s, Step::Final(..));
+          broadcast(rs, None, || ReplicaMsg::Insert(i, marked, ls.clone()))
+        }
+      }
+      ahead.push_back((Ok(s), worker));
+    }
+
+    let Some((s, worker)) = ahead.pop_front() else { break };
+    let s = s?;
+    if let Step::Reloc(_) = s { relocs_ahead -= 1 }
+    let found = match worker { Some(k) => rs[k].recv()?, None => None };
+    el.step(s, |needed| if needed { found } else { None }, w)?;
+    for i in el.marked.as_mut().unwrap().drain(..) {
+      broadcast(rs, None, || ReplicaMsg::Mark(i))
+    }
+  }
   Ok(())
 }
 
-struct DeleteLine<'a, W>(&'a mut W, u64, bool);
+struct DeleteLine(Vec<u64>);
 
-impl<'a, W: Write> DeleteLine<'a, W> {
-  fn with(lrat: &'a mut W, step: u64,
-    f: impl FnOnce(&mut DeleteLine<'a, W>) -> io::Result<()>
-  ) -> io::Result<()> {
-    let mut l = DeleteLine(lrat, step, false);
+impl DeleteLine {
+  fn with<M>(lrat: &mut impl ModeWrite<M>, step: u64,
+    f: impl FnOnce(&mut DeleteLine) -> Result<()>
+  ) -> Result<()> where for<'a> LRATStepRef<'a>: Serialize<M> {
+    let mut l = DeleteLine(vec![]);
     f(&mut l)?;
-    if l.2 { writeln!(l.0, " 0")? }
+    if !l.0.is_empty() { LRATStepRef::Del(step, &l.0).write(lrat)? }
     Ok(())
   }
 
-  fn delete(&mut self, i: u64) -> io::Result<()> {
-    if mem::replace(&mut self.2, true) {
-      write!(self.0, " {}", i)
-    } else {
-      write!(self.0, "{} d {}", self.1, i)
-    }
-  }
+  fn delete(&mut self, i: u64) -> io::Result<()> { self.0.push(i); Ok(()) }
 }
 
-fn trim(
+/// Write the trimmed LRAT proof, and return the number of times each clause of
+/// `cnf` is used as a hint in it. The clauses used at least once form an
+/// unsatisfiable core.
+pub fn trim<M>(
   cnf: &[Box<[i64]>],
-  temp_it: impl Iterator<Item=Segment>,
+  temp_it: impl Iterator<Item=Result<Segment>>,
   comments: bool,
-  lrat: &mut impl Write,
-) -> io::Result<()> {
+  lrat: &mut impl ModeWrite<M>,
+) -> Result<Vec<u64>> where for<'a> LRATStepRef<'a>: Serialize<M> {
 
   let mut k = 0u64; // Counter for the last used ID
   let cnf: HashMap<PermClauseRef, u64> = // original CNF
@@ -1053,20 +1379,30 @@ fn trim(
   let mut copies: HashMap<u64, u32> = HashMap::default();
   let mut bp = ElabStepIter(temp_it).peekable();
   let mut used_origs = vec![0u8; k as usize];
+  let mut uses = vec![0u64; k as usize];
   let mut rats = vec![];
+  // The number of steps of the temp file read so far
+  let mut n = 0;
 
-  while let Some(ElabStep::Orig(_, _)) = bp.peek() {
-    if let Some(ElabStep::Orig(i, ls)) = bp.next() {
+  while let Some(Ok(ElabStep::Orig(_, _))) = bp.peek() {
+    n += 1;
+    if let Some(Ok(ElabStep::Orig(i, ls))) = bp.next() {
       // eprintln!("-> Orig{:?}", (&i, &ls));
-      let j = *cnf.get(&PermClauseRef(&ls)).unwrap_or_else( // Find position of clause in original problem
-        || panic!("Orig step {} refers to nonexistent clause {:?}", i, ls));
+      // Find position of clause in original problem
+      let j = match cnf.get(&PermClauseRef(&ls)) {
+        Some(&j) => j,
+        None => return Err(FratError::OrigNotInCnf { step: i, clause: ls })
+      };
       let r = &mut used_origs[j as usize - 1];
       *r = r.saturating_add(1);
-      assert!(map.insert(i, j).is_none(), "Multiple orig steps with duplicate IDs");
+      if map.insert(i, j).is_some() {
+        return Err(FratError::DuplicateClause { step: n, id: i })
+      }
       // eprintln!("{} -> {}", i, j);
       if ls.is_empty() {
-        writeln!(lrat, "{} 0 {} 0", k+1, j)?;
-        return Ok(())
+        LRATStepRef::Add(k+1, AddStepRef::One(&[]), &[j as i64]).write(lrat)?;
+        uses[j as usize - 1] += 1;
+        return Ok(uses)
       }
     } else {unreachable!()}
   }
@@ -1080,12 +1416,13 @@ fn trim(
 
   while let Some(s) = bp.next() {
     // eprintln!("-> {:?}", s);
+    n += 1;
 
-    match s {
-      ElabStep::Comment(s) => if comments { writeln!(lrat, "{} c {}", k, s)? }
+    match s? {
+      ElabStep::Comment(s) => if comments { LRATStepRef::Comment(k, &s).write(lrat)? }
 
-      ElabStep::Orig(_, _) =>
-        panic!("Orig steps must come at the beginning of the temp file"),
+      ElabStep::Orig(i, _) => return Err(FratError::Malformed(format!(
+        "Orig step {} must come at the beginning of the temp file", i))),
 
       ElabStep::Add(i, AddStep(ls), mut is) => {
         if let Some(cl) = match *is {
@@ -1093,8 +1430,7 @@ fn trim(
           _ => None,
         } {
           // A one-hint RUP step is a subsumed clause, so we can skip it
-          let cl = *map.get(&cl).unwrap_or_else(||
-            panic!("step {}: proof step {:?} not found", i, cl));
+          let cl = *map.get(&cl).ok_or(FratError::MissingClause { step: i, id: cl })?;
           map.insert(i, cl);
           // eprintln!("{} -> {} copy", i, cl);
           *copies.entry(cl).or_default() += 1;
@@ -1104,17 +1440,14 @@ fn trim(
           // eprintln!("{} -> {}", i, k);
           let done = ls.is_empty();
 
-          write!(lrat, "{}", k)?;
-          for &x in &*ls { write!(lrat, " {}", x)? }
-          write!(lrat, " 0")?;
           let mut last_neg = None;
-          for (i, x) in is.iter_mut().enumerate() {
+          for (j, x) in is.iter_mut().enumerate() {
             let ux = x.unsigned_abs();
-            let lit = *map.get(&ux).unwrap_or_else(||
-              panic!("step {}: proof step {:?} not found", i, ux)) as i64;
+            let lit = *map.get(&ux).ok_or(FratError::MissingClause { step: i, id: ux })? as i64;
+            if let Some(n) = uses.get_mut(lit as usize - 1) { *n += 1 }
             *x = if *x < 0 {
-              if let Some((lit, j)) = last_neg { rats.push((lit, j, i)) }
-              last_neg = Some((lit, i));
+              if let Some((lit, j2)) = last_neg { rats.push((lit, j2, j)) }
+              last_neg = Some((lit, j));
               -lit
             } else {
               lit
@@ -1123,16 +1456,13 @@ fn trim(
           if let Some((lit, j)) = last_neg { rats.push((lit, j, is.len())) }
           if let [(_, start, _), ..] = *rats {
             rats.sort_by_key(|p| p.0);
-            for &i in &is[..start] { write!(lrat, " {}", i)? }
-            for (_, start, end) in rats.drain(..) {
-              for &i in &is[start..end] { write!(lrat, " {}", i)? }
-            }
-          } else {
-            for &i in &is { write!(lrat, " {}", i)? }
+            let mut sorted = is[..start].to_vec();
+            for (_, start, end) in rats.drain(..) { sorted.extend_from_slice(&is[start..end]) }
+            is = sorted;
           }
-          writeln!(lrat, " 0")?;
+          LRATStepRef::Add(k, AddStepRef::One(&ls), &is).write(lrat)?;
 
-          if done {return Ok(())}
+          if done {return Ok(uses)}
         }
       }
 
@@ -1148,8 +1478,8 @@ fn trim(
         let m = &mut map;
         let used_origs = &mut used_origs;
         let copies = &mut copies;
-        let mut delete = move |i| -> io::Result<()> {
-          let j = m.remove(&i).unwrap();
+        let mut delete = move |i| -> Result<()> {
+          let j = m.remove(&i).ok_or(FratError::MissingClause { step: n, id: i })?;
           let last_copy = match copies.get_mut(&j) {
             Some(val) if *val > 0 => { *val -= 1; false },
             _ => true,
@@ -1165,7 +1495,7 @@ fn trim(
         // Remove ID mapping to free space
         delete(i)?;
         // agglomerate additional del steps into this block
-        while let Some(&ElabStep::Del(i)) = bp.peek() {
+        while let Some(&Ok(ElabStep::Del(i))) = bp.peek() {
           bp.next();
           delete(i)?;
         }
@@ -1174,174 +1504,571 @@ fn trim(
     }
   }
 
-  panic!("did not find empty clause");
+  Err(FratError::NoEmptyClause)
 }
 
-pub fn main(args: impl Iterator<Item=String>) -> io::Result<()> {
-  let mut args = args.peekable();
-  let frat_path = args.next().expect("missing proof file");
-
-  let full = matches!(args.peek(), Some(s) if s == "--full") && { args.next(); true };
-
-  let (validate, all_hints) = match args.peek().as_ref().map(|s| &***s) {
-    Some("-s") => { args.next(); (true, false) }
-    Some("-ss") => { args.next(); (true, true) }
-    _ => (false, false)
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "elab", "\
+    FRATFILE [--full] [-s|-ss] [-m[NUM]] [--threads N] \
+      [DIMACSFILE [LRATFILE [--binary-lrat]] [-v] [-c] \
+      [--core COREFILE] [--usage USAGEFILE] [--tracecheck TRACEFILE] [--resolution RESFILE] \
+      [--grat LEMMAFILE PROOFFILE]]\n\n\
+    --threads N uses N workers (one per CPU if N is 0), each with its own copy of the\n\
+    clauses, so it needs about N + 1 times the memory");
+  let frat_path = args.arg()?;
+
+  let full = args.flag("--full");
+
+  let (validate, all_hints) = match args.choice(&["-s", "-ss"]) {
+    Some(0) => (true, false),
+    Some(_) => (true, true),
+    None => (false, false)
   };
 
-  let mut frat = File::open(&frat_path)?;
-
   let in_mem = match args.peek() {
     Some(arg) if arg.starts_with("-m") => {
-      let n = if let Ok(n) = arg[2..].parse() { n }
-      else { frat.metadata()?.len().saturating_mul(5) };
-      args.next();
+      let n = arg[2..].parse().ok();
+      args.arg()?;
       Some(n)
     }
     _ => None
   };
 
-  let dimacs = args.next();
-  let (lrat_file, verify, comments) = match args.next() {
-    Some(ref s) if s == "-v" => (None, true, false),
-    Some(lrat_file) => {
-      let verify = matches!(args.peek(), Some(s) if s == "-v") && { args.next(); true };
-      let comments = matches!(args.peek(), Some(s) if s == "-c") && { args.next(); true };
-      (Some(lrat_file), verify, comments)
+  let threads = args.parsed("--threads", cli::threads)?;
+
+  let dimacs = args.opt_arg();
+  let (lrat_file, binary, verify, comments) = match args.peek() {
+    Some("--core" | "--usage" | "--tracecheck" | "--resolution" | "--grat") => (None, false, false, false),
+    Some("-v") => { args.arg()?; (None, false, true, false) }
+    Some(_) => {
+      let lrat_file = args.arg()?;
+      let binary = args.flag("--binary-lrat");
+      let verify = args.flag("-v");
+      let comments = args.flag("-c");
+      (Some(lrat_file), binary, verify, comments)
     }
-    _ => (None, false, false),
+    _ => (None, false, false, false),
   };
 
-  if args.peek().is_some() {
-    eprintln!("\
-      Too many arguments to `frat-rs elab`. Expected:\n\n\
-      frat-rs elab FRATFILE [--full] [-s|-ss] [-m[NUM]] [DIMACSFILE [LRATFILE] [-v] [-c]]\n\n\
-      Note: options must appear in the specified order");
-    std::process::exit(2);
-  }
+  let core_file = args.value("--core")?;
+  let usage_file = args.value("--usage")?;
+  let trace_file = args.value("--tracecheck")?;
+  let res_file = args.value("--resolution")?;
+  let grat_files = match args.value("--grat")? {
+    Some(lemmas) => Some((lemmas, args.arg()?)),
+    None => None,
+  };
+  let out = Outputs {
+    lrat_file, binary, verify, comments, core_file, usage_file, trace_file, res_file, grat_files
+  };
+  args.finish()?;
+
+  // Standard input and pipes are spooled until the writer closes them
+  let regular = compress::is_regular(&frat_path)?;
+  if !regular { println!("reading proof...") }
+  let mut frat = compress::open_spooled(&frat_path, in_mem.is_some())?;
+  let in_mem = match in_mem {
+    Some(n) => Some(match n { Some(n) => n, None => frat.seek(SeekFrom::End(0))?.saturating_mul(5) }),
+    None => None
+  };
 
   let bin = detect_binary(&mut frat)?;
   println!("elaborating...");
   if let Some(temp_sz) = in_mem {
     let mut temp = ModeWriter(Bin, Vec::with_capacity(temp_sz as usize));
-    if bin { elab(Bin, full, validate, all_hints, frat, &mut temp)? }
-    else { elab(Ascii, full, validate, all_hints, frat, &mut temp)? }
+    run_elab(bin, full, validate, all_hints, threads, frat, &mut temp)?;
 
-    return finish(dimacs, lrat_file, verify, comments, VecBackParser(temp.1))
+    return finish(dimacs, out, VecBackParser(temp.1))
   } else {
-    let temp_path = format!("{}.temp", frat_path);
+    // The temporary file is kept for use with `refrat`, unless the input is not a file
+    let mut temp = if regular {
+      OpenOptions::new().read(true).write(true).create(true).truncate(true)
+        .open(format!("{}.temp", frat_path))?
+    } else {
+      tempfile::tempfile()?
+    };
     {
-      let mut temp_write = ModeWriter(Bin, BufWriter::new(File::create(&temp_path)?));
-      if bin { elab(Bin, full, validate, all_hints, frat, &mut temp_write)? }
-      else { elab(Ascii, full, validate, all_hints, frat, &mut temp_write)? };
+      let mut temp_write = ModeWriter(Bin, BufWriter::new(&mut temp));
+      run_elab(bin, full, validate, all_hints, threads, frat, &mut temp_write)?;
       temp_write.flush()?;
     }
 
-    let temp_read = BackParser::new(Bin, File::open(temp_path)?)?;
-    return finish(dimacs, lrat_file, verify, comments, temp_read)
+    let temp_read = BackParser::new(Bin, temp)?;
+    return finish(dimacs, out, temp_read)
+  }
+
+  fn run_elab(bin: bool, full: bool, validate: bool, all_hints: bool, threads: Option<usize>,
+    frat: impl Read + Seek, w: &mut impl ModeWrite<Bin>
+  ) -> Result<()> {
+    match threads {
+      None if bin => elab(Bin, full, validate, all_hints, frat, w),
+      None => elab(Ascii, full, validate, all_hints, frat, w),
+      Some(n) if bin => elab_par(Bin, full, validate, all_hints, n, frat, w),
+      Some(n) => elab_par(Ascii, full, validate, all_hints, n, frat, w),
+    }
+  }
+
+  /// The requested outputs of the trimming phase
+  struct Outputs {
+    lrat_file: Option<String>, binary: bool, verify: bool, comments: bool,
+    core_file: Option<String>, usage_file: Option<String>,
+    trace_file: Option<String>, res_file: Option<String>,
+    grat_files: Option<(String, String)>,
   }
 
-  fn finish(dimacs: Option<String>,
-    lrat_file: Option<String>, verify: bool, comments: bool,
-    temp_read: impl Iterator<Item=Segment>
-  ) -> io::Result<()> {
+  fn finish(dimacs: Option<String>, out: Outputs,
+    temp_read: impl Iterator<Item=Result<Segment>>
+  ) -> Result<()> {
+    let Outputs {
+      lrat_file, binary, verify, comments, core_file, usage_file, trace_file, res_file, grat_files
+    } = out;
     let dimacs = match dimacs {
-      Some(dimacs) => read_to_string(dimacs)?,
+      Some(dimacs) => compress::open(dimacs)?,
       None => return Ok(())
     };
     println!("parsing DIMACS...");
-    let (_vars, cnf) = parse_dimacs_map(dimacs.bytes(), |mut c| {dedup_vec(&mut c); c.into()});
+    let (vars, cnf) = read_dimacs_map(dimacs, |mut c| {dedup_vec(&mut c); c.into()})?;
     println!("trimming...");
-    if let Some(lrat_file) = lrat_file {
-      let mut lrat = BufWriter::new(File::create(&lrat_file)?);
-      trim(&cnf, temp_read, comments, &mut lrat)?;
-      lrat.flush()?;
-      if verify {
-        println!("verifying...");
-        let lrat = File::open(lrat_file)?;
-        check_lrat(Ascii, cnf, BufReader::new(lrat).bytes().map(Result::unwrap))?;
-        println!("VERIFIED");
+    let mut in_mem = None;
+    let uses = if let Some(lrat_file) = &lrat_file {
+      let lrat = BufWriter::new(File::create(lrat_file)?);
+      if binary {
+        let mut lrat = ModeWriter(Bin, lrat);
+        let uses = trim(&cnf, temp_read, comments, &mut lrat)?;
+        lrat.flush()?;
+        uses
+      } else {
+        let mut lrat = ModeWriter(Ascii, lrat);
+        let uses = trim(&cnf, temp_read, comments, &mut lrat)?;
+        lrat.flush()?;
+        uses
+      }
+    } else if verify || trace_file.is_some() || res_file.is_some() || grat_files.is_some() {
+      let mut lrat = ModeWriter(Ascii, vec![]);
+      let uses = trim(&cnf, temp_read, false, &mut lrat)?;
+      in_mem = Some(lrat.1);
+      uses
+    } else {
+      trim(&cnf, temp_read, false, &mut ModeWriter(Ascii, io::sink()))?
+    };
+
+    if let Some(core_file) = core_file {
+      let core = uses.iter().filter(|&&n| n > 0).count();
+      println!("{} of {} clauses in core", core, cnf.len());
+      let mut w = BufWriter::new(File::create(core_file)?);
+      writeln!(w, "p cnf {} {}", vars, core)?;
+      for (c, _) in cnf.iter().zip(&uses).filter(|p| *p.1 > 0) {
+        for lit in &**c { write!(w, "{} ", lit)? }
+        writeln!(w, "0")?;
       }
-    } else if verify {
+      w.flush()?;
+    }
+    if let Some(usage_file) = usage_file {
+      let mut w = BufWriter::new(File::create(usage_file)?);
+      for (j, n) in uses.iter().enumerate() { writeln!(w, "{} {}", j + 1, n)? }
+      w.flush()?;
+    }
+
+    // The remaining passes read the LRAT proof back, from memory or from the file
+    type Bytes<'a> = Box<dyn Iterator<Item=io::Result<u8>> + 'a>;
+    let read_lrat = || -> io::Result<(bool, Bytes<'_>)> {
+      Ok(match (&in_mem, &lrat_file) {
+        (Some(lrat), _) => (false, Box::new(lrat.iter().map(|&c| Ok(c)))),
+        (None, Some(f)) => (binary, Box::new(BufReader::new(File::open(f)?).bytes())),
+        (None, None) => unreachable!(),
+      })
+    };
+
+    if let Some(trace_file) = trace_file {
+      println!("writing TraceCheck trace...");
+      let mut w = BufWriter::new(File::create(trace_file)?);
+      let (bin, lrat) = read_lrat()?;
+      if bin { write_tracecheck(&cnf, Bin, lrat, &mut w)? }
+      else { write_tracecheck(&cnf, Ascii, lrat, &mut w)? }
+      w.flush()?;
+    }
+
+    if let Some(res_file) = res_file {
+      println!("writing resolution proof...");
+      let mut w = BufWriter::new(File::create(res_file)?);
+      let (bin, lrat) = read_lrat()?;
+      if bin { write_resolution(&cnf, Bin, lrat, &mut w)? }
+      else { write_resolution(&cnf, Ascii, lrat, &mut w)? }
+      w.flush()?;
+    }
+
+    if let Some((lemma_file, proof_file)) = grat_files {
+      println!("writing GRAT certificate...");
+      let mut lemmas = BufWriter::new(File::create(lemma_file)?);
+      let mut proof = BufWriter::new(File::create(proof_file)?);
+      let (bin, lrat) = read_lrat()?;
+      if bin { write_grat(&cnf, Bin, lrat, &mut lemmas, &mut proof)? }
+      else { write_grat(&cnf, Ascii, lrat, &mut lemmas, &mut proof)? }
+      lemmas.flush()?;
+      proof.flush()?;
+    }
+
+    if verify {
       println!("verifying...");
-      let mut lrat = vec![];
-      trim(&cnf, temp_read, false, &mut lrat)?;
-      check_lrat(Ascii, cnf, lrat.into_iter())?;
+      let (bin, lrat) = read_lrat()?;
+      if bin { check_lrat(Bin, IdOrder::Strict, false, cnf, lrat)? }
+      else { check_lrat(Ascii, IdOrder::Strict, false, cnf, lrat)? }
       println!("VERIFIED");
-    } else {
-      trim(&cnf, temp_read, false, &mut io::sink())?;
     }
     Ok(())
   }
 }
 
-fn check_lrat(mode: impl Mode, cnf: Vec<Box<[i64]>>, lrat: impl Iterator<Item=u8>) -> io::Result<()> {
-  let lp = LRATParser::from(mode, lrat);
+/// Which clause IDs [`check_lrat`] accepts on an addition step.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IdOrder {
+  /// IDs of added clauses must be increasing, and deletion steps can only refer to
+  /// the last added clause.
+  Strict,
+  /// Any ID that has never been used before can be added, and deletions can appear
+  /// anywhere. This accepts proofs with sparse or interleaved IDs.
+  #[default]
+  Fresh,
+  /// Like `Fresh`, but an ID can also be reused once its clause has been deleted.
+  Reuse,
+}
+
+/// Whether an LRAT file starting with `head` is binary, judging by its first step:
+/// binary LRAT starts with an `a` or `d` line, ASCII LRAT with a step ID.
+/// ASCII comment lines and whitespace are skipped. Returns `None` if there is no step in `head`.
+fn lrat_head_binary(head: &[u8]) -> Option<bool> {
+  let mut it = head.iter();
+  loop {
+    match *it.next()? {
+      c if c.is_ascii_whitespace() => {}
+      b'c' => { it.find(|&&c| c == b'\n')?; }
+      c => return Some(matches!(c, b'a' | b'd')),
+    }
+  }
+}
+
+/// Check that step `i` of an LRAT proof is a RUP step which only depends on the clauses
+/// of its hint: it is not a PR step, has no RAT hints, and has an empty hint only if it
+/// is a tautology. Such steps stay valid when the other clauses are missing.
+pub fn check_rup_only(i: u64, add: &AddStep, hint: &[i64]) -> Result<()> {
+  let what = match add.parse() {
+    AddKind::PR(..) => "PR steps",
+    _ if hint.iter().any(|&h| h < 0) => "RAT steps",
+    AddKind::RAT(ls) if hint.is_empty() && !ls.iter().any(|&x| ls.contains(&-x)) =>
+      "steps without hints",
+    AddKind::RAT(_) => return Ok(()),
+  };
+  Err(FratError::Malformed(format!("at {}: {} depend on the whole clause database", i, what)))
+}
+
+/// Check the LRAT proof `lrat` of `cnf`. If `rup_only` is set, every step must pass
+/// [`check_rup_only`].
+pub fn check_lrat(mode: impl Mode, ids: IdOrder, rup_only: bool, cnf: Vec<Box<[i64]>>,
+  lrat: impl Iterator<Item=io::Result<u8>>
+) -> Result<()> {
+  let lp = LRATParser::from(mode, lrat).after(cnf.len() as u64);
   let mut k = 0;
-  let ctx = &mut Context::default();
-  ctx.validate_hints = true;
-  ctx.all_hints = true;
-  ctx.lrat = true;
-  ctx.full = true;
+  // All IDs that have ever been live, needed to reject reuse in `Fresh` mode.
+  let mut used = HashSet::default();
+  let ctx = &mut Context::for_lrat();
   let hint = &mut RatHint::default();
 
   for c in cnf {
     k += 1;
     ctx.step = k;
     // eprintln!("{}: {:?}", k, c);
-    ctx.insert(k, true, c);
+    ctx.insert(k, true, c)?;
+    if ids == IdOrder::Fresh { used.insert(k); }
   }
 
-  for (i, s) in lp {
+  for (n, s) in (1..).zip(lp) {
+    let (i, s) = s?;
     ctx.step = i;
     // eprintln!("{}: {:?}", i, s);
     match s {
       LRATStep::Comment(_) => {}
 
       LRATStep::Add(add, p) => {
-        assert!(i > k, "out-of-order LRAT proofs not supported");
-        k = i;
-        let add = add.parse_into(|kind| {
+        if rup_only { check_rup_only(i, &add, &p)? }
+        match ids {
+          IdOrder::Strict => if i <= k { return Err(FratError::OutOfOrder { step: i }) },
+          IdOrder::Fresh => if !used.insert(i) { return Err(FratError::ReusedId { step: n, id: i }) },
+          IdOrder::Reuse => if ctx.names.contains_key(&i) {
+            return Err(FratError::DuplicateClause { step: n, id: i })
+          },
+        }
+        k = k.max(i);
+        let (res, add) = add.parse_into(|kind| ctx.run_lrat_step(kind, &p, hint));
+        res?;
+        if add.is_empty() { return Ok(()) }
+        ctx.insert_no_reserve(i, true, add.into())?;
+      }
+
+      LRATStep::Del(ls) => {
+        if ids == IdOrder::Strict {
+          if i < k { return Err(FratError::OutOfOrder { step: i }) }
+          k = i;
+        }
+        for c in ls { ctx.remove(c)?; }
+      }
+    }
+  }
+
+  Err(FratError::NoEmptyClause)
+}
+
+/// A step of an LRAT proof whose hint consists only of clauses (no RAT hints), which can
+/// be checked by [`check_chain`] without access to the rest of the clause database.
+struct ChainJob {
+  /// The position of the step in the proof, used to report the first failing step
+  seq: u64,
+  step: u64,
+  max_var: i64,
+  lemma: Arc<[i64]>,
+  hint: Vec<(u64, Arc<[i64]>)>,
+}
+
+/// Check a [`ChainJob`]. This is `propagate_hint` specialized to LRAT mode, where there
+/// are no units carried over between steps, so that it gives the same results.
+/// `va` holds the true literals, and is cleared again before returning.
+fn check_chain(va: &mut MidVec<bool>, trail: &mut Vec<i64>, job: &ChainJob) -> Result<()> {
+  va.reserve_to(job.max_var);
+  let res = (|| {
+    for &x in &*job.lemma {
+      // The lemma is a tautology
+      if va[x] { return Ok(()) }
+      if !va[-x] { va[-x] = true; trail.push(-x) }
+    }
+    for (name, cl) in &job.hint {
+      if cl.iter().any(|&l| va[l]) { continue }
+      let mut unit = None;
+      for &l in &**cl {
+        if !va[-l] {
+          if unit.is_some() {
+            return Err(FratError::verify(job.step, &job.lemma, VerifyError::NotUnit(*name)))
+          }
+          unit = Some(l)
+        }
+      }
+      match unit {
+        None => return Ok(()),
+        Some(l) => { va[l] = true; trail.push(l) }
+      }
+    }
+    Err(FratError::verify(job.step, &job.lemma, VerifyError::UnitPropStuck))
+  })();
+  for l in trail.drain(..) { va[l] = false }
+  res
+}
+
+/// The number of steps sent to a worker of [`check_lrat_par`] at once.
+const CHAIN_BATCH: usize = 256;
+
+/// Send a batch of [`ChainJob`]s to the workers of [`check_lrat_par`].
+fn send_chains(tx: &mpsc::SyncSender<Vec<ChainJob>>, batch: &mut Vec<ChainJob>) {
+  // This only fails if all workers have panicked, which is reported when they are joined
+  let _ = tx.send(mem::replace(batch, Vec::with_capacity(CHAIN_BATCH)));
+}
+
+/// The producer of [`check_lrat_par`], which checks everything except the [`ChainJob`]s
+/// collected in `batch` and sent to `tx`. An error is returned with the position of the
+/// failing step, and `Ok` means that the empty clause was reached or a worker has failed.
+/// `failed` is the position of the first step which a worker found to fail, or `u64::MAX`.
+#[allow(clippy::too_many_arguments)]
+fn lrat_producer(mode: impl Mode, ids: IdOrder, rup_only: bool, cnf: Vec<Box<[i64]>>,
+  lrat: impl Iterator<Item=io::Result<u8>>,
+  tx: &mpsc::SyncSender<Vec<ChainJob>>, batch: &mut Vec<ChainJob>, failed: &AtomicU64
+) -> std::result::Result<(), (u64, FratError)> {
+  let lp = LRATParser::from(mode, lrat).after(cnf.len() as u64);
+  let mut k = 0;
+  // All IDs that have ever been live, needed to reject reuse in `Fresh` mode.
+  let mut used = HashSet::default();
+  let mut max_var = 0;
+  let mut db: HashMap<u64, Arc<[i64]>> = HashMap::default();
+  let mut ctx: Option<Context> = None;
+  let hint = &mut RatHint::default();
+
+  for c in cnf {
+    k += 1;
+    max_var = c.iter().fold(max_var, |m, l| m.max(l.abs()));
+    db.insert(k, c.into());
+    if ids == IdOrder::Fresh { used.insert(k); }
+  }
+
+  for (seq, s) in (0..).zip(lp) {
+    if failed.load(Ordering::Relaxed) != u64::MAX { return Ok(()) }
+    let (i, s) = s.map_err(|e| (seq, e))?;
+    match s {
+      LRATStep::Comment(_) => {}
+
+      LRATStep::Add(add, p) => {
+        if rup_only { check_rup_only(i, &add, &p).map_err(|e| (seq, e))? }
+        match ids {
+          IdOrder::Strict => if i <= k { return Err((seq, FratError::OutOfOrder { step: i })) },
+          IdOrder::Fresh => if !used.insert(i) {
+            return Err((seq, FratError::ReusedId { step: seq + 1, id: i }))
+          },
+          IdOrder::Reuse => if db.contains_key(&i) {
+            return Err((seq, FratError::DuplicateClause { step: seq + 1, id: i }))
+          },
+        }
+        k = k.max(i);
+        let (res, add) = add.parse_into(|kind| {
           let ls = kind.lemma();
-          let wit = kind.witness();
-          ctx.reserve(ls);
-          // eprintln!("{}: {:?} {:?}", k, ls, p);
-          if let Some(start) = p.iter().position(|&i| i < 0).filter(|_| !ls.is_empty()) {
-            let (init, rest) = p.split_at(start);
-            ctx.run_step(ls, ls.first(), wit, Some(init), rest.split_first(), hint);
+          max_var = ls.iter().fold(max_var, |m, l| m.max(l.abs()));
+          if !p.is_empty() && p.iter().all(|&h| h > 0) {
+            match p.iter().map(|&h| db.get(&(h as u64)).map(|c| (h as u64, c.clone())).ok_or(h))
+              .collect::<std::result::Result<Vec<_>, _>>() {
+              Ok(hint) => Ok(Some(hint)),
+              // As in `propagate_hint`, a tautology is accepted before the hint is read
+              Err(_) if ls.iter().any(|&x| ls.contains(&-x)) => Ok(None),
+              Err(h) => Err(FratError::MissingClause { step: i, id: h as u64 }),
+            }
           } else {
-            ctx.run_step(ls, ls.first(), wit, Some(&p), None, hint);
+            // A copy of the live clauses, kept up to date from here on
+            let ctx = ctx.get_or_insert_with(|| {
+              let mut ctx = Context::for_lrat();
+              for (&j, c) in &db { ctx.insert(j, true, c.to_vec().into()).expect("duplicate clause") }
+              ctx
+            });
+            ctx.step = i;
+            ctx.run_lrat_step(kind, &p, hint).map(|_| None)
           }
-        }).1;
-        if add.is_empty() { return Ok(()) }
-        ctx.insert_no_reserve(i, true, add.into());
+        });
+        let lemma: Arc<[i64]> = add.into();
+        if let Some(hint) = res.map_err(|e| (seq, e))? {
+          batch.push(ChainJob { seq, step: i, max_var, lemma: lemma.clone(), hint });
+        }
+        if lemma.is_empty() { return Ok(()) }
+        if let Some(ctx) = &mut ctx {
+          ctx.step = i;
+          ctx.insert(i, true, lemma.to_vec().into()).map_err(|e| (seq, e))?
+        }
+        db.insert(i, lemma);
+        if batch.len() >= CHAIN_BATCH { send_chains(tx, batch) }
       }
 
       LRATStep::Del(ls) => {
-        assert!(i >= k, "out-of-order LRAT proofs not supported");
-        k = i;
-        for c in ls { ctx.remove(c.try_into().unwrap()); }
+        if ids == IdOrder::Strict {
+          if i < k { return Err((seq, FratError::OutOfOrder { step: i })) }
+          k = i;
+        }
+        for c in ls {
+          db.remove(&c).ok_or((seq, FratError::MissingClause { step: i, id: c }))?;
+          if let Some(ctx) = &mut ctx {
+            ctx.step = i;
+            ctx.remove(c).map_err(|e| (seq, e))?;
+          }
+        }
       }
     }
   }
 
-  panic!("did not find empty clause")
+  Err((u64::MAX, FratError::NoEmptyClause))
+}
+
+/// A multi-threaded version of [`check_lrat`], which gives the same verdict.
+///
+/// The calling thread parses the proof and keeps the clause database, storing clauses
+/// behind an [`Arc`] so that the steps with a plain unit propagation chain can be sent to
+/// `threads` worker threads together with the clauses of their hints. The other steps
+/// (RAT and PR steps, and steps without hints) depend on the whole database, and are
+/// checked by the calling thread, which builds a [`Context`] the first time it needs one.
+/// From then on every clause is stored both in the database and in the context, so a
+/// proof with an early RAT step needs about twice the memory of [`check_lrat`].
+/// If several steps fail, the error of the first one in the proof is returned.
+pub fn check_lrat_par(mode: impl Mode, ids: IdOrder, rup_only: bool, cnf: Vec<Box<[i64]>>,
+  lrat: impl Iterator<Item=io::Result<u8>>, threads: usize
+) -> Result<()> {
+  let first_err = Mutex::new(None::<(u64, FratError)>);
+  let failed = AtomicU64::new(u64::MAX);
+  let (tx, rx) = mpsc::sync_channel::<Vec<ChainJob>>(4 * threads);
+  let rx = Mutex::new(rx);
+  let res = thread::scope(|s| {
+    for _ in 0..threads {
+      s.spawn(|| {
+        let va = &mut MidVec::default();
+        let trail = &mut vec![];
+        while let Ok(batch) = { let rx = rx.lock().unwrap(); rx.recv() } {
+          for job in &batch {
+            // Steps after a failing step do not affect the verdict
+            if job.seq > failed.load(Ordering::Relaxed) { break }
+            if let Err(e) = check_chain(va, trail, job) {
+              let mut first_err = first_err.lock().unwrap();
+              if first_err.as_ref().is_none_or(|&(seq, _)| job.seq < seq) {
+                *first_err = Some((job.seq, e))
+              }
+              failed.fetch_min(job.seq, Ordering::Relaxed);
+              break
+            }
+          }
+        }
+      });
+    }
+    let mut batch = Vec::with_capacity(CHAIN_BATCH);
+    let res = lrat_producer(mode, ids, rup_only, cnf, lrat, &tx, &mut batch, &failed);
+    // The steps before a failure in the producer still need to be checked
+    send_chains(&tx, &mut batch);
+    drop(tx);
+    res
+  });
+  // A failing step is checked before the producer can fail on the same step
+  match (res, first_err.into_inner().unwrap()) {
+    (Err((s, _)), Some((t, e))) if t <= s => Err(e),
+    (Err((_, e)), _) | (Ok(()), Some((_, e))) => Err(e),
+    (Ok(()), None) => Ok(()),
+  }
 }
 
-pub fn lratchk(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-  let dimacs = args.next().expect("missing input file");
-  let (_vars, cnf) = parse_dimacs(read_to_string(dimacs)?.bytes());
-  let lrat = File::open(args.next().expect("missing proof file"))?;
-  check_lrat(Ascii, cnf, BufReader::new(lrat).bytes().map(Result::unwrap))
+pub fn lratchk(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "lratchk",
+    "DIMACSFILE LRATFILE [--strict|--reuse-ids] [--partial] [--threads N]");
+  let dimacs = args.arg()?;
+  let lrat_path = args.arg()?;
+  let ids = match args.choice(&["--strict", "--reuse-ids"]) {
+    Some(0) => IdOrder::Strict,
+    Some(_) => IdOrder::Reuse,
+    None => IdOrder::Fresh
+  };
+  let partial = args.flag("--partial");
+  let threads = args.parsed("--threads", cli::threads)?;
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  // The end of the file decides the mode, as for FRAT files, but if the start disagrees
+  // (binary LRAT starts with an `a` or `d` line, ASCII LRAT with a step ID)
+  // the file is corrupt, and would only produce a confusing parse error
+  let (bin, lrat) = compress::open_detect(lrat_path)?;
+  if let Some(head_bin) = lrat_head_binary(compress::head(&lrat)).filter(|&b| b != bin) {
+    return Err(FratError::Malformed(if head_bin {
+      "LRAT file starts as binary but does not end with a 0 byte; \
+        it is truncated or mixes binary and ASCII LRAT".into()
+    } else {
+      "LRAT file starts as ASCII but ends with a 0 byte; it mixes ASCII and binary LRAT".into()
+    }))
+  }
+  let lrat = BufReader::new(lrat).bytes();
+  let res = match threads {
+    None if bin => check_lrat(Bin, ids, partial, cnf, lrat),
+    None => check_lrat(Ascii, ids, partial, cnf, lrat),
+    Some(n) if bin => check_lrat_par(Bin, ids, partial, cnf, lrat, n),
+    Some(n) => check_lrat_par(Ascii, ids, partial, cnf, lrat, n),
+  };
+  match res {
+    // A segment produced by `split` need not derive the empty clause, and is only
+    // checked against part of the clause database, which is sound for RUP steps
+    Err(FratError::NoEmptyClause) if partial => Ok(()),
+    res => res,
+  }
 }
 
-fn refrat_pass(elab: File, w: &mut impl ModeWrite) -> io::Result<()> {
+pub fn refrat_pass<M>(elab: File, w: &mut impl ModeWrite<M>) -> Result<()>
+where for<'a> StepRef<'a>: Serialize<M>, for<'a> ElabStepRef<'a>: Serialize<M> {
 
   let mut ctx: HashMap<u64, Vec<i64>> = HashMap::default();
-  for s in ElabStepIter(BackParser::new(Bin, elab)?) {
+  for (n, s) in (1..).zip(ElabStepIter(BackParser::new(Bin, elab)?)) {
     // eprintln!("-> {:?}", s);
 
-    match s {
+    match s? {
       ElabStep::Comment(s) => ElabStep::Comment(s).write(w)?,
 
       ElabStep::Orig(i, ls) => {
@@ -1364,7 +2091,8 @@ fn refrat_pass(elab: File, w: &mut impl ModeWrite) -> io::Result<()> {
       }
 
       ElabStep::Del(i) => {
-        Step::Del(i, ctx.remove(&i).unwrap()).write(w)?;
+        let ls = ctx.remove(&i).ok_or(FratError::MissingClause { step: n, id: i })?;
+        Step::Del(i, ls).write(w)?;
       }
     }
   }
@@ -1374,10 +2102,121 @@ fn refrat_pass(elab: File, w: &mut impl ModeWrite) -> io::Result<()> {
   Ok(())
 }
 
-pub fn refrat(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-  let elab_path = args.next().expect("missing elab file");
-  let frat_path = args.next().expect("missing frat file");
-  let w = &mut ModeWriter(DefaultMode, BufWriter::new(File::create(&frat_path)?));
+pub fn refrat(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "refrat", "ELABFILE FRATFILE [--ascii|--binary]");
+  let (elab_path, frat_path) = (args.arg()?, args.arg()?);
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  args.finish()?;
+  let w = &mut ModeWriter(out_bin, BufWriter::new(File::create(&frat_path)?));
   refrat_pass(File::open(elab_path)?, w)?;
-  w.flush()
+  Ok(w.flush()?)
+}
+
+/// Elaborate the FRAT proof at `frat_path` in memory, and trim it against `cnf`,
+/// returning the resulting binary LRAT proof.
+pub fn elab_to_lrat(frat_path: impl AsRef<std::path::Path>, cnf: &[Box<[i64]>]) -> Result<Vec<u8>> {
+  let mut frat = compress::open_seekable(frat_path)?;
+  let mut temp = ModeWriter(Bin, vec![]);
+  if detect_binary(&mut frat)? { elab(Bin, false, false, false, frat, &mut temp)? }
+  else { elab(Ascii, false, false, false, frat, &mut temp)? }
+  let mut lrat = ModeWriter(Bin, vec![]);
+  trim(cnf, VecBackParser(temp.1), false, &mut lrat)?;
+  Ok(lrat.1)
+}
+
+/// Check by unit propagation that `cnf` is unsatisfiable, using `lemmas` as candidate
+/// RUP steps. Lemmas that are not RUP at their position are skipped. Returns the indices
+/// of the accepted lemmas, ending with an empty clause, or `None` if no contradiction is found.
+/// This replays all the lemmas, so [`mus`], which calls it once per core clause, takes
+/// time proportional to the size of the core times the size of the proof.
+fn rup_refute<'a>(cnf: impl Iterator<Item=&'a [i64]>, lemmas: &[Box<[i64]>]) -> Result<Option<Vec<usize>>> {
+  let ctx = &mut Context::default();
+  let mut hint = Hint::default();
+  let mut k = 0;
+  for c in cnf { k += 1; ctx.insert(k, false, c.into())? }
+  let mut accepted = vec![];
+  for (j, ls) in lemmas.iter().enumerate() {
+    if ls.iter().any(|&l| ls.contains(&-l)) { continue }
+    ctx.step = k + 1;
+    hint.steps.clear();
+    let rup = ctx.build_step(ls, None, &mut hint, |_| None)?;
+    if rup { ctx.clear_marks(&mut hint) }
+    ctx.va.clear_hyps();
+    if rup {
+      accepted.push(j);
+      if ls.is_empty() { return Ok(Some(accepted)) }
+      k += 1;
+      ctx.insert(k, false, ls.clone())?;
+    }
+  }
+  Ok(None)
+}
+
+/// Shrink the core of a FRAT proof to a subset which is irredundant with respect to
+/// the proof lemmas: dropping any clause leaves a set which unit propagation over
+/// the lemmas does not refute. This is not necessarily a minimal unsatisfiable subset.
+pub fn mus(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "mus", "DIMACSFILE FRATFILE [COREFILE [LRATFILE [-v]]]");
+  let dimacs = args.arg()?;
+  let frat_path = args.arg()?;
+  let core_file = args.opt_arg();
+  let lrat_file = args.opt_arg();
+  let verify = args.flag("-v");
+  args.finish()?;
+
+  println!("parsing DIMACS...");
+  let (vars, cnf) = read_dimacs_map(compress::open(dimacs)?, |mut c| {dedup_vec(&mut c); c.into()})?;
+  let mut frat = compress::open_seekable(&frat_path)?;
+  let bin = detect_binary(&mut frat)?;
+  println!("elaborating...");
+  let mut temp = ModeWriter(Bin, vec![]);
+  if bin { elab(Bin, false, false, false, frat, &mut temp)? }
+  else { elab(Ascii, false, false, false, frat, &mut temp)? }
+  let uses = trim(&cnf, VecBackParser(temp.1.clone()), false, &mut ModeWriter(Ascii, io::sink()))?;
+  let mut core: Vec<_> = cnf.iter().zip(&uses).filter(|p| *p.1 > 0).map(|p| p.0.clone()).collect();
+  let mut lemmas: Vec<Box<[i64]>> = vec![];
+  for s in ElabStepIter(VecBackParser(temp.1)) {
+    if let ElabStep::Add(_, ls, _) = s? { lemmas.push(ls.parse().lemma().into()) }
+  }
+  if !lemmas.last().is_some_and(|ls| ls.is_empty()) { lemmas.push(Box::new([])) }
+  println!("{} of {} clauses in core, {} lemmas", core.len(), cnf.len(), lemmas.len());
+
+  println!("minimizing...");
+  let mut proof = rup_refute(core.iter().map(|c| &**c), &lemmas)?.ok_or_else(||
+    FratError::Malformed("the core is not refuted by unit propagation over the proof lemmas".into()))?;
+  let mut j = 0;
+  while j < core.len() {
+    let rest = core[..j].iter().chain(&core[j+1..]).map(|c| &**c);
+    if let Some(p) = rup_refute(rest, &lemmas)? { core.remove(j); proof = p } else { j += 1 }
+  }
+  println!("{} clauses in core, irredundant with respect to the proof lemmas", core.len());
+
+  if let Some(core_file) = core_file {
+    let mut w = BufWriter::new(File::create(core_file)?);
+    writeln!(w, "p cnf {} {}", vars, core.len())?;
+    for c in &core {
+      for lit in &**c { write!(w, "{} ", lit)? }
+      writeln!(w, "0")?;
+    }
+    w.flush()?;
+  }
+
+  if let Some(lrat_file) = lrat_file {
+    // Reelaborate the accepted lemmas against the minimal core to get the LRAT proof
+    let mut frat = FratWriter::new(Bin, vec![]);
+    for c in &core { frat.orig(c)?; }
+    for &j in &proof { frat.add(&lemmas[j], None, None)?; }
+    let mut temp = ModeWriter(Bin, vec![]);
+    elab(Bin, false, false, false, io::Cursor::new(frat.finish()?), &mut temp)?;
+    let mut lrat = ModeWriter(Ascii, BufWriter::new(File::create(&lrat_file)?));
+    trim(&core, VecBackParser(temp.1), false, &mut lrat)?;
+    lrat.flush()?;
+    if verify {
+      println!("verifying...");
+      let lrat = BufReader::new(File::open(lrat_file)?).bytes();
+      check_lrat(Ascii, IdOrder::Strict, false, core, lrat)?;
+      println!("VERIFIED");
+    }
+  }
+  Ok(())
 }
@@ -0,0 +1,236 @@
+use std::{fmt, io};
+
+/// A parse error, without position information. The parser functions in
+/// [`Mode`](crate::parser::Mode) return this, and it is paired with a byte offset
+/// to make a [`FratError::Parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+  /// Expected a step keyword, found this character (`None` means end of input)
+  BadKeyword(Option<u8>),
+  /// Expected a digit, found this character
+  BadDigit(u8),
+  /// The input ended in the middle of a step
+  UnexpectedEof,
+  /// A number does not fit in 64 bits
+  Overflow,
+  /// A relocation step has an odd number of IDs
+  OddReloc,
+  /// A `t` step should contain exactly one number
+  BadTodo,
+  /// The step has extra characters after the terminating 0
+  TrailingChars,
+  /// A binary step did not end with a 0 byte
+  MissingTerminator,
+  /// A comment is not valid UTF-8
+  NonUtf8,
+  /// A clause ID in a binary LRAT file is not positive
+  BadId,
+  /// A DIMACS file has a bad header line
+  DimacsHeader,
+  /// A DIMACS file contains an unknown keyword
+  DimacsKeyword,
+  /// A DIMACS file contains an invalid character
+  DimacsToken(u8),
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match *self {
+      ParseError::BadKeyword(Some(k)) => write!(f, "bad step {:?}", k as char),
+      ParseError::BadKeyword(None) => write!(f, "bad step None"),
+      ParseError::BadDigit(c) => write!(f, "expected a digit, found {:?}", c as char),
+      ParseError::UnexpectedEof => write!(f, "unexpected end of input"),
+      ParseError::Overflow => write!(f, "number too large"),
+      ParseError::OddReloc => write!(f, "odd relocation"),
+      ParseError::BadTodo => write!(f, "expected 1 number in todo step"),
+      ParseError::TrailingChars => write!(f, "segment has trailing characters"),
+      ParseError::MissingTerminator => write!(f, "expected 0 byte"),
+      ParseError::NonUtf8 => write!(f, "non-utf8 comment"),
+      ParseError::BadId => write!(f, "clause IDs must be positive"),
+      ParseError::DimacsHeader => write!(f, "expected 'p cnf VARS CLAUSES'"),
+      ParseError::DimacsKeyword => write!(f, "unknown keyword"),
+      ParseError::DimacsToken(c) => write!(f, "invalid token start {:?}", c as char),
+    }
+  }
+}
+
+/// The reason a proof step failed to check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+  /// The hint refers to this clause, which is not unit at that point
+  NotUnit(u64),
+  /// The step has no hint, and hints are required
+  MissingProof,
+  /// The hint does not contain the RAT resolvent with this clause, and hints are required
+  MissingRatHint(u64),
+  /// Unit propagation did not derive a contradiction
+  UnitPropStuck,
+  /// Unit propagation did not derive a contradiction for the resolvent with this clause
+  RatResolventStuck(u64),
+  /// This witness literal is the complement of a unit in the context
+  WitnessConflict(i64),
+  /// The RAT hints in an LRAT step are not sorted
+  UnsortedRat,
+}
+
+impl fmt::Display for VerifyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match *self {
+      VerifyError::NotUnit(c) => write!(f, "hint clause {} is not unit", c),
+      VerifyError::MissingProof => write!(f, "proof missing"),
+      VerifyError::MissingRatHint(c) => write!(f, "RAT resolvent with {} missing", c),
+      VerifyError::UnitPropStuck => write!(f, "unit propagation stuck, failed to prove empty clause"),
+      VerifyError::RatResolventStuck(c) =>
+        write!(f, "unit propagation stuck, cannot resolve with clause {}", c),
+      VerifyError::WitnessConflict(lit) =>
+        write!(f, "witness literal {} is complement of a unit clause", lit),
+      VerifyError::UnsortedRat => write!(f, "RAT steps must be sorted"),
+    }
+  }
+}
+
+/// The class of a [`FratError`], which determines the exit code of the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+  /// The proof was read successfully but is not valid
+  Verify,
+  /// The input could not be parsed
+  Parse,
+  /// The input parsed, but does not make sense as a proof
+  /// (missing or duplicate clause IDs and the like)
+  Semantic,
+  /// An I/O error
+  Io,
+  /// The command line arguments are wrong
+  Usage,
+}
+
+impl ErrorClass {
+  /// The process exit code for this error class.
+  pub fn exit_code(self) -> i32 {
+    match self {
+      ErrorClass::Usage => 2,
+      ErrorClass::Verify => 1,
+      ErrorClass::Parse => 3,
+      ErrorClass::Semantic => 4,
+      ErrorClass::Io => 5,
+    }
+  }
+}
+
+/// The error type for the proof pipeline.
+#[derive(Debug)]
+pub enum FratError {
+  /// An I/O error
+  Io(io::Error),
+  /// A parse error at the given byte offset in the input
+  Parse { pos: usize, err: ParseError },
+  /// Step `step` refers to clause `id`, which does not exist
+  MissingClause { step: u64, id: u64 },
+  /// Step `step` introduces clause `id`, which already exists
+  DuplicateClause { step: u64, id: u64 },
+  /// The clause added at `step` is deleted later with different literals
+  ClauseMismatch { step: u64, added: Vec<i64>, deleted: Vec<i64> },
+  /// An `o` step refers to a clause which is not in the CNF
+  OrigNotInCnf { step: u64, clause: Vec<i64> },
+  /// A DRAT deletion refers to a clause which does not exist
+  DeletedNonexistent(Vec<i64>),
+  /// An `f` step at `step` appears before the non-`f` step `later`
+  FinalNotAtEnd { step: u64, later: u64 },
+  /// An LRAT step is out of order
+  OutOfOrder { step: u64 },
+  /// Step `step` adds clause `id`, but this ID was used before
+  ReusedId { step: u64, id: u64 },
+  /// The proof is structurally malformed
+  Malformed(String),
+  /// The empty clause was never finalized
+  EmptyClauseNotFinalized,
+  /// The proof ended without deriving the empty clause
+  NoEmptyClause,
+  /// Step `step`, adding `clause`, failed to check
+  Verify { step: u64, clause: Vec<i64>, err: VerifyError },
+  /// A worker thread panicked, with the given message
+  Worker(String),
+  /// The command line arguments do not match the usage message, which is included
+  Usage(String),
+}
+
+pub type Result<T, E = FratError> = std::result::Result<T, E>;
+
+impl FratError {
+  /// Attach a byte offset to a [`ParseError`].
+  pub fn parse(pos: usize, err: ParseError) -> Self { FratError::Parse { pos, err } }
+
+  pub fn verify(step: u64, clause: &[i64], err: VerifyError) -> Self {
+    FratError::Verify { step, clause: clause.into(), err }
+  }
+
+  pub fn class(&self) -> ErrorClass {
+    match self {
+      // Like an I/O error, this is not a problem with the proof
+      FratError::Io(_) | FratError::Worker(_) => ErrorClass::Io,
+      FratError::Parse {..} => ErrorClass::Parse,
+      FratError::MissingClause {..} |
+      FratError::DuplicateClause {..} |
+      FratError::ClauseMismatch {..} |
+      FratError::OrigNotInCnf {..} |
+      FratError::DeletedNonexistent(_) |
+      FratError::FinalNotAtEnd {..} |
+      FratError::OutOfOrder {..} |
+      FratError::ReusedId {..} |
+      FratError::Malformed(_) |
+      FratError::EmptyClauseNotFinalized => ErrorClass::Semantic,
+      FratError::NoEmptyClause |
+      FratError::Verify {..} => ErrorClass::Verify,
+      FratError::Usage(_) => ErrorClass::Usage,
+    }
+  }
+
+  pub fn exit_code(&self) -> i32 { self.class().exit_code() }
+}
+
+impl fmt::Display for FratError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FratError::Io(e) => write!(f, "{}", e),
+      FratError::Parse { pos, err } => write!(f, "parse error at byte {}: {}", pos, err),
+      FratError::MissingClause { step, id } =>
+        write!(f, "at {}: Clause {} to be accessed does not exist", step, id),
+      FratError::DuplicateClause { step, id } =>
+        write!(f, "at {}: Clause {} to be inserted already exists", step, id),
+      FratError::ClauseMismatch { step, added, deleted } =>
+        write!(f, "at {}: Clause {:?} added here will later be deleted as {:?}",
+          step, added, deleted),
+      FratError::OrigNotInCnf { step, clause } =>
+        write!(f, "Orig step {} refers to nonexistent clause {:?}", step, clause),
+      FratError::DeletedNonexistent(clause) => write!(f, "deleted nonexistent clause {:?}", clause),
+      FratError::FinalNotAtEnd { step, later } =>
+        write!(f, "step {}: 'f' steps should only appear at the end of the proof \
+          (step {} appears later).", step, later),
+      FratError::OutOfOrder { step } =>
+        write!(f, "step {}: out-of-order LRAT proofs not supported", step),
+      FratError::ReusedId { step, id } =>
+        write!(f, "at {}: Clause ID {} was already used", step, id),
+      FratError::Malformed(msg) => write!(f, "{}", msg),
+      FratError::EmptyClauseNotFinalized => write!(f, "empty clause never finalized"),
+      FratError::NoEmptyClause => write!(f, "did not find empty clause"),
+      FratError::Verify { step, clause, err } =>
+        write!(f, "step {} for {:?}: {}", step, clause, err),
+      FratError::Worker(msg) => write!(f, "worker thread panicked: {}", msg),
+      FratError::Usage(msg) => write!(f, "{}", msg),
+    }
+  }
+}
+
+impl std::error::Error for FratError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      FratError::Io(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for FratError {
+  fn from(e: io::Error) -> Self { FratError::Io(e) }
+}
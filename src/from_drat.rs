@@ -1,14 +1,18 @@
-use std::io::{self, Seek, SeekFrom, Read, BufReader, Write, BufWriter};
-use std::fs::{File, read_to_string};
-use crate::HashMap;
-use crate::dimacs::parse_dimacs;
-use crate::parser::{detect_binary, Mode, Bin, Ascii, DRATParser, DRATStep, StepRef, DefaultMode};
-use crate::serialize::{ModeWriter, Serialize};
+use std::io::{Read, BufReader, Write, BufWriter};
+use std::fs::File;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, DefaultMode, DRATParser, DRATStep, StepRef};
+use crate::serialize::{ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
 use crate::perm_clause::*;
 
-fn from_drat(mode: impl Mode, cnf: Vec<Box<[i64]>>, drat: File, frat: File) -> io::Result<()> {
-  let drat = DRATParser::from(mode, BufReader::new(drat).bytes().map(Result::unwrap));
-  let w = &mut ModeWriter(DefaultMode, BufWriter::new(frat));
+fn from_drat(mode: impl Mode, cnf: Vec<Box<[i64]>>, drat: impl Read,
+  frat: File, out_bin: bool
+) -> Result<()> {
+  let drat = DRATParser::from(mode, BufReader::new(drat).bytes());
+  let w = &mut ModeWriter(out_bin, BufWriter::new(frat));
   let mut k = 0; // Counter for the last used ID
   let mut ctx: HashMap<PermClause, Vec<u64>> = HashMap::default(); // current context
   for ls in cnf {
@@ -20,7 +24,7 @@ fn from_drat(mode: impl Mode, cnf: Vec<Box<[i64]>>, drat: File, frat: File) -> i
   for s in drat {
     // eprintln!("{:?}", s);
 
-    match s {
+    match s? {
       DRATStep::Comment(s) => StepRef::Comment(&s).write(w)?,
 
       DRATStep::Add(add) => {
@@ -36,9 +40,11 @@ fn from_drat(mode: impl Mode, cnf: Vec<Box<[i64]>>, drat: File, frat: File) -> i
 
       DRATStep::Del(ls) => {
         let ls = PermClause(ls);
-        let vec = ctx.get_mut(&ls).unwrap_or_else(
-          || panic!("deleted nonexistent clause {:?}", ls.0));
-        let st = vec.pop().expect("deleted nonexistent clause");
+        let st = match ctx.get_mut(&ls).and_then(|vec| vec.pop()) {
+          Some(st) => st,
+          None => return Err(FratError::DeletedNonexistent(ls.0)),
+        };
+        let vec = &ctx[&ls];
         if vec.is_empty() { ctx.remove(&ls); }
         StepRef::Del(st, &ls.0).write(w)?;
       }
@@ -57,15 +63,17 @@ fn from_drat(mode: impl Mode, cnf: Vec<Box<[i64]>>, drat: File, frat: File) -> i
     }
   }
 
-  w.flush()
+  Ok(w.flush()?)
 }
 
-pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-  let (_vars, cnf) = parse_dimacs(read_to_string(args.next().expect("missing input file"))?.bytes());
-  let mut drat = File::open(args.next().expect("missing proof file"))?;
-  let bin = detect_binary(&mut drat)?;
-  drat.seek(SeekFrom::Start(0))?;
-  let frat = File::create(args.next().expect("missing output file"))?;
-  if bin { from_drat(Bin, cnf, drat, frat) }
-  else { from_drat(Ascii, cnf, drat, frat) }
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "from-drat", "DIMACSFILE DRATFILE FRATFILE [--ascii|--binary]");
+  let (dimacs, proof, out) = (args.arg()?, args.arg()?, args.arg()?);
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let (bin, drat) = compress::open_detect(proof)?;
+  let frat = File::create(out)?;
+  if bin { from_drat(Bin, cnf, drat, frat, out_bin) }
+  else { from_drat(Ascii, cnf, drat, frat, out_bin) }
 }
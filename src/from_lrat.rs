@@ -0,0 +1,62 @@
+use std::io::{Read, BufReader, Write, BufWriter};
+use std::fs::File;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, DefaultMode, LRATParser, LRATStep, StepRef, ProofRef};
+use crate::serialize::{ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
+
+fn from_lrat(mode: impl Mode, cnf: Vec<Box<[i64]>>, lrat: impl Read,
+  frat: File, out_bin: bool
+) -> Result<()> {
+  let w = &mut ModeWriter(out_bin, BufWriter::new(frat));
+  let mut clauses: HashMap<u64, Box<[i64]>> = HashMap::default(); // the live clauses
+  for (i, ls) in (1..).zip(cnf) {
+    StepRef::Orig(i, &ls).write(w)?;
+    clauses.insert(i, ls);
+  }
+
+  let lrat = LRATParser::from(mode, BufReader::new(lrat).bytes()).after(clauses.len() as u64);
+  let mut unsat = None;
+  for (n, s) in (1..).zip(lrat) {
+    match s? {
+      (_, LRATStep::Comment(s)) => StepRef::Comment(&s).write(w)?,
+
+      (i, LRATStep::Add(add, hints)) => {
+        StepRef::Add(i, add.as_ref(), Some(ProofRef::LRAT(&hints))).write(w)?;
+        let ls = add.parse().lemma();
+        if clauses.insert(i, ls.into()).is_some() {
+          return Err(FratError::DuplicateClause { step: n, id: i })
+        }
+        if ls.is_empty() { unsat = Some(i); break }
+      }
+
+      (_, LRATStep::Del(ids)) => for c in ids {
+        let ls = clauses.remove(&c).ok_or(FratError::MissingClause { step: n, id: c })?;
+        StepRef::Del(c, &ls).write(w)?;
+      }
+    }
+  }
+  let unsat = unsat.ok_or(FratError::NoEmptyClause)?;
+
+  StepRef::Final(unsat, &[]).write(w)?;
+  clauses.remove(&unsat);
+  let mut live: Vec<_> = clauses.into_iter().collect();
+  live.sort_unstable_by_key(|p| p.0);
+  for (i, ls) in live { StepRef::Final(i, &ls).write(w)? }
+
+  Ok(w.flush()?)
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "from-lrat", "DIMACSFILE LRATFILE FRATFILE [--ascii|--binary]");
+  let (dimacs, proof, out) = (args.arg()?, args.arg()?, args.arg()?);
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let (bin, lrat) = compress::open_detect(proof)?;
+  let frat = File::create(out)?;
+  if bin { from_lrat(Bin, cnf, lrat, frat, out_bin) }
+  else { from_lrat(Ascii, cnf, lrat, frat, out_bin) }
+}
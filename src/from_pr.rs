@@ -1,27 +1,24 @@
 use std::collections::BTreeMap;
 use std::convert::TryInto;
-use std::fs::{File, read_to_string};
-use std::io::{self, Read, Write, Seek, SeekFrom, BufReader, BufWriter};
+use std::fs::File;
+use std::io::{self, Read, Write, BufReader, BufWriter};
 
-use crate::HashMap;
-use crate::dimacs::parse_dimacs;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
 use crate::midvec::MidVec;
-use crate::parser::{Mode, StepRef, Ascii, Bin, AddKind, DRATParser, DRATStep,
-  detect_binary, DefaultMode};
+use crate::parser::{Mode, StepRef, Ascii, Bin, DefaultMode, AddKind, DRATParser, DRATStep};
 use crate::perm_clause::PermClause;
-use crate::serialize::{Serialize, ModeWrite, ModeWriter};
+use crate::serialize::{Serialize, ModeWrite, ModeWriter, output_mode};
+use crate::cli::Args;
 
-#[repr(u8)] #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)] #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 enum Assign {
-  No = 0,
+  #[default] No = 0,
   Minimized = 1,
   Assigned = 2,
 }
 
-impl Default for Assign {
-  fn default() -> Self { Self::No }
-}
-
 struct PrStep {
   assignment: MidVec<Assign>,
   phase4_pfs: MidVec<Vec<i64>>,
@@ -35,7 +32,7 @@ fn add_pr_step(
   PrStep {assignment, phase4_pfs, marked}: &mut PrStep,
   k: &mut u64,
   ctx: &Context,
-  w: &mut impl ModeWrite,
+  w: &mut impl ModeWrite<bool>,
   opt: bool,
   lemma: &[i64], witness: &[i64],
   def: i64,
@@ -67,12 +64,12 @@ fn add_pr_step(
   // disabled because the original implementation does not make any sense
   let mflag = lemma.iter().all(|&lit| assignment[lit] != Assign::Assigned) && lemma.len() != 1;
 
-  fn add(k: &mut u64, c: Vec<i64>, pf: Option<&[i64]>, w: &mut impl ModeWrite) -> io::Result<(u64, Vec<i64>)> {
+  fn add(k: &mut u64, c: Vec<i64>, pf: Option<&[i64]>, w: &mut impl ModeWrite<bool>) -> io::Result<(u64, Vec<i64>)> {
     *k += 1;
     StepRef::add(*k, &c, pf).write(w)?;
     Ok((*k, c))
   }
-  fn delete((k, c): (u64, Vec<i64>), w: &mut impl ModeWrite) -> io::Result<()> {
+  fn delete((k, c): (u64, Vec<i64>), w: &mut impl ModeWrite<bool>) -> io::Result<()> {
     StepRef::Del(k, &c).write(w)
   }
 
@@ -211,11 +208,11 @@ fn add_pr_step(
 }
 
 fn from_pr(mode: impl Mode, (vars, cnf): (usize, Vec<Box<[i64]>>),
-  pr: File, frat: File, opt: bool
-) -> io::Result<()> {
-  let pr = DRATParser::from(mode, BufReader::new(pr).bytes().map(Result::unwrap));
+  pr: impl Read, frat: File, opt: bool, out_bin: bool
+) -> Result<()> {
+  let pr = DRATParser::from(mode, BufReader::new(pr).bytes());
   let mut maxvar = vars.try_into().unwrap();
-  let w = &mut ModeWriter(DefaultMode::default(), BufWriter::new(frat));
+  let w = &mut ModeWriter(out_bin, BufWriter::new(frat));
   let mut k = 0;
   let mut ctx: Context = Context::default();
   for ls in cnf {
@@ -230,7 +227,7 @@ fn from_pr(mode: impl Mode, (vars, cnf): (usize, Vec<Box<[i64]>>),
     marked: MidVec::with_capacity(maxvar),
   };
   for s in pr {
-    match s {
+    match s? {
       DRATStep::Comment(s) => StepRef::Comment(&s).write(w)?,
       DRATStep::Add(add) => {
         if let Some(new) = add.0.iter().copied().max() {
@@ -249,9 +246,11 @@ fn from_pr(mode: impl Mode, (vars, cnf): (usize, Vec<Box<[i64]>>),
       }
       DRATStep::Del(ls) => {
         let ls = PermClause(ls);
-        let vec = ctx.get_mut(&ls).unwrap_or_else(
-          || panic!("deleted nonexistent clause {:?}", ls.0));
-        let st = vec.pop().expect("deleted nonexistent clause");
+        let st = match ctx.get_mut(&ls).and_then(|vec| vec.pop()) {
+          Some(st) => st,
+          None => return Err(FratError::DeletedNonexistent(ls.0)),
+        };
+        let vec = &ctx[&ls];
         if vec.is_empty() { ctx.remove(&ls); }
         StepRef::Del(st, &ls.0).write(w)?;
       }
@@ -279,14 +278,15 @@ fn from_pr(mode: impl Mode, (vars, cnf): (usize, Vec<Box<[i64]>>),
   Ok(())
 }
 
-pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-  let dimacs = args.next().expect("missing input file");
-  let cnf = parse_dimacs(read_to_string(dimacs)?.bytes());
-  let mut pr = File::open(args.next().expect("missing proof file"))?;
-  let bin = detect_binary(&mut pr)?;
-  pr.seek(SeekFrom::Start(0))?;
-  let frat = File::create(args.next().expect("missing output file"))?;
-  let opt = matches!(args.next().as_deref(), Some("-O"));
-  if bin { from_pr(Bin, cnf, pr, frat, opt) }
-  else { from_pr(Ascii, cnf, pr, frat, opt) }
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "from-pr", "DIMACSFILE PRFILE FRATFILE [-O] [--ascii|--binary]");
+  let (dimacs, proof, out) = (args.arg()?, args.arg()?, args.arg()?);
+  let opt = args.flag("-O");
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  args.finish()?;
+  let cnf = read_dimacs(compress::open(dimacs)?)?;
+  let (bin, pr) = compress::open_detect(proof)?;
+  let frat = File::create(out)?;
+  if bin { from_pr(Bin, cnf, pr, frat, opt, out_bin) }
+  else { from_pr(Ascii, cnf, pr, frat, opt, out_bin) }
 }
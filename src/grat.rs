@@ -0,0 +1,176 @@
+//! GRAT certificates for the verified checker `gratchk`.
+//!
+//! A GRAT certificate consists of a lemma file, which lists the added clauses in
+//! DIMACS format with the RAT pivot first, and a binary proof file of little-endian
+//! 32-bit integers. The proof file is a sequence of items:
+//!
+//! ```text
+//! proof      ::= rat-counts item* conflict
+//! rat-counts ::= 6 (lit count)* 0      number of RAT lemmas for each pivot
+//! item       ::= 1 id* 0               unit propagation at the root
+//!              | 2 id* 0               deletion
+//!              | 3 id id* 0 id         RUP lemma: new ID, units, conflict
+//!              | 4 lit id id* 0 cand* 0  RAT lemma: pivot, new ID, units, candidates
+//! cand       ::= id id* 0 id           candidate, units, conflict
+//! conflict   ::= 5 id
+//! ```
+//!
+//! Each lemma item gives the ID of the next clause of the lemma file. `gratchk` reads
+//! the proof file from the end, so the integers are written in reverse order.
+//!
+//! The items are computed from an LRAT proof, by replaying the hints to find the
+//! clauses which are unit (hints which are already satisfied are dropped) and the
+//! conflict clause which ends each hint.
+
+use std::io::{self, Write};
+use std::convert::TryFrom;
+use crate::HashMap;
+use crate::error::{FratError, VerifyError, Result};
+use crate::parser::{Mode, LRATParser, LRATStep, AddKind};
+
+const UNIT_PROP: i32 = 1;
+const DELETION: i32 = 2;
+const RUP_LEMMA: i32 = 3;
+const RAT_LEMMA: i32 = 4;
+const CONFLICT: i32 = 5;
+const RAT_COUNTS: i32 = 6;
+
+fn word(step: u64, n: i64) -> Result<i32> {
+  i32::try_from(n).map_err(|_| FratError::Malformed(
+    format!("at {}: {} does not fit in a 32 bit GRAT integer", step, n)))
+}
+
+/// The clause set and partial assignment used to replay the hints.
+#[derive(Default)]
+struct Replay {
+  clauses: HashMap<u64, Box<[i64]>>,
+  /// The true literals, with the clause which made each one unit, if any
+  tru: HashMap<i64, Option<u64>>,
+}
+
+impl Replay {
+  /// Propagate the clauses in `hints` in order, until one of them is falsified.
+  /// Returns the unit clauses and the conflict clause.
+  fn walk(&mut self, step: u64, ls: &[i64], hints: &[i64]) -> Result<(Vec<u64>, Option<u64>)> {
+    let mut units = vec![];
+    for &h in hints {
+      let h = h as u64;
+      let c = self.clauses.get(&h).ok_or(FratError::MissingClause { step, id: h })?;
+      if c.iter().any(|lit| self.tru.contains_key(lit)) { continue }
+      let mut unit = None;
+      for &lit in &**c {
+        if self.tru.contains_key(&-lit) { continue }
+        if unit.is_some() { return Err(FratError::verify(step, ls, VerifyError::NotUnit(h))) }
+        unit = Some(lit)
+      }
+      match unit {
+        Some(lit) => { self.tru.insert(lit, Some(h)); units.push(h) }
+        None => return Ok((units, Some(h)))
+      }
+    }
+    Ok((units, None))
+  }
+}
+
+/// Write an LRAT proof of `cnf` as a GRAT certificate, with the lemmas going to `lemmas`
+/// and the items to `proof`.
+pub fn write_grat(cnf: &[Box<[i64]>], mode: impl Mode, lrat: impl Iterator<Item=io::Result<u8>>,
+  lemmas: &mut impl Write, proof: &mut impl Write
+) -> Result<()> {
+  let mut r = Replay::default();
+  for (i, c) in (1..).zip(cnf) { r.clauses.insert(i, c.clone()); }
+  let mut items = vec![];
+  let mut rat_counts: HashMap<i64, i64> = HashMap::default();
+  let stuck = |i, ls: &[i64]| FratError::verify(i, ls, VerifyError::UnitPropStuck);
+  let mut done = false;
+  for s in LRATParser::from(mode, lrat).after(cnf.len() as u64) {
+    match s? {
+      (_, LRATStep::Comment(_)) => {}
+
+      (i, LRATStep::Add(add, hints)) => {
+        let ls = match add.parse() {
+          AddKind::RAT(ls) => ls,
+          AddKind::PR(..) => return Err(FratError::Malformed(
+            format!("at {}: PR steps are not supported in GRAT", i))),
+        };
+        r.tru.clear();
+        if ls.is_empty() {
+          let (units, conflict) = r.walk(i, ls, &hints)?;
+          items.push(UNIT_PROP);
+          for u in units { items.push(word(i, u as i64)?) }
+          items.extend([0, CONFLICT, word(i, conflict.ok_or_else(|| stuck(i, ls))? as i64)?]);
+          done = true;
+          break
+        }
+        for &lit in ls { r.tru.insert(-lit, None); }
+        let start = hints.iter().position(|&h| h < 0).unwrap_or(hints.len());
+        let (units, conflict) = r.walk(i, ls, &hints[..start])?;
+        if let Some(conflict) = conflict {
+          items.extend([RUP_LEMMA, word(i, i as i64)?]);
+          for u in units { items.push(word(i, u as i64)?) }
+          items.extend([0, word(i, conflict as i64)?]);
+        } else {
+          let pivot = ls[0];
+          *rat_counts.entry(pivot).or_default() += 1;
+          items.extend([RAT_LEMMA, word(i, pivot)?, word(i, i as i64)?]);
+          for u in units { items.push(word(i, u as i64)?) }
+          items.push(0);
+          let mut rest = &hints[start..];
+          while let Some((&c, tail)) = rest.split_first() {
+            let end = tail.iter().position(|&h| h < 0).unwrap_or(tail.len());
+            let (chain, tail) = tail.split_at(end);
+            rest = tail;
+            let c = c.unsigned_abs();
+            let cand = r.clauses.get(&c).ok_or(FratError::MissingClause { step: i, id: c })?.clone();
+            let mut assumed = vec![];
+            let mut reason = None;
+            for &lit in &*cand {
+              if lit == -pivot || r.tru.contains_key(&-lit) { continue }
+              match r.tru.get(&lit) {
+                Some(&j) => { reason = Some(j); break }
+                None => { r.tru.insert(-lit, None); assumed.push(-lit) }
+              }
+            }
+            let (units, conflict) = match reason {
+              // A tautological resolvent needs no proof
+              Some(None) => (vec![], None),
+              // The resolvent is falsified by the units so far
+              Some(Some(j)) => (vec![], Some(j)),
+              None => r.walk(i, ls, chain)?,
+            };
+            for &u in &units { r.tru.retain(|_, j| *j != Some(u)) }
+            for lit in assumed { r.tru.remove(&lit); }
+            if reason == Some(None) { continue }
+            items.push(word(i, c as i64)?);
+            for u in units { items.push(word(i, u as i64)?) }
+            items.extend([0, word(i, conflict.ok_or_else(|| stuck(i, ls))? as i64)?]);
+          }
+          items.push(0);
+        }
+        for &lit in ls { write!(lemmas, "{} ", lit)? }
+        writeln!(lemmas, "0")?;
+        r.clauses.insert(i, ls.into());
+      }
+
+      (i, LRATStep::Del(ls)) => {
+        items.push(DELETION);
+        for c in ls {
+          r.clauses.remove(&c).ok_or(FratError::MissingClause { step: i, id: c })?;
+          items.push(word(i, c as i64)?);
+        }
+        items.push(0);
+      }
+    }
+  }
+  if !done { return Err(FratError::NoEmptyClause) }
+
+  let mut counts: Vec<_> = rat_counts.into_iter().collect();
+  counts.sort_unstable();
+  let mut header = vec![RAT_COUNTS];
+  for (lit, n) in counts { header.extend([word(0, lit)?, word(0, n)?]) }
+  header.push(0);
+  for &n in items.iter().rev().chain(header.iter().rev()) {
+    proof.write_all(&n.to_le_bytes())?
+  }
+  Ok(())
+}
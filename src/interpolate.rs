@@ -0,0 +1,292 @@
+//! Craig interpolants from refutations of a CNF split into two parts `A` and `B`.
+//!
+//! The proof is read as LRAT (a FRAT proof is elaborated first), and each hint chain
+//! is replayed as a linear resolution chain, computing a partial interpolant for every
+//! clause in either McMillan's or Pudlák's system. The interpolant of the empty clause
+//! is a circuit over the variables shared between `A` and `B`, which is implied by `A`
+//! and inconsistent with `B`.
+
+use std::io::{self, Read, Write, BufReader, BufWriter};
+use std::fs::File;
+use crate::HashMap;
+use crate::error::{FratError, Result};
+use crate::resolution::Resolver;
+use crate::dimacs::parse_dimacs_map;
+use crate::elab::{elab_to_lrat, dedup_vec};
+use crate::parser::{Mode, Ascii, Bin};
+use crate::compress;
+use crate::cli::Args;
+
+/// An AIG literal, encoded as in AIGER: `2 * node + negated`. Node 0 is the constant,
+/// so [`FALSE`] is 0 and [`TRUE`] is 1.
+pub type Lit = u32;
+pub const FALSE: Lit = 0;
+pub const TRUE: Lit = 1;
+
+#[derive(Debug, Copy, Clone)]
+enum Node { Const, Input(u64), And(Lit, Lit) }
+
+/// An and-inverter graph with structural hashing. The inputs are DIMACS variables.
+pub struct Aig {
+  nodes: Vec<Node>,
+  inputs: HashMap<u64, Lit>,
+  ands: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Default for Aig {
+  fn default() -> Self {
+    Aig { nodes: vec![Node::Const], inputs: HashMap::default(), ands: HashMap::default() }
+  }
+}
+
+impl Aig {
+  fn push(&mut self, n: Node) -> Lit {
+    self.nodes.push(n);
+    2 * (self.nodes.len() - 1) as Lit
+  }
+
+  /// The literal for a DIMACS literal, creating the input if necessary.
+  pub fn lit(&mut self, lit: i64) -> Lit {
+    let var = lit.unsigned_abs();
+    let a = match self.inputs.get(&var) {
+      Some(&a) => a,
+      None => { let a = self.push(Node::Input(var)); self.inputs.insert(var, a); a }
+    };
+    if lit < 0 { a ^ 1 } else { a }
+  }
+
+  pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+    let (a, b) = if a < b { (b, a) } else { (a, b) };
+    if b == FALSE || a == b ^ 1 { return FALSE }
+    if b == TRUE || a == b { return a }
+    if let Some(&c) = self.ands.get(&(a, b)) { return c }
+    let c = self.push(Node::And(a, b));
+    self.ands.insert((a, b), c);
+    c
+  }
+
+  pub fn or(&mut self, a: Lit, b: Lit) -> Lit { self.and(a ^ 1, b ^ 1) ^ 1 }
+
+  /// The nodes reachable from `out`, in creation order (which is topological).
+  fn cone(&self, out: Lit) -> Vec<usize> {
+    let mut seen = vec![false; self.nodes.len()];
+    let mut stack = vec![(out >> 1) as usize];
+    while let Some(n) = stack.pop() {
+      if std::mem::replace(&mut seen[n], true) { continue }
+      if let Node::And(a, b) = self.nodes[n] {
+        stack.push((a >> 1) as usize);
+        stack.push((b >> 1) as usize);
+      }
+    }
+    (1..self.nodes.len()).filter(|&n| seen[n]).collect()
+  }
+
+  /// Write the circuit with output `out` in ASCII AIGER format. `inputs` are the
+  /// DIMACS variables used as inputs, in order; they are named by their variable number.
+  pub fn write_aiger(&self, inputs: &[u64], out: Lit, w: &mut impl Write) -> io::Result<()> {
+    let mut map = vec![0; self.nodes.len()];
+    for (i, v) in inputs.iter().enumerate() {
+      if let Some(&a) = self.inputs.get(v) { map[(a >> 1) as usize] = 2 * (i + 1) as Lit }
+    }
+    let ands: Vec<_> = self.cone(out).into_iter()
+      .filter(|&n| matches!(self.nodes[n], Node::And(..))).collect();
+    let m = inputs.len() + ands.len();
+    let tr = |map: &[Lit], a: Lit| map[(a >> 1) as usize] | (a & 1);
+    writeln!(w, "aag {} {} 0 1 {}", m, inputs.len(), ands.len())?;
+    for i in 0..inputs.len() { writeln!(w, "{}", 2 * (i + 1))? }
+    for (i, &n) in ands.iter().enumerate() { map[n] = 2 * (inputs.len() + i + 1) as Lit }
+    writeln!(w, "{}", tr(&map, out))?;
+    for &n in &ands {
+      if let Node::And(a, b) = self.nodes[n] {
+        let (a, b) = (tr(&map, a), tr(&map, b));
+        writeln!(w, "{} {} {}", map[n], a.max(b), a.min(b))?
+      }
+    }
+    for (i, v) in inputs.iter().enumerate() { writeln!(w, "i{} {}", i, v)? }
+    writeln!(w, "c\ninterpolant over {} shared variables", inputs.len())
+  }
+
+  /// Write the circuit with output `out` as a Tseitin-encoded CNF asserting the output.
+  /// Inputs keep their DIMACS variable numbers, and gates are numbered from `vars + 1`.
+  pub fn write_cnf(&self, vars: u64, out: Lit, w: &mut impl Write) -> io::Result<()> {
+    let ands: Vec<_> = self.cone(out).into_iter()
+      .filter(|&n| matches!(self.nodes[n], Node::And(..))).collect();
+    let mut map = vec![0; self.nodes.len()];
+    for (n, node) in self.nodes.iter().enumerate() {
+      if let Node::Input(v) = *node { map[n] = v as i64 }
+    }
+    for (i, &n) in ands.iter().enumerate() { map[n] = (vars + 1 + i as u64) as i64 }
+    let tr = |a: Lit| { let v = map[(a >> 1) as usize]; if a & 1 != 0 { -v } else { v } };
+    writeln!(w, "c interpolant, gates are variables {} to {}", vars + 1, vars + ands.len() as u64)?;
+    match out {
+      TRUE => return writeln!(w, "p cnf {} 0", vars),
+      FALSE => return writeln!(w, "p cnf {} 1\n0", vars),
+      _ => {}
+    }
+    writeln!(w, "p cnf {} {}", vars + ands.len() as u64, 3 * ands.len() + 1)?;
+    for &n in &ands {
+      if let Node::And(a, b) = self.nodes[n] {
+        let (g, a, b) = (map[n], tr(a), tr(b));
+        writeln!(w, "{} {} 0\n{} {} 0\n{} {} {} 0", -g, a, -g, b, g, -a, -b)?
+      }
+    }
+    writeln!(w, "{} 0", tr(out))
+  }
+}
+
+/// The interpolation system, which decides how partial interpolants are combined.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum System { McMillan, Pudlak }
+
+/// The computation of an interpolant for a proof of `A ∧ B`.
+pub struct Interpolator {
+  pub aig: Aig,
+  system: System,
+  /// For each variable, bit 0 is set if it occurs in `A`, and bit 1 if it occurs in `B`.
+  occurs: Vec<u8>,
+  /// The partial interpolants of the clauses, by ID
+  interps: HashMap<u64, Lit>,
+}
+
+impl Interpolator {
+  /// Start from the CNF `cnf`, where `in_a[i]` says whether clause `i + 1` is in `A`.
+  pub fn new(system: System, cnf: &[Box<[i64]>], in_a: &[bool]) -> Self {
+    let mut occurs = vec![];
+    for (c, &a) in cnf.iter().zip(in_a) {
+      for lit in &**c {
+        let v = lit.unsigned_abs() as usize;
+        if v >= occurs.len() { occurs.resize(v + 1, 0) }
+        occurs[v] |= if a { 1 } else { 2 };
+      }
+    }
+    let mut this = Interpolator { aig: Aig::default(), system, occurs, interps: HashMap::default() };
+    for (i, (c, &a)) in cnf.iter().zip(in_a).enumerate() {
+      let p = match (system, a) {
+        (_, false) => TRUE,
+        (System::Pudlak, true) => FALSE,
+        (System::McMillan, true) => {
+          let mut p = FALSE;
+          for &lit in &**c { if this.shared(lit) { let l = this.aig.lit(lit); p = this.aig.or(p, l) } }
+          p
+        }
+      };
+      this.interps.insert(i as u64 + 1, p);
+    }
+    this
+  }
+
+  fn occurs(&self, lit: i64) -> u8 { self.occurs.get(lit.unsigned_abs() as usize).copied().unwrap_or(0) }
+  fn shared(&self, lit: i64) -> bool { self.occurs(lit) == 3 }
+
+  /// The variables occurring in both `A` and `B`.
+  pub fn shared_vars(&self) -> Vec<u64> {
+    (0..self.occurs.len() as u64).filter(|&v| self.occurs[v as usize] == 3).collect()
+  }
+
+  /// The partial interpolant of the resolvent of `c1` (containing the pivot `lit`) and
+  /// `c2` (containing `-lit`), with partial interpolants `p1` and `p2`.
+  fn resolve(&mut self, lit: i64, p1: Lit, p2: Lit) -> Lit {
+    match (self.system, self.occurs(lit)) {
+      (_, 1) => self.aig.or(p1, p2),
+      (System::Pudlak, 3) => {
+        let x = self.aig.lit(lit);
+        let (a, b) = (self.aig.or(x, p1), self.aig.or(x ^ 1, p2));
+        self.aig.and(a, b)
+      }
+      _ => self.aig.and(p1, p2),
+    }
+  }
+
+  /// Replay an LRAT proof of `cnf`, and return the interpolant of its empty clause.
+  pub fn replay(&mut self, cnf: &[Box<[i64]>],
+    mode: impl Mode, lrat: impl Iterator<Item=io::Result<u8>>
+  ) -> Result<Lit> {
+    let mut out = FALSE;
+    Resolver::replay(cnf, mode, lrat, |i, chain| {
+      let mut p = self.interps[&chain.start];
+      for &(h, lit) in &chain.links {
+        let p1 = self.interps[&h];
+        p = self.resolve(lit, p1, p);
+      }
+      self.interps.insert(i, p);
+      out = p;
+      Ok(())
+    })?;
+    Ok(out)
+  }
+}
+
+/// Parse a list of clause index ranges such as `1-10,15,20-` (1-based, inclusive).
+fn parse_ranges(s: &str, n: usize) -> Option<Vec<bool>> {
+  let mut in_a = vec![false; n];
+  for r in s.split(',') {
+    let (lo, hi) = match r.split_once('-') {
+      Some((lo, "")) => (lo.parse().ok()?, n),
+      Some((lo, hi)) => (lo.parse().ok()?, hi.parse().ok()?),
+      None => { let i = r.parse().ok()?; (i, i) }
+    };
+    if lo == 0 { return None }
+    for a in in_a.iter_mut().take(hi).skip(lo - 1) { *a = true }
+  }
+  Some(in_a)
+}
+
+/// The number of clauses before the first comment line `c MARKER` in a DIMACS file.
+fn clauses_before_marker(dimacs: &[u8], marker: &str) -> Option<usize> {
+  let mut n = 0;
+  for line in dimacs.split(|&c| c == b'\n') {
+    match line.first() {
+      Some(b'c') => if std::str::from_utf8(&line[1..]).is_ok_and(|s| s.trim() == marker) {
+        return Some(n)
+      },
+      Some(b'p') => {}
+      _ => n += line.split(|c| c.is_ascii_whitespace()).filter(|&tk| tk == b"0").count(),
+    }
+  }
+  None
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "interpolate", "\
+    DIMACSFILE PROOFFILE OUTFILE [--lrat] (--a-clauses RANGES | --marker TEXT) [--pudlak] [--cnf]");
+  let (dimacs_path, proof_path, out_path) = (args.arg()?, args.arg()?, args.arg()?);
+  let lrat = args.flag("--lrat");
+  let part = match args.value("--a-clauses")? {
+    Some(r) => Ok(r),
+    None => Err(args.value("--marker")?.ok_or_else(|| args.usage())?),
+  };
+  let system = if args.flag("--pudlak") { System::Pudlak } else { System::McMillan };
+  let cnf_out = args.flag("--cnf");
+  args.finish()?;
+
+  let mut dimacs = vec![];
+  compress::open(dimacs_path)?.read_to_end(&mut dimacs)?;
+  let (vars, cnf) = parse_dimacs_map(dimacs.iter().copied(), |mut c| {dedup_vec(&mut c); c.into()})?;
+  let in_a = match part {
+    Ok(r) => parse_ranges(&r, cnf.len()).ok_or_else(|| args.usage())?,
+    Err(m) => {
+      let n = clauses_before_marker(&dimacs, &m).ok_or_else(||
+        FratError::Malformed(format!("marker line `c {}` not found in DIMACS file", m)))?;
+      (0..cnf.len()).map(|i| i < n).collect()
+    }
+  };
+  println!("{} clauses in A, {} in B", in_a.iter().filter(|&&a| a).count(),
+    in_a.iter().filter(|&&a| !a).count());
+
+  let mut interp = Interpolator::new(system, &cnf, &in_a);
+  let out = if lrat {
+    let (bin, lrat) = compress::open_detect(proof_path)?;
+    let lrat = BufReader::new(lrat).bytes();
+    if bin { interp.replay(&cnf, Bin, lrat)? } else { interp.replay(&cnf, Ascii, lrat)? }
+  } else {
+    println!("elaborating...");
+    let lrat = elab_to_lrat(proof_path, &cnf)?;
+    interp.replay(&cnf, Bin, lrat.into_iter().map(Ok))?
+  };
+
+  let mut w = BufWriter::new(File::create(out_path)?);
+  if cnf_out { interp.aig.write_cnf(vars as u64, out, &mut w)? }
+  else { interp.aig.write_aiger(&interp.shared_vars(), out, &mut w)? }
+  w.flush()?;
+  Ok(())
+}
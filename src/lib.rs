@@ -0,0 +1,63 @@
+//! FRAT-rs is a toolchain for processing and transforming files in the
+//! [FRAT format](https://link.springer.com/chapter/10.1007/978-3-030-72016-2_4).
+//!
+//! The library exposes the same functionality as the `frat-rs` binary:
+//!
+//! * [`parser`] and [`backparser`] read FRAT, DRAT and LRAT step streams,
+//!   forwards ([`FwdParser`](parser::FwdParser)) and backwards
+//!   ([`BackParser`](backparser::BackParser)), in either [`Bin`](parser::Bin)
+//!   or [`Ascii`](parser::Ascii) mode.
+//! * [`serialize`] writes steps back out in either mode.
+//! * [`writer`] is a FRAT writer for solvers, which assigns IDs and writes the
+//!   final `f` steps ([`writer::FratWriter`]). It is also exported to C by
+//!   [`capi`], see `include/frat.h`.
+//! * [`elab`] contains the elaborator ([`elab::elab`], or multi-threaded
+//!   [`elab::elab_par`]), the LRAT generator
+//!   ([`elab::trim`]), the LRAT checker ([`elab::check_lrat`], or multi-threaded
+//!   [`elab::check_lrat_par`]) and the `refrat` pass ([`elab::refrat_pass`]).
+//! * [`resolution`] turns LRAT hints into resolution chains, which are used by
+//!   [`interpolate`] to compute Craig interpolants.
+//! * [`grat`] writes GRAT certificates for `gratchk`, and [`to_veripb`] writes
+//!   VeriPB proofs.
+//!
+//! Each subcommand of the binary is available as a `main` function in its module,
+//! taking the remaining command line arguments.
+
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod error;
+pub mod cli;
+pub mod compress;
+pub mod dimacs;
+pub mod parser;
+pub mod backparser;
+pub mod perm_clause;
+mod midvec;
+pub mod elab;
+pub mod stat;
+pub mod dratchk;
+pub mod serialize;
+pub mod from_drat;
+pub mod from_lrat;
+pub mod strip_frat;
+pub mod convert;
+pub mod renumber;
+pub mod merge;
+pub mod split;
+pub mod compose;
+pub mod drat_trim;
+pub mod from_pr;
+pub mod to_cnf;
+pub mod to_drat;
+pub mod writer;
+pub mod capi;
+pub mod resolution;
+pub mod interpolate;
+pub mod grat;
+pub mod to_veripb;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+pub type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+pub type HashSet<K> = std::collections::HashSet<K, BuildHasherDefault<DefaultHasher>>;
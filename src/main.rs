@@ -1,44 +1,41 @@
-#![allow(clippy::upper_case_acronyms)]
+use std::env;
+use frat_rs::*;
+use frat_rs::error::FratError;
 
-mod dimacs;
-mod parser;
-mod backparser;
-mod perm_clause;
-mod midvec;
-mod elab;
-mod stat;
-mod dratchk;
-mod serialize;
-mod from_drat;
-mod strip_frat;
-mod drat_trim;
-mod from_pr;
-mod to_cnf;
+const SUBCOMMANDS: &str = "\
+  elab, stat, dratchk, lratchk, refrat, to-cnf, to-drat, strip-frat, convert, renumber,\n\
+  merge, compose, split, splitchk, from-drat, from-lrat, drat-trim, from-pr, mus,\n\
+  interpolate, to-veripb";
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::BuildHasherDefault;
-use std::{env, io};
-
-pub type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
-pub type HashSet<K> = std::collections::HashSet<K, BuildHasherDefault<DefaultHasher>>;
-
-fn main() -> io::Result<()> {
+fn main() {
   let mut args = env::args().skip(1);
-  match args.next().expect("expected a subcommand").as_str() {
-    "elab" => elab::main(args),
-    "stat" => stat::main(args),
-    "dratchk" => dratchk::main(args),
-    "lratchk" => elab::lratchk(args),
-    "refrat" => elab::refrat(args),
-    "to-cnf" => to_cnf::main(args),
-    "strip-frat" => strip_frat::main(args),
-    "from-drat" => from_drat::main(args),
-    "drat-trim" => drat_trim::main(args),
-    "from-pr" => from_pr::main(args),
-    _ => {
-      eprintln!("incorrect subcommand, expected {{\
-        elab, stat, dratchk, lratchk, refrat, strip-frat, from-drat, from-pr}}");
-      std::process::exit(2);
-    }
+  let res = match args.next().as_deref() {
+    Some("elab") => elab::main(args),
+    Some("stat") => stat::main(args),
+    Some("dratchk") => dratchk::main(args),
+    Some("lratchk") => elab::lratchk(args),
+    Some("refrat") => elab::refrat(args),
+    Some("to-cnf") => to_cnf::main(args),
+    Some("to-drat") => to_drat::main(args),
+    Some("strip-frat") => strip_frat::main(args),
+    Some("convert") => convert::main(args),
+    Some("renumber") => renumber::main(args),
+    Some("merge") => merge::main(args),
+    Some("compose") => compose::main(args),
+    Some("split") => split::main(args),
+    Some("splitchk") => split::splitchk(args),
+    Some("from-drat") => from_drat::main(args),
+    Some("from-lrat") => from_lrat::main(args),
+    Some("drat-trim") => drat_trim::main(args),
+    Some("from-pr") => from_pr::main(args),
+    Some("mus") => elab::mus(args),
+    Some("interpolate") => interpolate::main(args),
+    Some("to-veripb") => to_veripb::main(args),
+    _ => Err(FratError::Usage(format!("Expected a subcommand, one of:\n\n{}", SUBCOMMANDS))),
+  };
+  match res {
+    Ok(()) => {}
+    Err(e @ FratError::Usage(_)) => { eprintln!("{}", e); std::process::exit(e.exit_code()) }
+    Err(e) => { eprintln!("error: {}", e); std::process::exit(e.exit_code()) }
   }
 }
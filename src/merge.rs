@@ -0,0 +1,280 @@
+//! Merging of cube-and-conquer proofs.
+//!
+//! Each cube `c1 /\ ... /\ ck` comes with a proof that the CNF together with the unit
+//! clauses `c1, ..., ck` is unsatisfiable. In the merged proof, every clause `D` derived
+//! in such a proof is weakened to `D \/ -c1 \/ ... \/ -ck`, so that the hints stay valid
+//! without the cube units, and the empty clause becomes the negated cube. Clauses which
+//! contain a cube literal are satisfied by the cube, so they are dropped. Finally, the
+//! empty clause is derived from the negated cubes by resolving along the tree formed by
+//! the cubes, which must be the paths of a complete decision tree (as produced by
+//! `march_cu`), each listing its decisions from the root.
+//!
+//! No separate proof that the cubes cover the search space is taken: the tree shape
+//! is what shows that they do, so any other covering set of cubes is rejected.
+
+use std::io::{BufRead, BufReader, Read, BufWriter};
+use std::fs::File;
+use crate::{HashMap, HashSet, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, DefaultMode, FwdParser, Step, Proof, AddStep, AddKind,
+  LRATParser, LRATStep};
+use crate::backparser::FwdStepIter;
+use crate::perm_clause::PermClause;
+use crate::serialize::output_mode;
+use crate::cli::Args;
+use crate::writer::FratWriter;
+
+/// What a clause ID of a cube proof refers to in the merged proof.
+#[derive(Copy, Clone)]
+enum Ref {
+  /// A clause of the merged proof
+  Id(u64),
+  /// A unit clause of the cube, or a clause satisfied by the cube
+  Cube,
+}
+
+/// Read the cubes (the `a` lines) of an iCNF file.
+pub fn read_cubes(r: impl Read) -> Result<Vec<Vec<i64>>> {
+  let mut cubes = vec![];
+  for line in BufReader::new(r).lines() {
+    let line = line?;
+    let mut it = line.split_whitespace();
+    match it.next() {
+      None | Some("c") | Some("p") => {}
+      Some("a") => {
+        let mut cube = vec![];
+        for s in it {
+          match s.parse::<i64>() {
+            Ok(0) => break,
+            Ok(lit) => cube.push(lit),
+            Err(_) => return Err(FratError::Malformed(format!("bad literal '{}' in cube", s))),
+          }
+        }
+        cubes.push(cube)
+      }
+      Some(_) => return Err(FratError::Malformed(
+        format!("expected a cube ('a' line) in cube file, found '{}'", line))),
+    }
+  }
+  Ok(cubes)
+}
+
+/// The merged proof, as it is being written.
+struct Merger<W: std::io::Write> {
+  w: FratWriter<bool, W>,
+  /// The clauses of the CNF, and their IDs
+  cnf: HashMap<PermClause, u64>,
+  /// The number of clauses in the CNF, which have IDs `1..=num_clauses`
+  num_clauses: u64,
+}
+
+/// The state of the proof of one cube.
+struct CubeProof<'a> {
+  cube: &'a [i64],
+  ids: HashMap<u64, Ref>,
+  /// The live clauses derived in this proof
+  derived: HashSet<u64>,
+}
+
+impl<'a> CubeProof<'a> {
+  fn get(&self, step: u64, i: u64) -> Result<Ref> {
+    self.ids.get(&i).copied().ok_or(FratError::MissingClause { step, id: i })
+  }
+
+  fn insert(&mut self, step: u64, i: u64, r: Ref) -> Result<()> {
+    if self.ids.insert(i, r).is_some() { return Err(FratError::DuplicateClause { step, id: i }) }
+    Ok(())
+  }
+
+  /// Add the weakened lemma of clause `i` at step `step`, returning the ID of the
+  /// negated cube if this is the empty clause.
+  fn add<W: std::io::Write>(&mut self, m: &mut Merger<W>, step: u64, i: u64, add: &AddStep,
+    hint: Option<&[i64]>
+  ) -> Result<Option<u64>> {
+    let ls = match add.parse() {
+      AddKind::RAT(ls) => ls,
+      AddKind::PR(..) => return Err(FratError::Malformed(
+        format!("at {}: PR steps in cube proofs are not supported", step))),
+    };
+    if ls.iter().any(|lit| self.cube.contains(lit)) { return self.insert(step, i, Ref::Cube).map(|_| None) }
+    let mut lits = ls.to_vec();
+    for &lit in self.cube { if !lits.contains(&-lit) { lits.push(-lit) } }
+    let hint = match hint {
+      None => None,
+      Some(hint) => Some(hint.iter().filter_map(|&h| {
+        if h < 0 { return Some(Err(FratError::Malformed(
+          format!("at {}: RAT steps in cube proofs are not supported", step)))) }
+        match self.get(step, h as u64) {
+          Ok(Ref::Id(j)) => Some(Ok(j as i64)),
+          Ok(Ref::Cube) => None,
+          Err(e) => Some(Err(e)),
+        }
+      }).collect::<Result<Vec<_>>>()?),
+    };
+    let j = m.w.add(&lits, hint.as_deref(), None)?;
+    self.insert(step, i, Ref::Id(j))?;
+    if ls.is_empty() { return Ok(Some(j)) }
+    self.derived.insert(j);
+    Ok(None)
+  }
+
+  fn del<W: std::io::Write>(&mut self, m: &mut Merger<W>, step: u64, i: u64) -> Result<()> {
+    // Clauses of the CNF are needed by the other cubes, so they are not deleted
+    if let Ref::Id(j) = self.ids.remove(&i).ok_or(FratError::MissingClause { step, id: i })? {
+      if self.derived.remove(&j) { m.w.del(j)? }
+    }
+    Ok(())
+  }
+
+  /// Delete the remaining derived clauses, once the negated cube is derived.
+  fn finish<W: std::io::Write>(self, m: &mut Merger<W>) -> Result<()> {
+    let mut derived: Vec<_> = self.derived.into_iter().collect();
+    derived.sort_unstable();
+    for j in derived { m.w.del(j)? }
+    Ok(())
+  }
+}
+
+impl<W: std::io::Write> Merger<W> {
+  fn cube_frat(&mut self, cube: &[i64], mode: impl Mode, frat: impl Read) -> Result<u64> {
+    let mut p = CubeProof { cube, ids: HashMap::default(), derived: HashSet::default() };
+    for (n, s) in (1..).zip(FwdStepIter::new(FwdParser::new(mode, frat))) {
+      match s? {
+        Step::Orig(i, ls) => {
+          let r = if ls.len() == 1 && cube.contains(&ls[0]) { Ref::Cube } else {
+            let j = self.cnf.get(&PermClause(ls)).copied().ok_or_else(|| FratError::Malformed(
+              format!("original clause {} is not in the CNF or the cube", i)))?;
+            Ref::Id(j)
+          };
+          p.insert(n, i, r)?
+        }
+        Step::Add(i, add, proof) => {
+          let hint = proof.as_ref().map(|Proof::LRAT(hint)| &**hint);
+          if let Some(j) = p.add(self, n, i, &add, hint)? { p.finish(self)?; return Ok(j) }
+        }
+        Step::Del(i, _) => p.del(self, n, i)?,
+        Step::Reloc(relocs) => {
+          let moved = relocs.iter().map(|&(from, to)|
+            Ok((to, p.ids.remove(&from).ok_or(FratError::MissingClause { step: n, id: from })?)))
+            .collect::<Result<Vec<_>>>()?;
+          for (to, r) in moved { p.insert(n, to, r)? }
+        }
+        Step::Comment(_) | Step::Final(..) | Step::Todo(_) => {}
+      }
+    }
+    Err(FratError::NoEmptyClause)
+  }
+
+  /// The LRAT proof is for the CNF followed by the unit clauses of the cube.
+  fn cube_lrat(&mut self, cube: &[i64], mode: impl Mode,
+    lrat: impl Iterator<Item=std::io::Result<u8>>
+  ) -> Result<u64> {
+    let mut p = CubeProof { cube, ids: HashMap::default(), derived: HashSet::default() };
+    let n = self.num_clauses;
+    p.ids.extend((1..=n).map(|i| (i, Ref::Id(i))));
+    p.ids.extend((1..=cube.len() as u64).map(|i| (n + i, Ref::Cube)));
+    for (k, s) in (1..).zip(LRATParser::from(mode, lrat).after(n + cube.len() as u64)) {
+      match s? {
+        (_, LRATStep::Comment(_)) => {}
+        (i, LRATStep::Add(add, hint)) =>
+          if let Some(j) = p.add(self, k, i, &add, Some(&hint))? { p.finish(self)?; return Ok(j) },
+        (_, LRATStep::Del(ids)) => for c in ids { p.del(self, k, c)? },
+      }
+    }
+    Err(FratError::NoEmptyClause)
+  }
+}
+
+/// A node of the tree of cubes, for the path of decisions leading to it.
+#[derive(Default)]
+struct Node {
+  /// The negated cube, if this path is a cube
+  cube: Option<u64>,
+  children: Vec<(i64, Node)>,
+}
+
+impl Node {
+  /// Add the cube `path` with negated cube `id`. Returns false if the cube is a prefix
+  /// of a cube already in the tree, or has one as a prefix (including itself).
+  fn insert(&mut self, path: &[i64], id: u64) -> bool {
+    if self.cube.is_some() { return false }
+    match path.split_first() {
+      None => self.children.is_empty() && { self.cube = Some(id); true },
+      Some((&lit, rest)) => {
+        let child = match self.children.iter().position(|c| c.0 == lit) {
+          Some(k) => &mut self.children[k].1,
+          None => { self.children.push((lit, Node::default())); &mut self.children.last_mut().unwrap().1 }
+        };
+        child.insert(rest, id)
+      }
+    }
+  }
+
+  /// Derive the negation of `path`, by resolving the clauses of the two branches.
+  fn derive<W: std::io::Write>(&self, w: &mut FratWriter<bool, W>, path: &mut Vec<i64>) -> Result<u64> {
+    if let Some(id) = self.cube { return Ok(id) }
+    let (pos, neg) = match &*self.children {
+      [(x, pos), (y, neg)] if *x == -*y => (pos, neg),
+      _ => return Err(FratError::Malformed(format!(
+        "the cubes do not form a decision tree below the path {:?}", path))),
+    };
+    path.push(self.children[0].0);
+    let a = pos.derive(w, path)?;
+    path.pop();
+    path.push(self.children[1].0);
+    let b = neg.derive(w, path)?;
+    path.pop();
+    let lits: Vec<_> = path.iter().map(|&lit| -lit).collect();
+    let id = w.add(&lits, Some(&[a as i64, b as i64]), None)?;
+    w.del(a)?;
+    w.del(b)?;
+    Ok(id)
+  }
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "merge",
+    "DIMACSFILE CUBEFILE FRATFILE [--lrat] [--ascii|--binary] PROOFFILE...\n\n\
+    The cubes must be the paths of a complete decision tree, listed from the root;\n\
+    other sets of cubes which cover the search space are not supported");
+  let (dimacs, cube_path, out) = (args.arg()?, args.arg()?, args.arg()?);
+  let lrat = args.flag("--lrat");
+  let out_bin = output_mode(&mut args, DefaultMode.bin());
+  let proofs: Vec<_> = args.rest().collect();
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let cubes = read_cubes(compress::open(cube_path)?)?;
+  if proofs.len() != cubes.len() {
+    return Err(FratError::Malformed(format!(
+      "{} cubes, but {} proofs were given", cubes.len(), proofs.len())))
+  }
+
+  let mut m = Merger {
+    w: FratWriter::new(out_bin, BufWriter::new(File::create(out)?)),
+    cnf: HashMap::default(),
+    num_clauses: cnf.len() as u64,
+  };
+  for ls in cnf {
+    let i = m.w.orig(&ls)?;
+    m.cnf.entry(PermClause(ls.into())).or_insert(i);
+  }
+  let mut tree = Node::default();
+  for (k, (cube, proof)) in cubes.iter().zip(proofs).enumerate() {
+    m.w.comment(&format!("cube {}", k + 1))?;
+    let (bin, r) = compress::open_detect(proof)?;
+    let id = match (lrat, bin) {
+      (false, true) => m.cube_frat(cube, Bin, r)?,
+      (false, false) => m.cube_frat(cube, Ascii, r)?,
+      (true, true) => m.cube_lrat(cube, Bin, BufReader::new(r).bytes())?,
+      (true, false) => m.cube_lrat(cube, Ascii, BufReader::new(r).bytes())?,
+    };
+    if !tree.insert(cube, id) {
+      return Err(FratError::Malformed(format!(
+        "cube {} {:?} repeats or extends an earlier cube", k + 1, cube)))
+    }
+  }
+  m.w.comment("coverage")?;
+  tree.derive(&mut m.w, &mut vec![])?;
+  m.w.finish()?;
+  Ok(())
+}
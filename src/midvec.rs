@@ -81,13 +81,13 @@ impl<T> MidVec<T> {
   }
 
   #[inline] pub fn get(&self, n: i64) -> Option<&T> {
-    if n <= self.size as i64 && -n <= self.size as i64 {
+    if n <= self.size && -n <= self.size {
       Some(unsafe {self.get_unchecked(n)})
     } else {None}
   }
 
   #[inline] pub fn get_mut(&mut self, n: i64) -> Option<&mut T> {
-    if n <= self.size as i64 && -n <= self.size as i64 {
+    if n <= self.size && -n <= self.size {
       Some(unsafe {self.get_unchecked_mut(n)})
     } else {None}
   }
@@ -149,7 +149,7 @@ impl<T> Index<i64> for MidVec<T> {
 impl<T> IndexMut<i64> for MidVec<T> {
   #[inline] #[track_caller] fn index_mut(&mut self, index: i64) -> &mut T {
     match self.get_mut(index) {
-      Some(x) => unsafe { std::mem::transmute(x) },
+      Some(x) => unsafe { std::mem::transmute::<&mut T, &mut T>(x) },
       None => panic_bounds_check(index, self.size)
     }
   }
@@ -166,7 +166,7 @@ impl<T> IntoIterator for MidVec<T> {
   type Item = (i64, T);
   type IntoIter = std::iter::Zip<RangeInclusive<i64>, std::vec::IntoIter<T>>;
   fn into_iter(self) -> Self::IntoIter {
-    (-self.size..=self.size).zip(Vec::from(self).into_iter())
+    (-self.size..=self.size).zip(Vec::from(self))
   }
 }
 
@@ -1,5 +1,8 @@
 use std::fs::File;
 use std::io::{self, Read, Seek, SeekFrom};
+use crate::error::{FratError, ParseError, Result};
+
+pub type PResult<T> = std::result::Result<T, ParseError>;
 
 pub trait BackScan {
   fn back_scan(&mut self, _: &[u8]) -> Option<usize>;
@@ -10,69 +13,76 @@ pub trait Mode: Default {
   fn bin(&self) -> bool;
   fn new_back_scan(&self) -> Self::BackScanState;
   fn keyword(&self, it: &mut impl Iterator<Item=u8>) -> Option<u8> { it.next() }
-  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> Option<u64>;
-  fn num(&self, it: &mut impl Iterator<Item=u8>) -> Option<i64>;
-  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> String;
+  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<u64>>;
+  fn num(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<i64>>;
+  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> PResult<String>;
+
+  fn unum1(&self, it: &mut impl Iterator<Item=u8>) -> PResult<u64> {
+    self.unum(it)?.ok_or(ParseError::UnexpectedEof)
+  }
 
-  fn uvec(&self, it: &mut impl Iterator<Item=u8>) -> Vec<u64> {
+  fn uvec(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<u64>> {
     let mut vec = Vec::new();
-    loop { match self.unum(it).expect("bad step") {
-      0 => return vec,
+    loop { match self.unum1(it)? {
+      0 => return Ok(vec),
       i => vec.push(i)
     } }
   }
 
-  fn ivec(&self, it: &mut impl Iterator<Item=u8>) -> Vec<i64> {
+  fn ivec(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<i64>> {
     let mut vec = Vec::new();
-    loop { match self.num(it).expect("bad step") {
-      0 => return vec,
+    loop { match self.num(it)?.ok_or(ParseError::UnexpectedEof)? {
+      0 => return Ok(vec),
       i => vec.push(i)
     } }
   }
 
-  fn uvec2(&self, it: &mut impl Iterator<Item=u8>) -> Vec<(u64, u64)> {
+  fn uvec2(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<(u64, u64)>> {
     let mut vec = Vec::new();
     loop {
-      match self.unum(it).expect("bad step") {
-        0 => return vec,
-        i => vec.push((i, match self.unum(it) {
+      match self.unum1(it)? {
+        0 => return Ok(vec),
+        i => vec.push((i, match self.unum(it)? {
           Some(j) if j != 0 => j,
-          _ => panic!("odd relocation")
+          _ => return Err(ParseError::OddReloc)
         }))
       }
     }
   }
 
-  fn segment_mut(&self, ch: impl FnOnce() -> usize, it: &mut impl Iterator<Item=u8>) -> Segment {
-    match self.keyword(it) {
-      Some(b'c') => Segment::Comment(self.comment(it)),
-      Some(b'a') => Segment::Add(self.unum(it).unwrap(), self.ivec(it)),
-      Some(b'd') => Segment::Del(self.unum(it).unwrap(), self.ivec(it)),
-      Some(b'f') => Segment::Final(self.unum(it).unwrap(), self.ivec(it)),
-      Some(b'l') => Segment::LProof(self.ivec(it)),
-      Some(b'o') => Segment::Orig(self.unum(it).unwrap(), self.ivec(it)),
-      Some(b'r') => Segment::Reloc(self.uvec2(it)),
+  fn segment_core(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Segment> {
+    Ok(match self.keyword(it) {
+      Some(b'c') => Segment::Comment(self.comment(it)?),
+      Some(b'a') => Segment::Add(self.unum1(it)?, self.ivec(it)?),
+      Some(b'd') => Segment::Del(self.unum1(it)?, self.ivec(it)?),
+      Some(b'f') => Segment::Final(self.unum1(it)?, self.ivec(it)?),
+      Some(b'l') => Segment::LProof(self.ivec(it)?),
+      Some(b'o') => Segment::Orig(self.unum1(it)?, self.ivec(it)?),
+      Some(b'r') => Segment::Reloc(self.uvec2(it)?),
       Some(b't') => {
-        let n = self.unum(it).unwrap();
-        assert!(self.unum(it).unwrap() == 0, "parse error at todo, expected 1 number");
+        let n = self.unum1(it)?;
+        if self.unum1(it)? != 0 { return Err(ParseError::BadTodo) }
         Segment::Todo(n)
       }
-      Some(k) => panic!("parse error at char {}: bad step {:?}", ch(), k as char),
-      None => panic!("parse error at char {}: bad step None", ch()),
-    }
+      k => return Err(ParseError::BadKeyword(k)),
+    })
+  }
+
+  fn segment_mut(&self, ch: impl FnOnce() -> usize, it: &mut impl Iterator<Item=u8>) -> Result<Segment> {
+    self.segment_core(it).map_err(|e| FratError::parse(ch(), e))
   }
 
   fn check_empty(&self, mut it: impl Iterator<Item=u8>) -> bool {
     it.next().is_none()
   }
 
-  fn segment(&self, ch: impl Fn() -> usize, mut it: impl Iterator<Item=u8>) -> Segment {
-    let seg = self.segment_mut(&ch, &mut it);
-    assert!(self.check_empty(it), "parse error at char {}: segment has trailing characters", ch());
-    seg
+  fn segment(&self, ch: impl Fn() -> usize, mut it: impl Iterator<Item=u8>) -> Result<Segment> {
+    let seg = self.segment_mut(&ch, &mut it)?;
+    if !self.check_empty(it) { return Err(FratError::parse(ch(), ParseError::TrailingChars)) }
+    Ok(seg)
   }
 
-	fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> Option<DRATStep>;
+  fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<DRATStep>>;
 }
 
 #[derive(Debug)]
@@ -104,37 +114,39 @@ impl Mode for Bin {
   #[inline] fn bin(&self) -> bool {true}
   fn new_back_scan(&self) -> Self::BackScanState { Bin }
 
-  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> Option<u64> {
+  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<u64>> {
     let mut res: u64 = 0;
     let mut mul: u8 = 0;
     for c in it {
+      if mul >= 64 { return Err(ParseError::Overflow) }
       res |= ((c & 0x7F) as u64) << mul;
       mul += 7;
       if c & 0x80 == 0 {
-        return Some(res)
+        return Ok(Some(res))
       }
     }
-    if res != 0 { panic!("iterator ran out with incomplete number") }
-    None
+    if res != 0 { return Err(ParseError::UnexpectedEof) }
+    Ok(None)
   }
 
-  fn num(&self, it: &mut impl Iterator<Item=u8>) -> Option<i64> {
-    self.unum(it).map(|ulit|
+  fn num(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<i64>> {
+    Ok(self.unum(it)?.map(|ulit|
       if ulit & 1 != 0 { -((ulit >> 1) as i64) }
-      else { (ulit >> 1) as i64 })
+      else { (ulit >> 1) as i64 }))
   }
 
-  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> String {
-    String::from_utf8(it.take_while(|i| *i != 0).collect()).expect("non-utf8")
+  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> PResult<String> {
+    String::from_utf8(it.take_while(|i| *i != 0).collect()).map_err(|_| ParseError::NonUtf8)
   }
 
-	fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> Option<DRATStep> {
-    match self.keyword(it)? {
-      b'c' => Some(DRATStep::Comment(self.comment(it))),
-      b'd' => Some(DRATStep::Del(self.ivec(it))),
-      b'a' => Some(DRATStep::Add(AddStep(self.ivec(it)))),
-      k => panic!("bad keyword {}", k as char)
-    }
+	fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<DRATStep>> {
+    Ok(Some(match self.keyword(it) {
+      None => return Ok(None),
+      Some(b'c') => DRATStep::Comment(self.comment(it)?),
+      Some(b'd') => DRATStep::Del(self.ivec(it)?),
+      Some(b'a') => DRATStep::Add(AddStep(self.ivec(it)?)),
+      k => return Err(ParseError::BadKeyword(k))
+    }))
   }
 }
 
@@ -147,18 +159,19 @@ impl Ascii {
   }
   fn initial_neg(it: &mut impl Iterator<Item=u8>) -> (bool, Option<u8>) {
     match Ascii::spaces(it) {
-      Some(c) if c == b'-' => (true, it.next()),
+      Some(b'-') => (true, it.next()),
       o => (false, o)
     }
   }
 
-  fn parse_num(peek: Option<u8>, it: &mut impl Iterator<Item=u8>) -> Option<u64> {
-    let mut val = (peek? as char).to_digit(10).unwrap() as u64;
+  fn parse_num(peek: Option<u8>, it: &mut impl Iterator<Item=u8>) -> PResult<Option<u64>> {
+    let c = match peek { Some(c) => c, None => return Ok(None) };
+    let mut val = (c as char).to_digit(10).ok_or(ParseError::BadDigit(c))? as u64;
 		while let Some(parsed) = it.next().and_then(|c| (c as char).to_digit(10)) {
-			val *= 10;
-      val += parsed as u64;
+			val = val.checked_mul(10).and_then(|val| val.checked_add(parsed as u64))
+        .ok_or(ParseError::Overflow)?;
     }
-		Some(val)
+		Ok(Some(val))
   }
 }
 
@@ -204,17 +217,16 @@ impl Mode for Ascii {
   #[inline] fn bin(&self) -> bool {false}
   fn new_back_scan(&self) -> Self::BackScanState { AsciiBackScan::default() }
   fn keyword(&self, it: &mut impl Iterator<Item=u8>) -> Option<u8> { Ascii::spaces(it) }
-  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> Option<u64> {
+  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<u64>> {
     Ascii::parse_num(Ascii::spaces(it), it)
   }
-  fn num(&self, it: &mut impl Iterator<Item=u8>) -> Option<i64> {
+  fn num(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<i64>> {
     let (neg, peek) = Ascii::initial_neg(it);
-    let val = Ascii::parse_num(peek, it)?;
-    Some(if neg { -(val as i64) } else { val as i64 })
+    Ok(Ascii::parse_num(peek, it)?.map(|val| if neg { -(val as i64) } else { val as i64 }))
   }
-  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> String {
+  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> PResult<String> {
     let mut vec = match it.next() {
-      None | Some(b'\n') => return String::new(),
+      None | Some(b'\n') => return Ok(String::new()),
       Some(b' ') => vec![],
       Some(c) => vec![c],
     };
@@ -228,19 +240,20 @@ impl Mode for Ascii {
         Some(c) => vec.push(c)
       }
     }
-    String::from_utf8(vec).expect("non-utf8")
+    String::from_utf8(vec).map_err(|_| ParseError::NonUtf8)
   }
   fn check_empty(&self, mut it: impl Iterator<Item=u8>) -> bool {
     it.all(|c| matches!(c, b' ' | b'\n'))
   }
 
-	fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> Option<DRATStep> {
-    match self.keyword(it)? {
-      b'c' => Some(DRATStep::Comment(self.comment(it))),
-      b'd' => Some(DRATStep::Del(self.ivec(it))),
-      k => Some(DRATStep::Add(AddStep(
-        self.ivec(&mut Some(k).iter().cloned().chain(it)))))
-    }
+	fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<DRATStep>> {
+    Ok(Some(match self.keyword(it) {
+      None => return Ok(None),
+      Some(b'c') => DRATStep::Comment(self.comment(it)?),
+      Some(b'd') => DRATStep::Del(self.ivec(it)?),
+      Some(k) => DRATStep::Add(AddStep(
+        self.ivec(&mut Some(k).iter().cloned().chain(it))?))
+    }))
   }
 }
 
@@ -266,77 +279,164 @@ impl Mode for bool {
   fn keyword(&self, it: &mut impl Iterator<Item=u8>) -> Option<u8> {
     if *self {Bin.keyword(it)} else {Ascii.keyword(it)}
   }
-  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> Option<u64> {
+  fn unum(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<u64>> {
     if *self {Bin.unum(it)} else {Ascii.unum(it)}
   }
-  fn num(&self, it: &mut impl Iterator<Item=u8>) -> Option<i64> {
+  fn num(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<i64>> {
     if *self {Bin.num(it)} else {Ascii.num(it)}
   }
-  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> String {
+  fn comment(&self, it: &mut impl Iterator<Item=u8>) -> PResult<String> {
     if *self {Bin.comment(it)} else {Ascii.comment(it)}
   }
-  fn uvec(&self, it: &mut impl Iterator<Item=u8>) -> Vec<u64> {
+  fn uvec(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<u64>> {
     if *self {Bin.uvec(it)} else {Ascii.uvec(it)}
   }
-  fn ivec(&self, it: &mut impl Iterator<Item=u8>) -> Vec<i64> {
+  fn ivec(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<i64>> {
     if *self {Bin.ivec(it)} else {Ascii.ivec(it)}
   }
-  fn uvec2(&self, it: &mut impl Iterator<Item=u8>) -> Vec<(u64, u64)> {
+  fn uvec2(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Vec<(u64, u64)>> {
     if *self {Bin.uvec2(it)} else {Ascii.uvec2(it)}
   }
-  fn segment_mut(&self, ch: impl FnOnce() -> usize, it: &mut impl Iterator<Item=u8>) -> Segment {
-    if *self {Bin.segment_mut(ch, it)} else {Ascii.segment_mut(ch, it)}
+  fn segment_core(&self, it: &mut impl Iterator<Item=u8>) -> PResult<Segment> {
+    if *self {Bin.segment_core(it)} else {Ascii.segment_core(it)}
   }
   fn check_empty(&self, it: impl Iterator<Item=u8>) -> bool {
     if *self {Bin.check_empty(it)} else {Ascii.check_empty(it)}
   }
-  fn segment(&self, ch: impl Fn() -> usize, it: impl Iterator<Item=u8>) -> Segment {
-    if *self {Bin.segment(ch, it)} else {Ascii.segment(ch, it)}
-  }
-  fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> Option<DRATStep> {
+  fn drat_step(&mut self, it: &mut impl Iterator<Item=u8>) -> PResult<Option<DRATStep>> {
     if *self {Bin.drat_step(it)} else {Ascii.drat_step(it)}
   }
 }
 
-pub fn detect_binary(f: &mut File) -> io::Result<bool> {
+pub fn detect_binary(f: &mut (impl Read + Seek)) -> io::Result<bool> {
   if f.seek(SeekFrom::End(-1)).is_err() { return Ok(false) }
   let mut c = [0u8; 1];
   f.read_exact(&mut c)?;
   Ok(c[0] == 0)
 }
 
-pub struct LRATParser<M, I> {mode: M, it: I}
+/// Adapts an iterator over `io::Result<u8>` (such as [`io::Bytes`]) to an iterator
+/// over `u8` for the [`Mode`] functions, counting the bytes read and stashing any I/O error.
+struct Stash<'a, I> {
+  it: &'a mut I,
+  pos: &'a mut usize,
+  err: &'a mut Option<io::Error>,
+}
+
+impl<I: Iterator<Item=io::Result<u8>>> Iterator for Stash<'_, I> {
+  type Item = u8;
+  fn next(&mut self) -> Option<u8> {
+    match self.it.next()? {
+      Ok(c) => { *self.pos += 1; Some(c) }
+      Err(e) => { *self.err = Some(e); None }
+    }
+  }
+}
+
+/// Wraps a parse function for use with [`Stash`], to report I/O errors and
+/// attach the position of the start of the step to parse errors.
+fn stashed<I: Iterator<Item=io::Result<u8>>, T>(it: &mut I, pos: &mut usize,
+  f: impl FnOnce(&mut Stash<'_, I>) -> PResult<Option<T>>
+) -> Option<Result<T>> {
+  let start = *pos;
+  let mut err = None;
+  let res = f(&mut Stash { it, pos, err: &mut err });
+  if let Some(e) = err { return Some(Err(e.into())) }
+  res.map_err(|e| FratError::parse(start, e)).transpose()
+}
+
+/// A parser for LRAT files. The input is an iterator over `io::Result<u8>`,
+/// such as [`Read::bytes`], or `vec.into_iter().map(Ok)` for in-memory data.
+///
+/// In `Bin` mode this reads the binary LRAT format accepted by `lrat-check` and
+/// `cake_lpr`: `a ID LITS 0 HINTS 0` and `d IDS 0`, where all numbers (including IDs)
+/// use the signed varint encoding. Binary deletion lines have no step ID, so they are
+/// reported with the ID of the last added clause (initially the value set by
+/// [`LRATParser::after`], usually the number of clauses in the CNF).
+///
+/// In `Ascii` mode, comment lines `c ...` without a step ID are also accepted,
+/// and are reported with the ID of the previous step.
+pub struct LRATParser<M, I> {mode: M, it: I, pos: usize, last: u64}
 
 impl<M, I> LRATParser<M, I> {
-	pub fn from(mode: M, it: I) -> Self { LRATParser {mode, it} }
+	pub fn from(mode: M, it: I) -> Self { LRATParser {mode, it, pos: 0, last: 0} }
+
+  /// Set the step ID reported for binary deletion lines before the first addition.
+  pub fn after(self, last: u64) -> Self { LRATParser {last, ..self} }
 }
 
 #[derive(Debug)]
 pub enum LRATStep {
 	Comment(String),
 	Add(AddStep, Vec<i64>),
-	Del(Vec<i64>)
+	Del(Vec<u64>)
 }
 
-impl<M: Mode, I: Iterator<Item=u8>> Iterator for LRATParser<M, I> {
-	type Item = (u64, LRATStep);
-	fn next(&mut self) -> Option<(u64, LRATStep)> {
-		Some((self.mode.unum(&mut self.it)?,
-      match self.mode.keyword(&mut self.it)? {
-        b'c' => LRATStep::Comment(self.mode.comment(&mut self.it)),
-        b'd' => LRATStep::Del(self.mode.ivec(&mut self.it)),
-        k => LRATStep::Add(
-          AddStep(self.mode.ivec(&mut Some(k).into_iter().chain(&mut self.it))),
-          self.mode.ivec(&mut self.it))
+impl LRATStep {
+  /// The step with its ID `i`, as returned by [`LRATParser`].
+  pub fn as_ref(&self, i: u64) -> LRATStepRef<'_> {
+    match self {
+      LRATStep::Comment(s) => LRATStepRef::Comment(i, s),
+      LRATStep::Add(add, hints) => LRATStepRef::Add(i, add.as_ref(), hints),
+      LRATStep::Del(ids) => LRATStepRef::Del(i, ids),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum LRATStepRef<'a> {
+	Comment(u64, &'a str),
+	Add(u64, AddStepRef<'a>, &'a [i64]),
+	Del(u64, &'a [u64])
+}
+
+fn bin_lrat_id(i: i64) -> PResult<u64> {
+  if i > 0 { Ok(i as u64) } else { Err(ParseError::BadId) }
+}
+
+impl<M: Mode, I: Iterator<Item=io::Result<u8>>> Iterator for LRATParser<M, I> {
+	type Item = Result<(u64, LRATStep)>;
+	fn next(&mut self) -> Option<Self::Item> {
+    let mode = &self.mode;
+    let last = &mut self.last;
+    stashed(&mut self.it, &mut self.pos, |it| {
+      if mode.bin() {
+        return Ok(Some(match mode.keyword(it) {
+          None => return Ok(None),
+          Some(b'a') => {
+            let i = bin_lrat_id(mode.num(it)?.ok_or(ParseError::UnexpectedEof)?)?;
+            *last = i;
+            (i, LRATStep::Add(AddStep(mode.ivec(it)?), mode.ivec(it)?))
+          }
+          Some(b'd') => (*last, LRATStep::Del(
+            mode.ivec(it)?.into_iter().map(bin_lrat_id).collect::<PResult<_>>()?)),
+          k => return Err(ParseError::BadKeyword(k))
+        }))
       }
-    ))
+      let i = match mode.keyword(it) {
+        None => return Ok(None),
+        Some(b'c') => return Ok(Some((*last, LRATStep::Comment(mode.comment(it)?)))),
+        Some(c) => mode.unum1(&mut Some(c).into_iter().chain(&mut *it))?,
+      };
+      *last = i;
+      Ok(Some((i, match mode.keyword(it) {
+        None => return Err(ParseError::UnexpectedEof),
+        Some(b'c') => LRATStep::Comment(mode.comment(it)?),
+        Some(b'd') => LRATStep::Del(mode.uvec(it)?),
+        Some(k) => LRATStep::Add(
+          AddStep(mode.ivec(&mut Some(k).into_iter().chain(&mut *it))?),
+          mode.ivec(it)?)
+      })))
+    })
 	}
 }
 
-pub struct DRATParser<M, I> {mode: M, it: I}
+/// A parser for DRAT files. The input is an iterator over `io::Result<u8>`,
+/// as for [`LRATParser`].
+pub struct DRATParser<M, I> {mode: M, it: I, pos: usize}
 
 impl<M, I> DRATParser<M, I> {
-	pub fn from(mode: M, it: I) -> Self { DRATParser {mode, it} }
+	pub fn from(mode: M, it: I) -> Self { DRATParser {mode, it, pos: 0} }
 }
 
 #[derive(Debug)]
@@ -346,9 +446,29 @@ pub enum DRATStep {
 	Del(Vec<i64>)
 }
 
-impl<M: Mode, I: Iterator<Item=u8>> Iterator for DRATParser<M, I> {
-	type Item = DRATStep;
-	fn next(&mut self) -> Option<DRATStep> { self.mode.drat_step(&mut self.it) }
+impl DRATStep {
+  pub fn as_ref(&self) -> DRATStepRef<'_> {
+    match self {
+      DRATStep::Comment(s) => DRATStepRef::Comment(s),
+      DRATStep::Add(add) => DRATStepRef::Add(add.as_ref()),
+      DRATStep::Del(ls) => DRATStepRef::Del(ls),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum DRATStepRef<'a> {
+	Comment(&'a str),
+	Add(AddStepRef<'a>),
+	Del(&'a [i64])
+}
+
+impl<M: Mode, I: Iterator<Item=io::Result<u8>>> Iterator for DRATParser<M, I> {
+	type Item = Result<DRATStep>;
+	fn next(&mut self) -> Option<Result<DRATStep>> {
+    let mode = &mut self.mode;
+    stashed(&mut self.it, &mut self.pos, |it| mode.drat_step(it))
+  }
 }
 
 #[derive(Debug)]
@@ -362,7 +482,7 @@ pub enum ProofRef<'a> {
 }
 
 impl Proof {
-  pub fn as_ref(&self) -> ProofRef {
+  pub fn as_ref(&self) -> ProofRef<'_> {
     match self {
       Proof::LRAT(v) => ProofRef::LRAT(v),
     }
@@ -375,7 +495,7 @@ pub struct AddStep(pub Vec<i64>);
 #[derive(Debug, Copy, Clone)]
 pub enum AddStepRef<'a> {
   One(&'a [i64]),
-  #[allow(unused)] Two(&'a [i64], &'a [i64]),
+  Two(&'a [i64], &'a [i64]),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -495,7 +615,7 @@ pub enum ElabStepRef<'a> {
 }
 
 impl ElabStep {
-  pub fn as_ref(&self) -> ElabStepRef {
+  pub fn as_ref(&self) -> ElabStepRef<'_> {
     match *self {
       ElabStep::Comment(ref s) => ElabStepRef::Comment(s),
       ElabStep::Orig(i, ref v) => ElabStepRef::Orig(i, v),
@@ -508,15 +628,16 @@ impl ElabStep {
 
 pub(crate) const BUFFER_SIZE: usize = 0x4000;
 
-struct FwdParserInner {
-  file: File,
+struct FwdParserInner<R> {
+  file: R,
   buffer_start: usize,
   buffer: [u8; BUFFER_SIZE],
   pos: usize,
   end: usize,
+  err: Option<io::Error>,
 }
 
-impl FwdParserInner {
+impl<R: Read> FwdParserInner<R> {
   fn refill(&mut self) -> io::Result<bool> {
     if self.end == BUFFER_SIZE {
       self.buffer_start += BUFFER_SIZE;
@@ -532,10 +653,10 @@ impl FwdParserInner {
   }
 }
 
-impl Iterator for FwdParserInner {
+impl<R: Read> Iterator for FwdParserInner<R> {
   type Item = u8;
   fn next(&mut self) -> Option<Self::Item> {
-    if self.pos < self.end || self.refill().expect("could not read from proof file") {
+    if self.pos < self.end || self.refill().unwrap_or_else(|e| { self.err = Some(e); false }) {
       let c = unsafe { *self.buffer.get_unchecked(self.pos) };
       self.pos += 1;
       Some(c)
@@ -545,25 +666,30 @@ impl Iterator for FwdParserInner {
   }
 }
 
-pub struct FwdParser<M: Mode> {
+pub struct FwdParser<M: Mode, R = File> {
   mode: M,
-  inner: FwdParserInner
+  inner: FwdParserInner<R>
 }
 
-impl<M: Mode> FwdParser<M> {
-  pub fn new(mode: M, file: File) -> Self {
-    let inner = FwdParserInner { file, buffer_start: 0, buffer: [0; BUFFER_SIZE], pos: 0, end: 0 };
+impl<M: Mode, R: Read> FwdParser<M, R> {
+  pub fn new(mode: M, file: R) -> Self {
+    let inner = FwdParserInner {
+      file, buffer_start: 0, buffer: [0; BUFFER_SIZE], pos: 0, end: 0, err: None
+    };
     Self { mode, inner }
   }
 }
-impl<M: Mode> Iterator for FwdParser<M> {
-  type Item = Segment;
+impl<M: Mode, R: Read> Iterator for FwdParser<M, R> {
+  type Item = Result<Segment>;
 
-  fn next(&mut self) -> Option<Segment> {
+  fn next(&mut self) -> Option<Result<Segment>> {
     let inner = &mut self.inner;
-    self.mode.keyword(inner)?;
+    if self.mode.keyword(inner).is_none() {
+      return inner.err.take().map(|e| Err(e.into()))
+    }
     inner.pos -= 1;
     let start = inner.buffer_start + inner.pos;
-    Some(self.mode.segment_mut(|| start, inner))
+    let res = self.mode.segment_mut(|| start, inner);
+    Some(match inner.err.take() { Some(e) => Err(e.into()), None => res })
   }
 }
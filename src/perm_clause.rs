@@ -27,7 +27,7 @@ impl<'a> Eq for PermClauseRef<'a> {}
 pub struct PermClause(pub Vec<i64>);
 
 impl PermClause {
-  pub fn as_ref(&self) -> PermClauseRef { PermClauseRef(&self.0) }
+  pub fn as_ref(&self) -> PermClauseRef<'_> { PermClauseRef(&self.0) }
 }
 
 impl Hash for PermClause {
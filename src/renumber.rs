@@ -0,0 +1,127 @@
+//! Renumbering of clause IDs, so that they are dense and assigned in order of first
+//! appearance. Relocation steps are resolved away, and the hints are updated to match.
+
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::fs::File;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, FwdParser, Step, StepRef, Proof, ProofRef,
+  LRATParser, LRATStep, LRATStepRef};
+use crate::backparser::FwdStepIter;
+use crate::serialize::{ModeWrite, ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
+
+/// The map from old to new IDs.
+#[derive(Default)]
+struct Renumber {
+  ids: HashMap<u64, u64>,
+  /// The last new ID that was handed out
+  last: u64,
+}
+
+impl Renumber {
+  fn fresh(&mut self, step: u64, i: u64) -> Result<u64> {
+    self.last += 1;
+    if self.ids.insert(i, self.last).is_some() {
+      return Err(FratError::DuplicateClause { step, id: i })
+    }
+    Ok(self.last)
+  }
+
+  fn get(&self, step: u64, i: u64) -> Result<u64> {
+    self.ids.get(&i).copied().ok_or(FratError::MissingClause { step, id: i })
+  }
+
+  fn remove(&mut self, step: u64, i: u64) -> Result<u64> {
+    self.ids.remove(&i).ok_or(FratError::MissingClause { step, id: i })
+  }
+
+  /// Rename the clause IDs in a hint, keeping the sign of RAT hints.
+  fn hint(&self, step: u64, hint: &[i64]) -> Result<Vec<i64>> {
+    hint.iter().map(|&h| {
+      let i = self.get(step, h.unsigned_abs())? as i64;
+      Ok(if h < 0 { -i } else { i })
+    }).collect()
+  }
+}
+
+/// Renumber the clauses of a FRAT proof, writing the result to `w`.
+pub fn renumber_frat<M>(mode: impl Mode, frat: impl Read, w: &mut impl ModeWrite<M>) -> Result<()>
+where for<'a> StepRef<'a>: Serialize<M> {
+  let mut r = Renumber::default();
+  for (n, s) in (1..).zip(FwdStepIter::new(FwdParser::new(mode, frat))) {
+    match s? {
+      Step::Comment(s) => StepRef::Comment(&s).write(w)?,
+      Step::Orig(i, ls) => StepRef::Orig(r.fresh(n, i)?, &ls).write(w)?,
+      Step::Add(i, add, proof) => {
+        let hint = match proof { Some(Proof::LRAT(p)) => Some(r.hint(n, &p)?), None => None };
+        let j = r.fresh(n, i)?;
+        StepRef::Add(j, add.as_ref(), hint.as_deref().map(ProofRef::LRAT)).write(w)?
+      }
+      Step::Del(i, ls) => StepRef::Del(r.remove(n, i)?, &ls).write(w)?,
+      Step::Reloc(relocs) => {
+        let moved = relocs.into_iter().map(|(from, to)| Ok((to, r.remove(n, from)?)))
+          .collect::<Result<Vec<_>>>()?;
+        for (to, j) in moved {
+          if r.ids.insert(to, j).is_some() {
+            return Err(FratError::DuplicateClause { step: n, id: to })
+          }
+        }
+      }
+      Step::Final(i, ls) => StepRef::Final(r.remove(n, i)?, &ls).write(w)?,
+      // The argument of a `t` step is a reason, not a clause ID
+      Step::Todo(i) => StepRef::Todo(i).write(w)?,
+    }
+  }
+  Ok(())
+}
+
+/// Renumber the clauses of an LRAT proof of a CNF with `num_clauses` clauses, writing
+/// the result to `w`. The original clauses keep their IDs.
+pub fn renumber_lrat<M>(mode: impl Mode, num_clauses: u64,
+  lrat: impl Iterator<Item=std::io::Result<u8>>, w: &mut impl ModeWrite<M>
+) -> Result<()>
+where for<'a> LRATStepRef<'a>: Serialize<M> {
+  let mut r = Renumber::default();
+  r.ids.extend((1..=num_clauses).map(|i| (i, i)));
+  r.last = num_clauses;
+  for (n, s) in (1..).zip(LRATParser::from(mode, lrat).after(num_clauses)) {
+    match s? {
+      (_, LRATStep::Comment(s)) => LRATStepRef::Comment(r.last, &s).write(w)?,
+      (i, LRATStep::Add(add, hint)) => {
+        let hint = r.hint(n, &hint)?;
+        let j = r.fresh(n, i)?;
+        LRATStepRef::Add(j, add.as_ref(), &hint).write(w)?
+      }
+      (_, LRATStep::Del(ids)) => {
+        let ids = ids.into_iter().map(|c| r.remove(n, c)).collect::<Result<Vec<_>>>()?;
+        LRATStepRef::Del(r.last, &ids).write(w)?
+      }
+    }
+  }
+  Ok(())
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "renumber",
+    "PROOFFILE NEWPROOFFILE [--lrat DIMACSFILE] [--ascii|--binary]");
+  let (proof, out) = (args.arg()?, args.arg()?);
+  let dimacs = args.value("--lrat")?;
+  let (bin, r) = compress::open_detect(proof)?;
+  // By default the output is in the same mode as the input
+  let out_bin = output_mode(&mut args, bin);
+  args.finish()?;
+  let w = &mut ModeWriter(out_bin, BufWriter::new(File::create(out)?));
+  match dimacs {
+    None if bin => renumber_frat(Bin, r, w)?,
+    None => renumber_frat(Ascii, r, w)?,
+    Some(dimacs) => {
+      let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+      let lrat = BufReader::new(r).bytes();
+      let n = cnf.len() as u64;
+      if bin { renumber_lrat(Bin, n, lrat, w)? } else { renumber_lrat(Ascii, n, lrat, w)? }
+    }
+  }
+  Ok(w.flush()?)
+}
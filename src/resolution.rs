@@ -0,0 +1,168 @@
+//! Linear resolution chains from LRAT hints.
+//!
+//! An LRAT hint lists the clauses used by unit propagation in the order they become unit,
+//! ending with a falsified clause. Read backwards, it is a resolution chain: starting from
+//! the falsified clause, each earlier hint is resolved on the literal it made true, if the
+//! resolvent so far still contains its negation, and skipped otherwise. The result is a
+//! subset of the lemma, which can be stronger than the lemma itself.
+
+use std::io::{self, Write};
+use crate::{HashMap, HashSet};
+use crate::error::{FratError, VerifyError, Result};
+use crate::parser::{Mode, LRATParser, LRATStep, AddKind};
+
+/// A resolution chain: the clause `start`, resolved with each clause in `links` in order.
+/// The pivot of each link is the literal which occurs in the link clause, and whose
+/// negation occurs in the resolvent so far.
+#[derive(Debug, Default)]
+pub struct Chain {
+  pub start: u64,
+  pub links: Vec<(u64, i64)>,
+  /// The resolvent, with literals in the order of the lemma
+  pub resolvent: Box<[i64]>,
+}
+
+/// The live clauses of a proof, used to turn hints into resolution chains.
+#[derive(Default)]
+pub struct Resolver {
+  clauses: HashMap<u64, Box<[i64]>>,
+}
+
+impl Resolver {
+  pub fn len(&self) -> usize { self.clauses.len() }
+  pub fn is_empty(&self) -> bool { self.clauses.is_empty() }
+
+  /// The clauses of `cnf`, numbered from 1.
+  pub fn from_cnf(cnf: &[Box<[i64]>]) -> Self {
+    Resolver { clauses: (1..).zip(cnf.iter().cloned()).collect() }
+  }
+
+  /// Add clause `i` at step `step` of the proof.
+  pub fn insert(&mut self, step: u64, i: u64, lits: Box<[i64]>) -> Result<()> {
+    if self.clauses.insert(i, lits).is_some() {
+      return Err(FratError::DuplicateClause { step, id: i })
+    }
+    Ok(())
+  }
+
+  /// Remove clause `i` at step `step` of the proof.
+  pub fn remove(&mut self, step: u64, i: u64) -> Result<Box<[i64]>> {
+    self.clauses.remove(&i).ok_or(FratError::MissingClause { step, id: i })
+  }
+
+  /// Build the resolution chain for the lemma `ls` at step `i` from the RUP hint `hints`.
+  /// The resolvent is not added to the clause set.
+  pub fn chain(&self, i: u64, ls: &[i64], hints: &[i64]) -> Result<Chain> {
+    let mut tru: HashSet<i64> = ls.iter().map(|&l| -l).collect();
+    let mut units = vec![];
+    let mut conflict = None;
+    for &h in hints {
+      if h < 0 {
+        return Err(FratError::Malformed(format!("at {}: RAT steps have no resolution chain", i)))
+      }
+      let h = h as u64;
+      let c = self.clauses.get(&h).ok_or(FratError::MissingClause { step: i, id: h })?;
+      // Satisfied hint clauses are skipped, as in `check_lrat`
+      if c.iter().any(|lit| tru.contains(lit)) { continue }
+      let mut unit = None;
+      for &lit in &**c {
+        if tru.contains(&-lit) { continue }
+        if unit.is_some() { return Err(FratError::verify(i, ls, VerifyError::NotUnit(h))) }
+        unit = Some(lit)
+      }
+      match unit {
+        Some(lit) => { tru.insert(lit); units.push((h, lit)) }
+        None => { conflict = Some(h); break }
+      }
+    }
+    let start = conflict.ok_or_else(|| FratError::verify(i, ls, VerifyError::UnitPropStuck))?;
+    let mut res: HashSet<i64> = self.clauses[&start].iter().copied().collect();
+    let mut links = vec![];
+    for (h, lit) in units.into_iter().rev() {
+      if res.remove(&-lit) {
+        res.extend(self.clauses[&h].iter().filter(|&&l| l != lit));
+        links.push((h, lit));
+      }
+    }
+    let mut resolvent = Vec::with_capacity(res.len());
+    for &lit in ls { if res.remove(&lit) { resolvent.push(lit) } }
+    debug_assert!(res.is_empty(), "resolvent is not a subset of the lemma");
+    Ok(Chain { start, links, resolvent: resolvent.into() })
+  }
+
+  /// Replay an LRAT proof of `cnf`, calling `f` on the chain of each addition step,
+  /// until the empty clause is reached. The resolvent of each chain replaces its lemma.
+  pub fn replay(cnf: &[Box<[i64]>], mode: impl Mode, lrat: impl Iterator<Item=io::Result<u8>>,
+    mut f: impl FnMut(u64, &Chain) -> Result<()>
+  ) -> Result<Self> {
+    let mut this = Resolver::from_cnf(cnf);
+    for (n, s) in (1..).zip(LRATParser::from(mode, lrat).after(cnf.len() as u64)) {
+      match s? {
+        (_, LRATStep::Comment(_)) => {}
+        (i, LRATStep::Add(add, hints)) => {
+          let ls = match add.parse() {
+            AddKind::RAT(ls) => ls,
+            AddKind::PR(..) => return Err(FratError::Malformed(
+              format!("at {}: PR steps have no resolution chain", i))),
+          };
+          let chain = this.chain(i, ls, &hints)?;
+          f(i, &chain)?;
+          if chain.resolvent.is_empty() { return Ok(this) }
+          this.insert(n, i, chain.resolvent)?;
+        }
+        (_, LRATStep::Del(ls)) => for i in ls { this.remove(n, i)?; },
+      }
+    }
+    Err(FratError::NoEmptyClause)
+  }
+}
+
+/// Write the original clauses and the resolution chains of an LRAT proof of `cnf`, one
+/// per line as `ID lits 0 chain 0`. A chain which is just one clause gets no line of its
+/// own, and the clause is used in its place. If `pivots` is set, each link of the chain
+/// after the first clause is followed by its pivot.
+fn write_chains(cnf: &[Box<[i64]>], mode: impl Mode,
+  lrat: impl Iterator<Item=io::Result<u8>>, pivots: bool, w: &mut impl Write
+) -> Result<()> {
+  for (i, c) in (1..).zip(cnf) {
+    write!(w, "{} ", i)?;
+    for lit in &**c { write!(w, "{} ", lit)? }
+    writeln!(w, "0 0")?;
+  }
+  let mut alias: HashMap<u64, u64> = HashMap::default();
+  Resolver::replay(cnf, mode, lrat, |i, chain| {
+    let name = |j| alias.get(&j).copied().unwrap_or(j);
+    if chain.links.is_empty() {
+      let j = name(chain.start);
+      alias.insert(i, j);
+      return Ok(())
+    }
+    write!(w, "{} ", i)?;
+    for lit in &*chain.resolvent { write!(w, "{} ", lit)? }
+    write!(w, "0 {}", name(chain.start))?;
+    for &(j, lit) in &chain.links {
+      if pivots { write!(w, " {} {}", name(j), lit)? } else { write!(w, " {}", name(j))? }
+    }
+    writeln!(w, " 0")?;
+    Ok(())
+  })?;
+  Ok(())
+}
+
+/// Write an LRAT proof of `cnf` as a TraceCheck resolution trace. Each derived clause
+/// lists its antecedents in the order of a linear resolution chain.
+pub fn write_tracecheck(cnf: &[Box<[i64]>], mode: impl Mode,
+  lrat: impl Iterator<Item=io::Result<u8>>, w: &mut impl Write
+) -> Result<()> {
+  write_chains(cnf, mode, lrat, false, w)
+}
+
+/// Write an LRAT proof of `cnf` as a resolution proof. This is like TraceCheck, but
+/// each antecedent after the first is followed by the pivot literal, as it occurs in
+/// the antecedent, so the line `i lits 0 c0 c1 p1 c2 p2 0` says that clause `i` is
+/// the resolvent of `c0` with `c1` on `p1`, and of that with `c2` on `p2`.
+pub fn write_resolution(cnf: &[Box<[i64]>], mode: impl Mode,
+  lrat: impl Iterator<Item=io::Result<u8>>, w: &mut impl Write
+) -> Result<()> {
+  write_chains(cnf, mode, lrat, true, w)
+}
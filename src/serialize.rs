@@ -1,9 +1,12 @@
 use arrayvec::ArrayVec;
 use std::io::{self, Write};
+use super::cli::Args;
 use super::parser::{Ascii, Bin, DefaultMode,
-  Step, StepRef, AddStep, AddStepRef, ElabStep, ElabStepRef, ProofRef};
+  Step, StepRef, AddStep, AddStepRef, ElabStep, ElabStepRef, ProofRef, LRATStepRef, DRATStepRef};
 
-pub trait ModeWrite<M=DefaultMode>: Write {}
+pub trait ModeWrite<M=DefaultMode>: Write {
+  fn mode(&self) -> &M;
+}
 
 pub struct ModeWriter<M, W>(pub M, pub W);
 
@@ -12,12 +15,34 @@ impl<M, W: Write> Write for ModeWriter<M, W> {
   fn write_all(&mut self, buf: &[u8]) -> io::Result<()> { self.1.write_all(buf) }
   fn flush(&mut self) -> io::Result<()> { self.1.flush() }
 }
-impl<M, W: Write> ModeWrite<M> for ModeWriter<M, W> {}
+impl<M, W: Write> ModeWrite<M> for ModeWriter<M, W> {
+  fn mode(&self) -> &M { &self.0 }
+}
 
 pub trait Serialize<M=DefaultMode> {
   fn write(&self, w: &mut impl ModeWrite<M>) -> io::Result<()>;
 }
 
+/// Parse the optional `--ascii` or `--binary` argument of a subcommand, which selects the
+/// output mode at runtime (`true` for binary), for use with the `bool` mode. Without it,
+/// the output mode is `default`.
+pub fn output_mode(args: &mut Args<impl Iterator<Item=String>>, default: bool) -> bool {
+  match args.choice(&["--ascii", "--binary"]) {
+    Some(i) => i == 1,
+    None => default
+  }
+}
+
+/// Write `t` in binary or ASCII mode, depending on the runtime mode of `w`.
+fn write_bool<T>(t: &T, w: &mut impl ModeWrite<bool>) -> io::Result<()>
+where T: Serialize<Bin> + Serialize<Ascii> {
+  if *w.mode() {
+    Serialize::<Bin>::write(t, &mut ModeWriter(Bin, w))
+  } else {
+    Serialize::<Ascii>::write(t, &mut ModeWriter(Ascii, w))
+  }
+}
+
 impl<A: Serialize<Bin>, B: Serialize<Bin>> Serialize<Bin> for (A, B) {
   fn write(&self, w: &mut impl ModeWrite<Bin>) -> io::Result<()> {
     self.0.write(w)?; self.1.write(w)
@@ -200,3 +225,80 @@ impl<M> Serialize<M> for ElabStep where for<'a> ElabStepRef<'a>: Serialize<M> {
     self.as_ref().write(w)
   }
 }
+
+/// Binary LRAT, as read by `lrat-check` and `cake_lpr`. IDs are written with the
+/// signed encoding, like literals, deletion lines carry no step ID, and comments are dropped.
+impl<'a> Serialize<Bin> for LRATStepRef<'a> {
+  fn write(&self, w: &mut impl ModeWrite<Bin>) -> io::Result<()> {
+    match *self {
+      LRATStepRef::Comment(_, _) => Ok(()),
+      LRATStepRef::Add(idx, step, hints) => ((b'a', (idx as i64, step)), hints).write(w),
+      LRATStepRef::Del(_, ids) => {
+        b'd'.write(w)?;
+        for &i in ids { (i as i64).write(w)? }
+        0u8.write(w)
+      }
+    }
+  }
+}
+
+impl<'a> Serialize<Ascii> for LRATStepRef<'a> {
+  fn write(&self, w: &mut impl ModeWrite<Ascii>) -> io::Result<()> {
+    match *self {
+      LRATStepRef::Comment(idx, s) =>
+        s.split('\n').try_for_each(|s| writeln!(w, "{} c {}", idx, s)),
+      LRATStepRef::Add(idx, step, hints) => {
+        write!(w, "{}", idx)?;
+        match step {
+          AddStepRef::One(ls) => for &x in ls { write!(w, " {}", x)? },
+          AddStepRef::Two(ls, ls2) => for &x in ls.iter().chain(ls2) { write!(w, " {}", x)? },
+        }
+        write!(w, " 0")?;
+        for &x in hints { write!(w, " {}", x)? }
+        writeln!(w, " 0")
+      }
+      LRATStepRef::Del(idx, ids) => {
+        write!(w, "{} d", idx)?;
+        for &x in ids { write!(w, " {}", x)? }
+        writeln!(w, " 0")
+      }
+    }
+  }
+}
+
+/// Binary DRAT, as read by `drat-trim`. Comments are dropped.
+impl<'a> Serialize<Bin> for DRATStepRef<'a> {
+  fn write(&self, w: &mut impl ModeWrite<Bin>) -> io::Result<()> {
+    match *self {
+      DRATStepRef::Comment(_) => Ok(()),
+      DRATStepRef::Add(step) => (b'a', step).write(w),
+      DRATStepRef::Del(ls) => (b'd', ls).write(w),
+    }
+  }
+}
+
+impl<'a> Serialize<Ascii> for DRATStepRef<'a> {
+  fn write(&self, w: &mut impl ModeWrite<Ascii>) -> io::Result<()> {
+    match *self {
+      DRATStepRef::Comment(s) => s.split('\n').try_for_each(|s| writeln!(w, "c {}", s)),
+      DRATStepRef::Add(step) => { step.write(w)?; writeln!(w) }
+      DRATStepRef::Del(ls) => { write!(w, "d ")?; ls.write(w)?; writeln!(w) }
+    }
+  }
+}
+
+impl Serialize<bool> for StepRef<'_> {
+  fn write(&self, w: &mut impl ModeWrite<bool>) -> io::Result<()> { write_bool(self, w) }
+}
+
+impl Serialize<bool> for ElabStepRef<'_> {
+  fn write(&self, w: &mut impl ModeWrite<bool>) -> io::Result<()> { write_bool(self, w) }
+}
+
+impl Serialize<bool> for LRATStepRef<'_> {
+  fn write(&self, w: &mut impl ModeWrite<bool>) -> io::Result<()> { write_bool(self, w) }
+}
+
+impl Serialize<bool> for DRATStepRef<'_> {
+  fn write(&self, w: &mut impl ModeWrite<bool>) -> io::Result<()> { write_bool(self, w) }
+}
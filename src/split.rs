@@ -0,0 +1,340 @@
+//! Splitting of LRAT proofs into segments which can be checked independently.
+//!
+//! Each segment is a CNF file and an LRAT proof of some lemmas from that CNF. The CNF of
+//! a segment consists of its imports, the clauses live at the start of the segment which
+//! are referenced by its hints. The lemmas derived in a segment and imported by later
+//! segments are its exports. The manifest records, for each segment, the clause IDs of
+//! the original proof for its imports and exports, and [`check_manifest`] checks that
+//! every import is a clause of the original CNF or an export of an earlier segment.
+//!
+//! Checking the lemmas of a segment against fewer clauses than the whole proof has is
+//! only sound for RUP steps which only use the clauses of their hints, so proofs with
+//! other steps cannot be split (see [`check_rup_only`]).
+
+use std::io::{Read, Write, BufRead, BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use crate::{HashMap, HashSet, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::{read_dimacs, Clause};
+use crate::elab::check_rup_only;
+use crate::parser::{Mode, Bin, Ascii, LRATParser, LRATStep, LRATStepRef};
+use crate::serialize::{ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
+
+fn parse_lrat<M: Mode>(r: impl Read, num_clauses: u64
+) -> impl Iterator<Item=Result<(u64, LRATStep)>> {
+  LRATParser::from(M::default(), BufReader::new(r).bytes()).after(num_clauses)
+}
+
+/// Keeps track of the current segment. Segment `k` consists of the additions with index
+/// in `cuts[k]..cuts[k+1]`, together with the deletions which follow them.
+struct Cuts {
+  cuts: Vec<u64>,
+  adds: u64,
+  seg: usize,
+}
+
+impl Cuts {
+  fn new(num_adds: u64, num_segs: usize) -> Self {
+    let cuts = (0..=num_segs as u64).map(|k| k * num_adds / num_segs as u64).collect();
+    Cuts { cuts, adds: 0, seg: 0 }
+  }
+
+  /// Advance past an addition step, returning true if it starts a new segment.
+  fn add(&mut self) -> bool {
+    self.adds += 1;
+    let new = self.adds > 1 && self.adds - 1 == self.cuts[self.seg + 1];
+    if new { self.seg += 1 }
+    new
+  }
+}
+
+/// Count the addition steps of the proof, up to the empty clause.
+fn count_adds<M: Mode>(r: impl Read, num_clauses: u64) -> Result<u64> {
+  let mut adds = 0;
+  for s in parse_lrat::<M>(r, num_clauses) {
+    if let (_, LRATStep::Add(add, _)) = s? {
+      adds += 1;
+      if add.0.is_empty() { return Ok(adds) }
+    }
+  }
+  Err(FratError::NoEmptyClause)
+}
+
+/// The imports and exports of each segment, by their IDs in the original proof.
+struct Plan {
+  imports: Vec<Vec<u64>>,
+  exports: Vec<HashSet<u64>>,
+}
+
+fn plan<M: Mode>(r: impl Read, num_clauses: u64, mut cuts: Cuts) -> Result<Plan> {
+  let num_segs = cuts.cuts.len() - 1;
+  let mut imports = vec![HashSet::default(); num_segs];
+  let mut exports = vec![HashSet::default(); num_segs];
+  // The segment which derived each live clause, or `None` for the original clauses
+  let mut def: HashMap<u64, Option<usize>> = (1..=num_clauses).map(|i| (i, None)).collect();
+  for s in parse_lrat::<M>(r, num_clauses) {
+    match s? {
+      (_, LRATStep::Comment(_)) => {}
+      (i, LRATStep::Add(add, hint)) => {
+        cuts.add();
+        check_rup_only(i, &add, &hint)?;
+        for &h in &*hint {
+          match *def.get(&(h as u64)).ok_or(FratError::MissingClause { step: i, id: h as u64 })? {
+            Some(j) if j == cuts.seg => {}
+            d => {
+              imports[cuts.seg].insert(h as u64);
+              if let Some(j) = d { exports[j].insert(h as u64); }
+            }
+          }
+        }
+        if add.0.is_empty() { break }
+        def.insert(i, Some(cuts.seg));
+      }
+      (_, LRATStep::Del(ids)) => for c in ids { def.remove(&c); },
+    }
+  }
+  let imports = imports.into_iter().map(|s| {
+    let mut v: Vec<_> = s.into_iter().collect();
+    v.sort_unstable();
+    v
+  }).collect();
+  Ok(Plan { imports, exports })
+}
+
+/// The segment being written.
+struct Segment {
+  cnf: String,
+  lrat: String,
+  w: ModeWriter<bool, BufWriter<File>>,
+  /// The local IDs of the clauses live in this segment
+  ids: HashMap<u64, u64>,
+  last: u64,
+}
+
+impl Segment {
+  fn start(prefix: &str, k: usize, vars: usize, imports: &[u64],
+    lits: &HashMap<u64, Box<[i64]>>, out_bin: bool
+  ) -> Result<Self> {
+    let cnf = format!("{}.{}.cnf", prefix, k + 1);
+    let lrat = format!("{}.{}.lrat", prefix, k + 1);
+    let mut w = BufWriter::new(File::create(&cnf)?);
+    writeln!(w, "p cnf {} {}", vars, imports.len())?;
+    let mut ids = HashMap::default();
+    for (j, &i) in imports.iter().enumerate() {
+      for lit in &*lits[&i] { write!(w, "{} ", lit)? }
+      writeln!(w, "0")?;
+      ids.insert(i, j as u64 + 1);
+    }
+    w.flush()?;
+    let w = ModeWriter(out_bin, BufWriter::new(File::create(&lrat)?));
+    Ok(Segment { cnf, lrat, w, ids, last: imports.len() as u64 })
+  }
+
+  /// Finish the segment, and write its entry in the manifest.
+  fn finish(mut self, k: usize, imports: &[u64], exports: &HashSet<u64>,
+    manifest: &mut impl Write
+  ) -> Result<()> {
+    self.w.flush()?;
+    let name = |s: &str| Path::new(s).file_name().unwrap().to_string_lossy().into_owned();
+    writeln!(manifest, "s {} {} {}", k + 1, name(&self.cnf), name(&self.lrat))?;
+    write!(manifest, "i")?;
+    for i in imports { write!(manifest, " {}", i)? }
+    writeln!(manifest, " 0")?;
+    let mut exports: Vec<_> = exports.iter().map(|&i| (self.ids[&i], i)).collect();
+    exports.sort_unstable();
+    write!(manifest, "e")?;
+    for (j, i) in exports { write!(manifest, " {} {}", j, i)? }
+    writeln!(manifest, " 0")?;
+    Ok(())
+  }
+}
+
+/// Split the LRAT proof of `cnf` into `num_segs` segments, written to `PREFIX.K.cnf`
+/// and `PREFIX.K.lrat`, with the manifest in `PREFIX.manifest`.
+/// `open` opens the proof, which is read three times.
+pub fn split<M: Mode, R: Read>(vars: usize, cnf: Vec<Clause>, open: impl Fn() -> Result<R>,
+  num_segs: usize, prefix: &str, out_bin: bool
+) -> Result<usize> {
+  let num_clauses = cnf.len() as u64;
+  let num_adds = count_adds::<M>(open()?, num_clauses)?;
+  let num_segs = num_segs.min(num_adds as usize);
+  let plan = plan::<M>(open()?, num_clauses, Cuts::new(num_adds, num_segs))?;
+
+  // The clauses which are imported into some segment
+  let wanted: HashSet<u64> = plan.imports.iter().flatten().copied().collect();
+  let mut lits: HashMap<u64, Box<[i64]>> = HashMap::default();
+  for (i, c) in (1..).zip(cnf) {
+    if wanted.contains(&i) { lits.insert(i, c); }
+  }
+  let mut manifest = BufWriter::new(File::create(format!("{}.manifest", prefix))?);
+  writeln!(manifest, "c frat-rs split manifest")?;
+  writeln!(manifest, "p split {}", num_segs)?;
+  let mut cuts = Cuts::new(num_adds, num_segs);
+  let mut seg = Segment::start(prefix, 0, vars, &plan.imports[0], &lits, out_bin)?;
+  for s in parse_lrat::<M>(open()?, num_clauses) {
+    match s? {
+      (_, LRATStep::Comment(s)) => LRATStepRef::Comment(seg.last, &s).write(&mut seg.w)?,
+      (i, LRATStep::Add(add, hint)) => {
+        if cuts.add() {
+          let k = cuts.seg;
+          seg.finish(k - 1, &plan.imports[k - 1], &plan.exports[k - 1], &mut manifest)?;
+          seg = Segment::start(prefix, k, vars, &plan.imports[k], &lits, out_bin)?;
+        }
+        let hint = hint.iter().map(|&h| seg.ids[&(h as u64)] as i64).collect::<Vec<_>>();
+        seg.last += 1;
+        LRATStepRef::Add(seg.last, add.as_ref(), &hint).write(&mut seg.w)?;
+        if add.0.is_empty() { break }
+        seg.ids.insert(i, seg.last);
+        if wanted.contains(&i) { lits.insert(i, add.0.into()); }
+      }
+      (_, LRATStep::Del(ids)) => {
+        let ids: Vec<_> = ids.into_iter().filter_map(|c| {
+          lits.remove(&c);
+          seg.ids.remove(&c)
+        }).collect();
+        if !ids.is_empty() { LRATStepRef::Del(seg.last, &ids).write(&mut seg.w)? }
+      }
+    }
+  }
+  let k = num_segs - 1;
+  seg.finish(k, &plan.imports[k], &plan.exports[k], &mut manifest)?;
+  manifest.flush()?;
+  Ok(num_segs)
+}
+
+/// An entry of the manifest.
+struct Entry {
+  cnf: String,
+  lrat: String,
+  imports: Vec<u64>,
+  /// Pairs of local and original clause IDs
+  exports: Vec<(u64, u64)>,
+}
+
+fn read_manifest(r: impl Read) -> Result<Vec<Entry>> {
+  let bad = |line: &str| FratError::Malformed(format!("bad line in manifest: '{}'", line));
+  let nums = |it: std::str::SplitWhitespace<'_>, line: &str| -> Result<Vec<u64>> {
+    let mut v = vec![];
+    for s in it {
+      match s.parse() {
+        Ok(0) => return Ok(v),
+        Ok(i) => v.push(i),
+        Err(_) => break,
+      }
+    }
+    Err(bad(line))
+  };
+  let mut entries: Vec<Entry> = vec![];
+  for line in BufReader::new(r).lines() {
+    let line = line?;
+    let mut it = line.split_whitespace();
+    match it.next() {
+      None | Some("c") | Some("p") => {}
+      Some("s") => match (it.next(), it.next(), it.next()) {
+        (Some(k), Some(cnf), Some(lrat)) if k.parse() == Ok(entries.len() + 1) =>
+          entries.push(Entry { cnf: cnf.into(), lrat: lrat.into(), imports: vec![], exports: vec![] }),
+        _ => return Err(bad(&line)),
+      },
+      Some("i") => entries.last_mut().ok_or_else(|| bad(&line))?.imports = nums(it, &line)?,
+      Some("e") => {
+        let v = nums(it, &line)?;
+        if v.len() % 2 != 0 { return Err(bad(&line)) }
+        entries.last_mut().ok_or_else(|| bad(&line))?.exports =
+          v.chunks(2).map(|p| (p[0], p[1])).collect();
+      }
+      Some(_) => return Err(bad(&line)),
+    }
+  }
+  Ok(entries)
+}
+
+/// Read the lemmas of `lrat` with the given local IDs, up to the empty clause.
+/// Returns true if the empty clause was derived.
+fn read_exports<M: Mode>(r: impl Read, num_clauses: u64, exports: &mut HashMap<u64, Option<Clause>>
+) -> Result<bool> {
+  for s in parse_lrat::<M>(r, num_clauses) {
+    if let (i, LRATStep::Add(add, _)) = s? {
+      if add.0.is_empty() { return Ok(true) }
+      if let Some(c) = exports.get_mut(&i) { *c = Some(add.0.into()) }
+    }
+  }
+  Ok(false)
+}
+
+/// Check that the segments listed in the manifest compose to a refutation of `cnf`.
+/// This does not check the segments themselves, which is done by [`check_lrat`] on each
+/// segment, allowing all but the last segment to end without the empty clause.
+///
+/// [`check_lrat`]: crate::elab::check_lrat
+pub fn check_manifest(cnf: &[Clause], manifest: impl AsRef<Path>) -> Result<usize> {
+  let manifest = manifest.as_ref();
+  let dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+  let entries = read_manifest(compress::open(manifest)?)?;
+  // The lemmas exported by the segments so far
+  let mut exported: HashMap<u64, Clause> = HashMap::default();
+  let mut refuted = false;
+  for (k, e) in entries.iter().enumerate() {
+    let (_vars, seg) = read_dimacs(compress::open(dir.join(&e.cnf))?)?;
+    if seg.len() != e.imports.len() {
+      return Err(FratError::Malformed(format!(
+        "segment {} has {} clauses, but {} imports", k + 1, seg.len(), e.imports.len())))
+    }
+    for (j, (c, &i)) in seg.iter().zip(&e.imports).enumerate() {
+      let src = match i.checked_sub(1).and_then(|i| cnf.get(i as usize)) {
+        Some(src) => src,
+        None => exported.get(&i).ok_or_else(|| FratError::Malformed(format!(
+          "clause {} of segment {} is clause {}, which is not exported by an earlier segment",
+          j + 1, k + 1, i)))?,
+      };
+      if c != src {
+        return Err(FratError::Malformed(format!(
+          "clause {} of segment {} is {:?}, but it is imported as clause {}, which is {:?}",
+          j + 1, k + 1, c, i, src)))
+      }
+    }
+    let mut exports = e.exports.iter().map(|&(j, _)| (j, None)).collect();
+    let (bin, r) = compress::open_detect(dir.join(&e.lrat))?;
+    let n = seg.len() as u64;
+    refuted = if bin { read_exports::<Bin>(r, n, &mut exports)? }
+      else { read_exports::<Ascii>(r, n, &mut exports)? };
+    for &(j, i) in &e.exports {
+      let c = exports.get_mut(&j).and_then(Option::take).ok_or_else(|| FratError::Malformed(
+        format!("segment {} does not derive clause {} (exported as {})", k + 1, j, i)))?;
+      exported.insert(i, c);
+    }
+  }
+  if !refuted { return Err(FratError::NoEmptyClause) }
+  Ok(entries.len())
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "split", "DIMACSFILE LRATFILE NUM PREFIX [--ascii|--binary]");
+  let (dimacs, lrat) = (args.arg()?, args.arg()?);
+  let num_segs = match args.arg()?.parse::<usize>() {
+    Ok(n) if n > 0 => n,
+    _ => return Err(args.usage())
+  };
+  let prefix = args.arg()?;
+  let (bin, _) = compress::open_detect(&lrat)?;
+  // By default the output is in the same mode as the input
+  let out_bin = output_mode(&mut args, bin);
+  args.finish()?;
+  let (vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let open = || Ok(compress::open_detect(&lrat)?.1);
+  let n = if bin { split::<Bin, _>(vars, cnf, open, num_segs, &prefix, out_bin)? }
+    else { split::<Ascii, _>(vars, cnf, open, num_segs, &prefix, out_bin)? };
+  println!("wrote {} segments", n);
+  Ok(())
+}
+
+pub fn splitchk(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "splitchk", "DIMACSFILE MANIFEST");
+  let (dimacs, manifest) = (args.arg()?, args.arg()?);
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+  let n = check_manifest(&cnf, manifest)?;
+  println!("{} segments compose to a refutation", n);
+  Ok(())
+}
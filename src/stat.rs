@@ -1,7 +1,6 @@
 use std::fs::File;
-use std::process::exit;
-use std::io;
-use crate::HashMap;
+use crate::{HashMap, compress, cli::Args};
+use crate::error::{FratError, Result};
 use crate::parser::{detect_binary, Mode, Ascii, Bin};
 use crate::backparser::*;
 
@@ -11,7 +10,7 @@ fn subsumes(clause: &[i64], clause2: &[i64]) -> bool {
   clause2.iter().all(|lit2| clause.contains(lit2))
 }
 
-pub fn check_proof(mode: impl Mode, proof: File) -> io::Result<()> {
+pub fn check_proof(mode: impl Mode, proof: File) -> Result<()> {
   let mut bp = StepIter(BackParser::new(mode, proof)?).peekable();
   let (mut orig, mut added, mut deleted, mut fin) = (0i64, 0i64, 0i64, 0i64);
   let (mut dirty_orig, mut dirty_add, mut double_del, mut double_fin) = (0i64, 0i64, 0i64, 0i64);
@@ -21,7 +20,7 @@ pub fn check_proof(mode: impl Mode, proof: File) -> io::Result<()> {
   let mut bad = false;
   while let Some(s) = bp.next() {
     // println!("{:?}", s);
-    match s {
+    match s? {
       Step::Comment(_) => {}
       Step::Orig(i, lits) => {
         orig += 1;
@@ -39,7 +38,7 @@ pub fn check_proof(mode: impl Mode, proof: File) -> io::Result<()> {
       Step::Add(i, step, p) => {
         added += 1;
         if p.is_none() { missing += 1 }
-        if let Some(Step::Todo(_)) = bp.peek() {} else if p.is_none() {
+        if let Some(Ok(Step::Todo(_))) = bp.peek() {} else if p.is_none() {
           *todos.entry(0).or_insert(0i64) += 1;
           // eprintln!("added clause {} {:?} has no proof and no todo", i, lits);
         }
@@ -52,7 +51,8 @@ pub fn check_proof(mode: impl Mode, proof: File) -> io::Result<()> {
           if need {
             if let Some(Proof::LRAT(steps)) = p {
               for s in steps {
-                let needed = &mut active.get_mut(&s.unsigned_abs()).expect("bad LRAT proof").0;
+                let needed = &mut active.get_mut(&s.unsigned_abs())
+                  .ok_or(FratError::MissingClause { step: i, id: s.unsigned_abs() })?.0;
                 if !*needed {
                   // unimplemented!();
                   *needed = true;
@@ -117,12 +117,15 @@ pub fn check_proof(mode: impl Mode, proof: File) -> io::Result<()> {
     eprintln!("{} unjustified", active.len());
     bad = true;
   }
-  if bad { exit(1) }
+  if bad { return Err(FratError::Malformed("FRAT file is not well formed".into())) }
   Ok(())
 }
 
-pub fn main<I: Iterator<Item=String>>(mut args: I) -> io::Result<()> {
-  let mut proof = File::open(args.next().expect("missing proof file"))?;
+pub fn main<I: Iterator<Item=String>>(args: I) -> Result<()> {
+  let mut args = Args::new(args, "stat", "FRATFILE");
+  let path = args.arg()?;
+  args.finish()?;
+  let mut proof = compress::open_seekable(path)?;
   let bin = detect_binary(&mut proof)?;
   if bin { check_proof(Bin, proof) }
   else { check_proof(Ascii, proof) }
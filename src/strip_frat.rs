@@ -1,23 +1,91 @@
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::io::{Read, Write, BufWriter};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use crate::compress;
+use crate::error::Result;
+use crate::parser::{Mode, Bin, Ascii, FwdParser, Step, StepRef, Proof};
+use crate::backparser::FwdStepIter;
+use crate::serialize::{ModeWrite, ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
 
-pub fn main(mut args: impl Iterator<Item=String>) -> io::Result<()> {
-  let mut r = BufReader::new(File::open(&args.next().expect("missing input file"))?)
-    .bytes().map(Result::unwrap);
-  let w = &mut BufWriter::new(File::create(&args.next().expect("missing output file"))?);
+/// Which annotations to remove.
+pub enum Strip {
+  /// All `l` hints and `t` annotations
+  All,
+  /// The `l` hints which have a negative ID, that is, RAT hints
+  Rat,
+  /// Each `l` hint with the given probability
+  Random(f64, Box<StdRng>),
+  /// The `l` hints of the steps whose ID is in one of the (inclusive) ranges
+  Ids(Vec<(u64, u64)>),
+  /// Only the `t` annotations
+  Todo,
+}
 
-  loop {
-    match r.next() {
-      None => return Ok(()),
-      Some(c) if c == b't' || c == b'l' => while r.next() != Some(0) {},
-      Some(c) => {
-        w.write_all(&[c])?;
-        loop {
-          let c = r.next().unwrap();
-          w.write_all(&[c])?;
-          if c == 0 {break}
-        }
+impl Strip {
+  fn hint(&mut self, i: u64, hint: &[i64]) -> bool {
+    match self {
+      Strip::All => true,
+      Strip::Rat => hint.iter().any(|&h| h < 0),
+      Strip::Random(p, rng) => rng.gen_bool(*p),
+      Strip::Ids(ranges) => ranges.iter().any(|&(lo, hi)| lo <= i && i <= hi),
+      Strip::Todo => false,
+    }
+  }
+
+  fn todo(&self) -> bool { matches!(self, Strip::All | Strip::Todo) }
+}
+
+/// Copy the FRAT proof `frat` to `w`, removing the annotations selected by `strip`.
+/// Returns the number of annotations removed.
+pub fn strip_frat<M>(mode: impl Mode, frat: impl Read, mut strip: Strip,
+  w: &mut impl ModeWrite<M>
+) -> Result<u64>
+where for<'a> StepRef<'a>: Serialize<M> {
+  let mut removed = 0;
+  for s in FwdStepIter::new(FwdParser::new(mode, frat)) {
+    match s? {
+      Step::Add(i, add, Some(Proof::LRAT(hint))) if strip.hint(i, &hint) => {
+        removed += 1;
+        Step::Add(i, add, None).write(w)?
       }
+      Step::Todo(_) if strip.todo() => removed += 1,
+      s => s.write(w)?,
     }
   }
-}
\ No newline at end of file
+  Ok(removed)
+}
+
+/// Parse a comma separated list of IDs and ID ranges such as `1-100,150,200-`.
+fn parse_id_ranges(s: &str) -> Option<Vec<(u64, u64)>> {
+  s.split(',').map(|r| match r.split_once('-') {
+    None => r.parse().ok().map(|i| (i, i)),
+    Some((lo, "")) => lo.parse().ok().map(|lo| (lo, u64::MAX)),
+    Some((lo, hi)) => Some((lo.parse().ok()?, hi.parse().ok()?)),
+  }).collect()
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "strip-frat", "FRATFILE NEWFRATFILE \
+    [--rat | --random PCT [--seed NUM] | --ids RANGES | --todo] [--ascii|--binary]");
+  let (frat_path, out_path) = (args.arg()?, args.arg()?);
+  let strip = if args.flag("--rat") { Strip::Rat }
+  else if let Some(pct) = args.parsed("--random", |s| s.parse::<f64>().ok()
+    .filter(|p| (0.0..=100.0).contains(p)))? {
+    let rng = match args.parsed("--seed", |s| s.parse().ok())? {
+      Some(seed) => StdRng::seed_from_u64(seed),
+      None => StdRng::from_entropy()
+    };
+    Strip::Random(pct / 100.0, Box::new(rng))
+  } else if let Some(ids) = args.parsed("--ids", parse_id_ranges)? { Strip::Ids(ids) }
+  else if args.flag("--todo") { Strip::Todo }
+  else { Strip::All };
+  let (bin, frat) = compress::open_detect(frat_path)?;
+  // By default the output is in the same mode as the input
+  let out_bin = output_mode(&mut args, bin);
+  args.finish()?;
+  let w = &mut ModeWriter(out_bin, BufWriter::new(File::create(out_path)?));
+  let removed = if bin { strip_frat(Bin, frat, strip, w)? } else { strip_frat(Ascii, frat, strip, w)? };
+  println!("removed {} annotations", removed);
+  Ok(w.flush()?)
+}
@@ -0,0 +1,80 @@
+use std::io::{Read, BufReader, Write, BufWriter};
+use std::fs::File;
+use crate::{HashMap, compress};
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs;
+use crate::parser::{Mode, Bin, Ascii, FwdParser, Segment, LRATParser, LRATStep,
+  DRATStepRef, AddStepRef};
+use crate::serialize::{ModeWrite, ModeWriter, Serialize, output_mode};
+use crate::cli::Args;
+
+/// Write the steps of a FRAT proof as DRAT, up to the empty clause. Original and final
+/// steps and hints are dropped, and deletions already carry the clause literals.
+fn frat_to_drat<O>(mode: impl Mode, frat: impl Read, w: &mut impl ModeWrite<O>) -> Result<()>
+where for<'a> DRATStepRef<'a>: Serialize<O> {
+  for s in FwdParser::new(mode, frat) {
+    match s? {
+      Segment::Comment(s) => DRATStepRef::Comment(&s).write(w)?,
+      // PR steps are kept as is, with the witness after the lemma
+      Segment::Add(_, ls) => {
+        DRATStepRef::Add(AddStepRef::One(&ls)).write(w)?;
+        if ls.is_empty() { return Ok(()) }
+      }
+      Segment::Del(_, ls) => DRATStepRef::Del(&ls).write(w)?,
+      Segment::Orig(..) | Segment::LProof(_) | Segment::Reloc(_) |
+      Segment::Final(..) | Segment::Todo(_) => {}
+    }
+  }
+  Err(FratError::NoEmptyClause)
+}
+
+/// Write the steps of an LRAT proof of `cnf` as DRAT, up to the empty clause. The clause
+/// of each ID is kept so that deletions can be written as literals.
+fn lrat_to_drat<O>(mode: impl Mode, cnf: Vec<Box<[i64]>>,
+  lrat: impl Iterator<Item=std::io::Result<u8>>, w: &mut impl ModeWrite<O>
+) -> Result<()>
+where for<'a> DRATStepRef<'a>: Serialize<O> {
+  let mut clauses: HashMap<u64, Box<[i64]>> = (1..).zip(cnf).collect();
+  for s in LRATParser::from(mode, lrat).after(clauses.len() as u64) {
+    match s? {
+      (_, LRATStep::Comment(s)) => DRATStepRef::Comment(&s).write(w)?,
+      (i, LRATStep::Add(add, _)) => {
+        DRATStepRef::Add(add.as_ref()).write(w)?;
+        let ls = add.parse().lemma();
+        if ls.is_empty() { return Ok(()) }
+        if clauses.insert(i, ls.into()).is_some() {
+          return Err(FratError::DuplicateClause { step: i, id: i })
+        }
+      }
+      (i, LRATStep::Del(ids)) => for c in ids {
+        let ls = clauses.remove(&c).ok_or(FratError::MissingClause { step: i, id: c })?;
+        DRATStepRef::Del(&ls).write(w)?
+      }
+    }
+  }
+  Err(FratError::NoEmptyClause)
+}
+
+fn to_drat(proof: String, dimacs: Option<String>, w: &mut impl ModeWrite<bool>) -> Result<()> {
+  let (bin, r) = compress::open_detect(proof)?;
+  match dimacs {
+    None if bin => frat_to_drat(Bin, r, w),
+    None => frat_to_drat(Ascii, r, w),
+    Some(dimacs) => {
+      let (_vars, cnf) = read_dimacs(compress::open(dimacs)?)?;
+      let lrat = BufReader::new(r).bytes();
+      if bin { lrat_to_drat(Bin, cnf, lrat, w) } else { lrat_to_drat(Ascii, cnf, lrat, w) }
+    }
+  }
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "to-drat", "PROOFFILE DRATFILE [--lrat DIMACSFILE] [--ascii|--binary]");
+  let (proof, out) = (args.arg()?, args.arg()?);
+  let dimacs = args.value("--lrat")?;
+  let binary = output_mode(&mut args, false);
+  args.finish()?;
+  let w = &mut ModeWriter(binary, BufWriter::new(File::create(out)?));
+  to_drat(proof, dimacs, w)?;
+  Ok(w.flush()?)
+}
@@ -0,0 +1,132 @@
+//! Conversion of LRAT proofs (or FRAT proofs, after elaboration) to VeriPB proofs.
+//!
+//! The input CNF is read by VeriPB as the formula, so clause `i` is constraint `i`.
+//! RUP steps become `pol` derivations following the resolution chain of the hint
+//! (a resolution step is an addition followed by saturation), or `u` steps with `--rup`.
+//! RAT and PR steps become `red` steps with the witness as substitution, deletions
+//! become `del id` steps, and the empty clause is concluded with `c`.
+
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::fs::File;
+use crate::HashMap;
+use crate::error::{FratError, Result};
+use crate::dimacs::read_dimacs_map;
+use crate::elab::{elab_to_lrat, dedup_vec};
+use crate::parser::{Mode, Ascii, Bin, LRATParser, LRATStep, AddKind};
+use crate::resolution::Resolver;
+use crate::compress;
+use crate::cli::Args;
+
+/// Write a clause as the constraint `1 l1 1 l2 ... >= 1 ;`, without a newline.
+fn write_clause(w: &mut impl Write, lits: &[i64]) -> Result<()> {
+  for &lit in lits {
+    if lit < 0 { write!(w, "1 ~x{} ", -lit)? } else { write!(w, "1 x{} ", lit)? }
+  }
+  write!(w, ">= 1 ;")?;
+  Ok(())
+}
+
+/// Write an LRAT proof of `cnf` as a VeriPB proof. If `rup` is set, RUP steps are
+/// written as `u` steps instead of explicit `pol` derivations.
+pub fn to_veripb(cnf: &[Box<[i64]>], mode: impl Mode,
+  lrat: impl Iterator<Item=std::io::Result<u8>>, rup: bool, w: &mut impl Write
+) -> Result<()> {
+  writeln!(w, "pseudo-Boolean proof version 1.2")?;
+  writeln!(w, "f {}", cnf.len())?;
+  let mut r = Resolver::from_cnf(cnf);
+  // The VeriPB constraint ID of each LRAT clause ID
+  let mut ids: HashMap<u64, u64> = (1..=cnf.len() as u64).map(|i| (i, i)).collect();
+  // The number of LRAT clauses sharing each constraint, if more than one
+  let mut shared: HashMap<u64, u64> = HashMap::default();
+  let mut next = cnf.len() as u64;
+  let id = |ids: &HashMap<u64, u64>, step, i| ids.get(&i).copied()
+    .ok_or(FratError::MissingClause { step, id: i });
+  for (n, s) in (1..).zip(LRATParser::from(mode, lrat).after(cnf.len() as u64)) {
+    match s? {
+      (_, LRATStep::Comment(s)) => writeln!(w, "* {}", s)?,
+
+      (i, LRATStep::Add(add, hints)) => {
+        let (ls, witness) = match add.parse() {
+          AddKind::RAT(ls) if hints.iter().any(|&h| h < 0) => match ls.first() {
+            Some(pivot) => (ls, Some(std::slice::from_ref(pivot))),
+            None => return Err(FratError::Malformed(format!(
+              "step {}: the empty clause has no pivot for a RAT step", i))),
+          },
+          AddKind::RAT(ls) => (ls, None),
+          AddKind::PR(ls, wit) => (ls, Some(wit)),
+        };
+        let lits: Box<[i64]> = match witness {
+          Some(wit) => {
+            write!(w, "red ")?;
+            write_clause(w, ls)?;
+            for &lit in wit { write!(w, " x{} -> {}", lit.abs(), (lit > 0) as u8)? }
+            writeln!(w)?;
+            ls.into()
+          }
+          None if rup => { write!(w, "u ")?; write_clause(w, ls)?; writeln!(w)?; ls.into() }
+          None => {
+            let chain = r.chain(i, ls, &hints)?;
+            if chain.links.is_empty() {
+              // The lemma is subsumed by a single clause, so it needs no new constraint
+              let j = id(&ids, i, chain.start)?;
+              *shared.entry(j).or_insert(1) += 1;
+              ids.insert(i, j);
+              r.insert(n, i, chain.resolvent)?;
+              continue
+            }
+            write!(w, "pol {}", id(&ids, i, chain.start)?)?;
+            for &(j, _) in &chain.links { write!(w, " {} + s", id(&ids, i, j)?)? }
+            writeln!(w)?;
+            chain.resolvent
+          }
+        };
+        next += 1;
+        ids.insert(i, next);
+        if lits.is_empty() {
+          writeln!(w, "c {}", next)?;
+          return Ok(())
+        }
+        r.insert(n, i, lits)?;
+      }
+
+      (_, LRATStep::Del(cs)) => {
+        let mut del = vec![];
+        for c in cs {
+          r.remove(n, c)?;
+          let j = ids.remove(&c).ok_or(FratError::MissingClause { step: n, id: c })?;
+          // Constraints shared with a subsumed lemma are only deleted with the last one
+          match shared.get_mut(&j) {
+            Some(k) if *k > 1 => *k -= 1,
+            _ => { shared.remove(&j); del.push(j) }
+          }
+        }
+        if !del.is_empty() {
+          write!(w, "del id")?;
+          for j in del { write!(w, " {}", j)? }
+          writeln!(w)?;
+        }
+      }
+    }
+  }
+  Err(FratError::NoEmptyClause)
+}
+
+pub fn main(args: impl Iterator<Item=String>) -> Result<()> {
+  let mut args = Args::new(args, "to-veripb", "DIMACSFILE PROOFFILE PBPFILE [--lrat] [--rup]");
+  let (dimacs, proof_path, out_path) = (args.arg()?, args.arg()?, args.arg()?);
+  let lrat = args.flag("--lrat");
+  let rup = args.flag("--rup");
+  args.finish()?;
+  let (_vars, cnf) = read_dimacs_map(compress::open(dimacs)?, |mut c| {dedup_vec(&mut c); c.into()})?;
+  let mut w = BufWriter::new(File::create(out_path)?);
+  if lrat {
+    let (bin, lrat) = compress::open_detect(proof_path)?;
+    let lrat = BufReader::new(lrat).bytes();
+    if bin { to_veripb(&cnf, Bin, lrat, rup, &mut w)? } else { to_veripb(&cnf, Ascii, lrat, rup, &mut w)? }
+  } else {
+    let lrat = elab_to_lrat(proof_path, &cnf)?;
+    to_veripb(&cnf, Bin, lrat.into_iter().map(Ok), rup, &mut w)?
+  }
+  w.flush()?;
+  Ok(())
+}
@@ -0,0 +1,143 @@
+//! A FRAT writer for solvers, which assigns clause IDs and keeps track of the
+//! live clause set, so that the `d` and `f` steps always carry the right literals.
+
+use std::io::Write;
+use crate::{HashMap, HashSet};
+use crate::error::{FratError, Result};
+use crate::parser::{StepRef, AddStepRef, ProofRef};
+use crate::serialize::{Serialize, ModeWriter};
+
+pub struct FratWriter<M, W: Write> {
+  w: ModeWriter<M, W>,
+  /// The last ID that was handed out
+  last: u64,
+  /// The number of steps written so far
+  steps: u64,
+  /// The currently live clauses, by ID
+  live: HashMap<u64, Box<[i64]>>,
+}
+
+impl<M, W: Write> FratWriter<M, W> where for<'a> StepRef<'a>: Serialize<M> {
+  pub fn new(mode: M, w: W) -> Self {
+    FratWriter { w: ModeWriter(mode, w), last: 0, steps: 0, live: HashMap::default() }
+  }
+
+  fn fresh(&mut self) -> u64 { self.last += 1; self.last }
+
+  /// The number of the step being written, for error messages.
+  fn step(&self) -> u64 { self.steps + 1 }
+
+  fn write(&mut self, s: StepRef<'_>) -> Result<()> {
+    s.write(&mut self.w)?;
+    self.steps += 1;
+    Ok(())
+  }
+
+  /// Check that `lits` can be written: a 0 would end the step early, and
+  /// `i64::MIN` has no negation.
+  fn check_lits(&self, what: &str, lits: &[i64]) -> Result<()> {
+    match lits.iter().find(|&&l| l == 0 || l == i64::MIN) {
+      Some(l) => Err(FratError::Malformed(format!("at {}: {} contains the literal {}", self.step(), what, l))),
+      None => Ok(()),
+    }
+  }
+
+  /// Check that every clause in `hint` is live. Negative IDs name RAT candidates.
+  fn check_hint(&self, hint: &[i64]) -> Result<()> {
+    for &h in hint {
+      if h == 0 || h == i64::MIN {
+        return Err(FratError::Malformed(format!("at {}: hint contains the ID {}", self.step(), h)))
+      }
+      if !self.live.contains_key(&h.unsigned_abs()) {
+        return Err(FratError::MissingClause { step: self.step(), id: h.unsigned_abs() })
+      }
+    }
+    Ok(())
+  }
+
+  /// The number of clauses which are currently live.
+  pub fn live(&self) -> usize { self.live.len() }
+
+  /// Write an original clause (an `o` step), and return its ID.
+  /// Literals must be nonzero.
+  pub fn orig(&mut self, lits: &[i64]) -> Result<u64> {
+    self.check_lits("clause", lits)?;
+    let i = self.fresh();
+    self.write(StepRef::Orig(i, lits))?;
+    self.live.insert(i, lits.into());
+    Ok(i)
+  }
+
+  /// Write a derived clause (an `a` step), and return its ID. `hint` is an
+  /// optional LRAT-style proof (an `l` step), and `witness` makes this a PR step.
+  /// The witness must start with the first literal of `lits`, which is the pivot.
+  /// Literals must be nonzero, and the hint must only name live clauses.
+  pub fn add(&mut self, lits: &[i64], hint: Option<&[i64]>, witness: Option<&[i64]>) -> Result<u64> {
+    self.check_lits("clause", lits)?;
+    if let Some(wit) = witness { self.check_lits("witness", wit)? }
+    if let Some(hint) = hint { self.check_hint(hint)? }
+    let step = match witness {
+      None => AddStepRef::One(lits),
+      Some(wit) => {
+        if lits.is_empty() || lits.first() != wit.first() {
+          return Err(FratError::Malformed(
+            "PR witness must start with the first literal of the clause".into()))
+        }
+        AddStepRef::Two(lits, wit)
+      }
+    };
+    let i = self.fresh();
+    self.write(StepRef::Add(i, step, hint.map(ProofRef::LRAT)))?;
+    self.live.insert(i, lits.into());
+    Ok(i)
+  }
+
+  /// Delete a live clause (a `d` step).
+  pub fn del(&mut self, i: u64) -> Result<()> {
+    let lits = self.live.remove(&i)
+      .ok_or(FratError::MissingClause { step: self.step(), id: i })?;
+    self.write(StepRef::Del(i, &lits))
+  }
+
+  /// Rename clauses (an `r` step). Each pair `(from, to)` moves the live clause
+  /// `from` to ID `to`. IDs handed out later are always larger than any `to`.
+  /// If this fails, nothing is moved and no step is written.
+  pub fn reloc(&mut self, relocs: &[(u64, u64)]) -> Result<()> {
+    let mut froms = HashSet::default();
+    for &(from, _) in relocs {
+      if !self.live.contains_key(&from) || !froms.insert(from) {
+        return Err(FratError::MissingClause { step: self.step(), id: from })
+      }
+    }
+    let mut tos = HashSet::default();
+    for &(_, to) in relocs {
+      if !tos.insert(to) || (self.live.contains_key(&to) && !froms.contains(&to)) {
+        return Err(FratError::DuplicateClause { step: self.step(), id: to })
+      }
+    }
+    self.write(StepRef::Reloc(relocs))?;
+    let moved: Vec<_> = relocs.iter()
+      .map(|&(from, to)| (to, self.live.remove(&from).unwrap())).collect();
+    for (to, lits) in moved {
+      self.live.insert(to, lits);
+      self.last = self.last.max(to);
+    }
+    Ok(())
+  }
+
+  /// Write a `t` step, marking the next step as unjustified for the given reason.
+  pub fn todo(&mut self, i: u64) -> Result<()> { self.write(StepRef::Todo(i)) }
+
+  pub fn comment(&mut self, s: &str) -> Result<()> { self.write(StepRef::Comment(s)) }
+
+  /// Finalize all live clauses (the `f` block) and flush the output.
+  pub fn finish(mut self) -> Result<W> {
+    let mut live: Vec<_> = self.live.drain().collect();
+    live.sort_unstable_by_key(|&(i, _)| i);
+    for (i, lits) in live.into_iter().rev() {
+      StepRef::Final(i, &lits).write(&mut self.w)?
+    }
+    self.w.flush()?;
+    Ok(self.w.1)
+  }
+}
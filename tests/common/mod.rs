@@ -0,0 +1,240 @@
+//! Helpers for the integration tests: small unsatisfiable CNFs with DRAT proofs,
+//! and a runner for the `frat-rs` binary.
+#![allow(dead_code)]
+
+use std::fmt::Write;
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// A DRAT proof, as a list of steps `(is_deletion, clause)`.
+pub type Drat = Vec<(bool, Vec<i64>)>;
+
+/// A temporary directory which is removed when dropped.
+pub struct Dir(tempfile::TempDir);
+
+impl Dir {
+  pub fn new() -> Self { Dir(tempfile::tempdir().unwrap()) }
+
+  /// The path of `name` in this directory, as a string (for use as an argument).
+  pub fn path(&self, name: &str) -> String {
+    let p: PathBuf = self.0.path().join(name);
+    p.into_os_string().into_string().unwrap()
+  }
+
+  pub fn write(&self, name: &str, data: impl AsRef<[u8]>) -> String {
+    let p = self.path(name);
+    fs::write(&p, data).unwrap();
+    p
+  }
+
+  pub fn read(&self, name: &str) -> Vec<u8> { fs::read(self.path(name)).unwrap() }
+
+  pub fn read_str(&self, name: &str) -> String { String::from_utf8(self.read(name)).unwrap() }
+}
+
+/// Run `frat-rs` with the given arguments.
+pub fn run(args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_frat-rs")).args(args).output().unwrap()
+}
+
+/// Run `frat-rs` with the given arguments and standard input, and panic if it fails.
+pub fn ok_stdin(args: &[&str], input: &[u8]) -> String {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_frat-rs")).args(args)
+    .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+  // written from another thread, so that a full stdout pipe cannot block it
+  let mut stdin = child.stdin.take().unwrap();
+  let input = input.to_vec();
+  let writer = std::thread::spawn(move || stdin.write_all(&input));
+  let out = child.wait_with_output().unwrap();
+  writer.join().unwrap().unwrap();
+  let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+  assert!(out.status.success(), "frat-rs {:?} failed with {:?}\nstdout:\n{}\nstderr:\n{}",
+    args, out.status, stdout, String::from_utf8_lossy(&out.stderr));
+  stdout
+}
+
+/// Run `frat-rs` with the given arguments, and return its exit code.
+pub fn code(args: &[&str]) -> i32 { run(args).status.code().expect("killed by a signal") }
+
+/// Run `frat-rs` with the given arguments, and panic if it fails.
+pub fn ok(args: &[&str]) -> String {
+  let out = run(args);
+  let stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+  assert!(out.status.success(), "frat-rs {:?} failed with {:?}\nstdout:\n{}\nstderr:\n{}",
+    args, out.status, stdout, String::from_utf8_lossy(&out.stderr));
+  stdout
+}
+
+/// A random 3-CNF over the variables `1..=vars`.
+pub fn random_cnf(seed: u64, vars: i64, clauses: usize) -> Vec<Vec<i64>> {
+  let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+  let mut rand = move |n: u64| {
+    state ^= state << 13; state ^= state >> 7; state ^= state << 17;
+    state % n
+  };
+  (0..clauses).map(|_| {
+    let mut c: Vec<i64> = vec![];
+    while c.len() < 3 {
+      let v = rand(vars as u64) as i64 + 1;
+      if c.iter().all(|l| l.abs() != v) { c.push(if rand(2) == 0 { v } else { -v }) }
+    }
+    c
+  }).collect()
+}
+
+/// Unit propagation over `db` starting from `assign` (indexed by variable, with
+/// values 0, 1 or -1). Returns false on a conflict.
+pub fn propagate(db: &[Vec<i64>], assign: &mut [i8]) -> bool {
+  let val = |assign: &[i8], l: i64| assign[l.unsigned_abs() as usize] * l.signum() as i8;
+  loop {
+    let mut progress = false;
+    for c in db {
+      if c.iter().any(|&l| val(assign, l) == 1) { continue }
+      let mut open = c.iter().filter(|&&l| val(assign, l) == 0);
+      match (open.next(), open.next()) {
+        (None, _) => return false,
+        (Some(&l), None) => { assign[l.unsigned_abs() as usize] = l.signum() as i8; progress = true }
+        _ => {}
+      }
+    }
+    if !progress { return true }
+  }
+}
+
+/// Refute `cnf` over the variables `1..=vars` by DPLL, returning a DRAT proof
+/// ending with the empty clause.
+/// Every lemma is the negation of a decision prefix, so it is RUP.
+/// Returns `None` if `cnf` is satisfiable.
+pub fn refute(cnf: &[Vec<i64>], vars: i64) -> Option<Drat> {
+  fn go(db: &mut Vec<Vec<i64>>, vars: i64, decisions: &mut Vec<i64>,
+    proof: &mut Drat
+  ) -> bool {
+    let mut assign = vec![0; vars as usize + 1];
+    for &d in &*decisions { assign[d.unsigned_abs() as usize] = d.signum() as i8 }
+    let mut branch = None;
+    let refuted = if !propagate(db, &mut assign) { true }
+    else if let Some(v) = (1..=vars).find(|&v| assign[v as usize] == 0) {
+      branch = Some(v);
+      [v, -v].iter().all(|&l| {
+        decisions.push(l);
+        let ok = go(db, vars, decisions, proof);
+        decisions.pop();
+        ok
+      })
+    } else { false };
+    if refuted {
+      let lemma: Vec<i64> = decisions.iter().map(|&d| -d).collect();
+      db.push(lemma.clone());
+      proof.push((false, lemma.clone()));
+      // the lemmas of the two branches are subsumed by this one
+      if let Some(v) = branch.filter(|_| !lemma.is_empty()) {
+        for &l in &[v, -v] {
+          let mut child = lemma.clone();
+          child.push(-l);
+          let i = db.iter().rposition(|c| *c == child).unwrap();
+          db.remove(i);
+          proof.push((true, child));
+        }
+      }
+    }
+    refuted
+  }
+  let mut db = cnf.to_vec();
+  let mut proof = vec![];
+  if !go(&mut db, vars, &mut vec![], &mut proof) { return None }
+  Some(proof)
+}
+
+/// An unsatisfiable random 3-CNF over 20 variables, and its DRAT refutation.
+pub fn unsat_instance(seed: u64) -> (Vec<Vec<i64>>, Drat) {
+  let cnf = random_cnf(seed, 20, 120);
+  let proof = refute(&cnf, 20).expect("instance is satisfiable");
+  (cnf, proof)
+}
+
+pub fn dimacs(cnf: &[Vec<i64>]) -> String {
+  let vars = cnf.iter().flatten().map(|l| l.abs()).max().unwrap_or(0);
+  let mut s = format!("p cnf {} {}\n", vars, cnf.len());
+  for c in cnf {
+    for l in c { write!(s, "{} ", l).unwrap() }
+    s.push_str("0\n");
+  }
+  s
+}
+
+pub fn drat(proof: &[(bool, Vec<i64>)]) -> String {
+  let mut s = String::new();
+  for (del, c) in proof {
+    if *del { s.push_str("d ") }
+    for l in c { write!(s, "{} ", l).unwrap() }
+    s.push_str("0\n");
+  }
+  s
+}
+
+/// Parse a DIMACS file, checking that the header matches the clauses.
+/// Returns the number of variables and the clauses.
+pub fn parse_dimacs(s: &str) -> (i64, Vec<Vec<i64>>) {
+  let mut lines = s.lines().filter(|l| !l.starts_with('c'));
+  let header: Vec<_> = lines.next().expect("missing header").split_whitespace().collect();
+  assert_eq!(header[..2], ["p", "cnf"], "bad header {:?}", header);
+  let (vars, n): (i64, usize) = (header[2].parse().unwrap(), header[3].parse().unwrap());
+  let mut lits: Vec<i64> = lines.flat_map(|l| l.split_whitespace()).map(|w| w.parse().unwrap()).collect();
+  let cnf: Vec<Vec<i64>> = if lits.is_empty() { vec![] } else {
+    assert_eq!(lits.pop(), Some(0), "last clause is not terminated");
+    lits.split(|&l| l == 0).map(|c| c.to_vec()).collect()
+  };
+  assert_eq!(cnf.len(), n, "header says {} clauses", n);
+  assert!(cnf.iter().flatten().all(|l| l.abs() <= vars), "header says {} variables", vars);
+  (vars, cnf)
+}
+
+/// A step of an ASCII LRAT proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LratStep {
+  /// `id lits 0 hints 0`
+  Add(u64, Vec<i64>, Vec<i64>),
+  /// `id d ids 0`
+  Del(u64, Vec<u64>),
+}
+
+/// Parse an ASCII LRAT proof without comments.
+pub fn parse_lrat(s: &str) -> Vec<LratStep> {
+  s.lines().map(|l| {
+    let mut words = l.split_whitespace();
+    let id = words.next().unwrap().parse().unwrap();
+    let nums = |ws: &mut std::str::SplitWhitespace<'_>| -> Vec<i64> {
+      ws.map(|w| w.parse().unwrap()).take_while(|&n| n != 0).collect()
+    };
+    if words.clone().next() == Some("d") {
+      words.next();
+      LratStep::Del(id, nums(&mut words).into_iter().map(|i| i as u64).collect())
+    } else {
+      let lits = nums(&mut words);
+      LratStep::Add(id, lits, nums(&mut words))
+    }
+  }).collect()
+}
+
+/// Write an ASCII LRAT proof.
+pub fn lrat(steps: &[LratStep]) -> String {
+  let mut s = String::new();
+  for step in steps {
+    match step {
+      LratStep::Add(i, lits, hint) => {
+        write!(s, "{} ", i).unwrap();
+        for l in lits { write!(s, "{} ", l).unwrap() }
+        s.push_str("0 ");
+        for h in hint { write!(s, "{} ", h).unwrap() }
+      }
+      LratStep::Del(i, ids) => {
+        write!(s, "{} d ", i).unwrap();
+        for j in ids { write!(s, "{} ", j).unwrap() }
+      }
+    }
+    s.push_str("0\n");
+  }
+  s
+}
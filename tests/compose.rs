@@ -0,0 +1,31 @@
+//! `compose` of a preprocessing proof with a solver proof.
+
+mod common;
+use common::*;
+
+#[test]
+fn compose_with_rat_steps() {
+  let d = Dir::new();
+  let (clauses, _) = unsat_instance(10);
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  // The preprocessor adds the blocked clause `21 2 3`, which the solver does not use
+  let pre = d.write("pre.drat", "21 2 3 0\n");
+  // The solver defines 21 as a copy of 1 by RAT steps on 21, which are only valid
+  // once the unused clause is deleted, and refutes the CNF with 1 replaced by 21
+  let renamed: Vec<Vec<i64>> = clauses.iter()
+    .map(|c| c.iter().map(|&l| if l.abs() == 1 { l * 21 } else { l }).collect()).collect();
+  let mut proof = vec![(false, vec![-21, 1]), (false, vec![21, -1])];
+  proof.extend(refute(&renamed, 21).unwrap());
+  let solver_drat = d.write("solver.drat", drat(&proof));
+  let solver = d.path("solver.frat");
+  ok(&["from-drat", &cnf, &solver_drat, &solver]);
+
+  let (frat, lrat) = (d.path("c.frat"), d.path("c.lrat"));
+  ok(&["compose", &cnf, &pre, &solver, &frat, "--ascii"]);
+  let text = d.read_str("c.frat");
+  let del = text.lines().position(|l| l.split_whitespace().collect::<Vec<_>>() == ["d", "121", "21", "2", "3", "0"]);
+  let rat = text.lines().position(|l| l.split_whitespace().take(4).collect::<Vec<_>>() == ["a", "122", "-21", "1"]);
+  assert!(del.unwrap() < rat.unwrap());
+  ok(&["elab", &frat, &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat]);
+}
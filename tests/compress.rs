@@ -0,0 +1,55 @@
+//! Reading gzip, xz and zstd compressed inputs, forwards and backwards.
+
+mod common;
+use common::*;
+use std::io::Write;
+
+fn compress(format: &str, data: &[u8]) -> Vec<u8> {
+  match format {
+    "gz" => {
+      let mut e = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+      e.write_all(data).unwrap();
+      e.finish().unwrap()
+    }
+    "xz" => {
+      let mut e = xz2::write::XzEncoder::new(vec![], 6);
+      e.write_all(data).unwrap();
+      e.finish().unwrap()
+    }
+    "zst" => zstd::encode_all(data, 0).unwrap(),
+    _ => unreachable!(),
+  }
+}
+
+#[test]
+fn compressed_inputs() {
+  let d = Dir::new();
+  let (cnf, proof) = unsat_instance(3);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let drat = d.write("a.drat", drat(&proof));
+  let (frat, ascii) = (d.path("a.frat"), d.path("b.frat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["convert", &frat, &ascii]);
+  let lrat = d.path("a.lrat");
+  ok(&["elab", &frat, &cnf, &lrat]);
+  let cnf_out = ok(&["to-cnf", &frat]);
+  for format in ["gz", "xz", "zst"] {
+    let z = |name: &str| d.write(&format!("{}.{}", name, format), compress(format, &d.read(name)));
+    let (zcnf, zdrat, zfrat, zascii) = (z("a.cnf"), z("a.drat"), z("a.frat"), z("b.frat"));
+    // from-drat reads both inputs forwards
+    let frat2 = d.path("c.frat");
+    ok(&["from-drat", &zcnf, &zdrat, &frat2]);
+    assert_eq!(d.read("c.frat"), d.read("a.frat"), "{}", format);
+    // elab spools the FRAT to read it backwards, in a file or with -m in memory
+    for zfrat in [&zfrat, &zascii] {
+      let lrat2 = d.path("c.lrat");
+      ok(&["elab", zfrat, &zcnf, &lrat2]);
+      assert_eq!(d.read("c.lrat"), d.read("a.lrat"), "{}", format);
+      ok(&["elab", zfrat, "-m", &zcnf, &lrat2]);
+      assert_eq!(d.read("c.lrat"), d.read("a.lrat"), "{}", format);
+      assert_eq!(ok(&["to-cnf", zfrat]), cnf_out, "{}", format);
+    }
+    let zlrat = z("a.lrat");
+    ok(&["lratchk", &zcnf, &zlrat]);
+  }
+}
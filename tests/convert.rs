@@ -0,0 +1,135 @@
+//! The proof format converters.
+
+mod common;
+use common::*;
+
+/// A CNF, its DRAT refutation, and the FRAT and LRAT proofs elaborated from it.
+fn setup(d: &Dir, seed: u64) -> (String, String, String, String) {
+  let (cnf, proof) = unsat_instance(seed);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let drat = d.write("a.drat", drat(&proof));
+  let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  (cnf, drat, frat, lrat)
+}
+
+#[test]
+fn to_veripb() {
+  let d = Dir::new();
+  let (cnf, _, frat, lrat) = setup(&d, 3);
+  for args in [[&*frat, "a.pbp", "", ""], [&*lrat, "b.pbp", "--lrat", ""], [&*lrat, "c.pbp", "--lrat", "--rup"]] {
+    let out = d.path(args[1]);
+    let mut cmd = vec!["to-veripb", &*cnf, args[0], &*out];
+    cmd.extend(args[2..].iter().filter(|s| !s.is_empty()));
+    ok(&cmd);
+    let pbp = d.read_str(args[1]);
+    let mut lines = pbp.lines();
+    assert_eq!(lines.next(), Some("pseudo-Boolean proof version 1.2"));
+    assert_eq!(lines.next(), Some("f 120"));
+    assert!(lines.last().unwrap().starts_with("c "));
+  }
+}
+
+#[test]
+fn to_veripb_shared_constraints() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n");
+  // Clause 5 is clause 1, so it shares its constraint, which is deleted with the last of them
+  let lrat = d.write("a.lrat", "\
+    5 1 2 0 1 0\n5 d 1 0\n6 2 0 5 2 0\n6 d 5 2 0\n7 -2 0 3 4 0\n8 0 6 7 0\n");
+  let out = d.path("a.pbp");
+  ok(&["to-veripb", &cnf, &lrat, &out, "--lrat"]);
+  assert_eq!(d.read_str("a.pbp"), "\
+    pseudo-Boolean proof version 1.2\nf 4\n\
+    pol 2 1 + s\ndel id 1 2\npol 4 3 + s\npol 6 5 + s\nc 7\n");
+}
+
+#[test]
+fn to_veripb_empty_rat_lemma() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 1 2\n1 0\n-1 0\n");
+  let lrat = d.write("a.lrat", "3 0 -1 0\n");
+  let out = d.path("a.pbp");
+  assert_eq!(code(&["to-veripb", &cnf, &lrat, &out, "--lrat"]), 4);
+}
+
+#[test]
+fn convert_round_trip() {
+  let d = Dir::new();
+  let (cnf, _, frat, _) = setup(&d, 4);
+  let (ascii, bin) = (d.path("b.frat"), d.path("c.frat"));
+  ok(&["convert", &frat, &ascii]);
+  ok(&["convert", &ascii, &bin]);
+  assert_ne!(d.read("a.frat"), d.read("b.frat"));
+  assert_eq!(d.read("a.frat"), d.read("c.frat"));
+  let same = d.path("d.frat");
+  ok(&["convert", &frat, &same, "--binary"]);
+  assert_eq!(d.read("a.frat"), d.read("d.frat"));
+  ok(&["elab", &ascii, &cnf, &d.path("b.lrat")]);
+  ok(&["lratchk", &cnf, &d.path("b.lrat")]);
+}
+
+#[test]
+fn to_drat() {
+  let d = Dir::new();
+  let (cnf, _, frat, lrat) = setup(&d, 6);
+  let (a, b) = (d.path("b.drat"), d.path("c.drat"));
+  ok(&["to-drat", &frat, &a, "--binary"]);
+  ok(&["to-drat", &lrat, &b, "--lrat", &cnf, "--ascii"]);
+  // `dratchk` only reads binary DRAT
+  ok(&["dratchk", &cnf, &a]);
+  assert!(d.read_str("c.drat").lines().all(|l| l.ends_with(" 0") || l == "0"));
+  let frat = d.path("c.frat");
+  ok(&["from-drat", &cnf, &b, &frat]);
+  ok(&["elab", &frat, &cnf, &d.path("c.lrat")]);
+  ok(&["lratchk", &cnf, &d.path("c.lrat")]);
+  assert_eq!(code(&["to-drat", &frat, &a, "--bogus"]), 2);
+}
+
+#[test]
+fn from_lrat_round_trip() {
+  let d = Dir::new();
+  let (cnf, _, _, lrat) = setup(&d, 6);
+  let bin = d.path("b.lrat");
+  ok(&["elab", &d.path("a.frat"), &cnf, &bin, "--binary-lrat"]);
+  for (i, lrat) in [lrat, bin].iter().enumerate() {
+    let (frat, out) = (d.path(&format!("{}.frat", i)), d.path(&format!("{}.lrat", i)));
+    ok(&["from-lrat", &cnf, lrat, &frat, "--ascii"]);
+    ok(&["elab", &frat, &cnf, &out]);
+    ok(&["lratchk", &cnf, &out]);
+  }
+}
+
+#[test]
+fn strip_frat() {
+  let d = Dir::new();
+  let (cnf, _, _, lrat) = setup(&d, 7);
+  let full = d.path("full.frat");
+  ok(&["from-lrat", &cnf, &lrat, &full, "--binary"]);
+  for (name, opts) in [
+    ("all", &[][..]), ("rat", &["--rat"]), ("random", &["--random", "50", "--seed", "1"]),
+    ("ids", &["--ids", "130-140,150-"]), ("todo", &["--todo"]),
+  ] {
+    let out = d.path(&format!("{}.frat", name));
+    let mut args = vec!["strip-frat", &*full, &*out];
+    args.extend(opts);
+    args.push("--ascii");
+    ok(&args);
+    let frat = d.read_str(&format!("{}.frat", name));
+    let hints = frat.lines().filter(|l| l.split_whitespace().any(|w| w == "l")).count();
+    match name {
+      "all" => assert_eq!(hints, 0),
+      "rat" | "todo" => assert!(hints > 0),
+      _ => {}
+    }
+    let lrat = d.path(&format!("{}.lrat", name));
+    ok(&["elab", &out, &cnf, &lrat]);
+    ok(&["lratchk", &cnf, &lrat]);
+  }
+  // the random choice is determined by the seed
+  let again = d.path("again.frat");
+  ok(&["strip-frat", &full, &again, "--random", "50", "--seed", "1", "--ascii"]);
+  assert_eq!(d.read("random.frat"), d.read("again.frat"));
+  assert_eq!(code(&["strip-frat", &full, &again, "--random", "150"]), 2);
+}
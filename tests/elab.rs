@@ -0,0 +1,113 @@
+//! `elab` on standard input, its `--core` and `--usage` outputs, and
+//! `elab --threads`, whose output must check like that of `elab`.
+
+mod common;
+use common::*;
+
+#[test]
+fn threads_output_checks() {
+  let d = Dir::new();
+  for seed in [1, 2, 3, 4] {
+    let (cnf, proof) = unsat_instance(seed);
+    let cnf = d.write("a.cnf", dimacs(&cnf));
+    let drat = d.write("a.drat", drat(&proof));
+    let frat = d.path("a.frat");
+    ok(&["from-drat", &cnf, &drat, &frat]);
+    let lrat = d.path("a.lrat");
+    for threads in ["1", "2", "3"] {
+      ok(&["elab", &frat, "--threads", threads, &cnf, &lrat]);
+      ok(&["lratchk", &cnf, &lrat]);
+      ok(&["elab", &frat, "--full", "--threads", threads, &cnf, &lrat]);
+      ok(&["lratchk", &cnf, &lrat]);
+    }
+  }
+}
+
+#[test]
+fn threads_wrong_proof() {
+  let d = Dir::new();
+  let (cnf, _) = unsat_instance(2);
+  // the empty clause does not follow by unit propagation
+  let lits = |c: &[i64]| c.iter().map(|l| format!("{} ", l)).collect::<String>();
+  let mut proof = String::new();
+  for (i, c) in cnf.iter().enumerate() { proof += &format!("o {} {}0\n", i + 1, lits(c)) }
+  proof += &format!("a {} 0\n", cnf.len() + 1);
+  for (i, c) in cnf.iter().enumerate() { proof += &format!("f {} {}0\n", i + 1, lits(c)) }
+  proof += &format!("f {} 0\n", cnf.len() + 1);
+  let frat = d.write("a.frat", proof);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let lrat = d.path("a.lrat");
+  let expected = code(&["elab", &frat, &cnf, &lrat]);
+  assert_eq!(expected, 1);
+  for threads in ["1", "2"] {
+    let out = run(&["elab", &frat, "--threads", threads, &cnf, &lrat]);
+    assert_eq!(out.status.code(), Some(expected));
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("panicked"));
+  }
+}
+
+#[test]
+fn stdin_proof() {
+  let d = Dir::new();
+  let (cnf, proof) = unsat_instance(4);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let drat = d.write("a.drat", drat(&proof));
+  let (frat, ascii) = (d.path("a.frat"), d.path("b.frat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["convert", &frat, &ascii]);
+  let (lrat, lrat2) = (d.path("a.lrat"), d.path("b.lrat"));
+  ok(&["elab", &frat, &cnf, &lrat]);
+  for name in ["a.frat", "b.frat"] {
+    ok_stdin(&["elab", "-", &cnf, &lrat2], &d.read(name));
+    assert_eq!(d.read("b.lrat"), d.read("a.lrat"), "{}", name);
+    ok_stdin(&["elab", "-", "-m", &cnf, &lrat2], &d.read(name));
+    assert_eq!(d.read("b.lrat"), d.read("a.lrat"), "{}", name);
+  }
+}
+
+#[test]
+fn core_and_usage() {
+  let d = Dir::new();
+  let (clauses, proof) = unsat_instance(7);
+  let n = clauses.len() as u64;
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  let drat = d.write("a.drat", drat(&proof));
+  let frat = d.path("a.frat");
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  let (out, core, usage) = (d.path("a.lrat"), d.path("core.cnf"), d.path("a.usage"));
+  ok(&["elab", &frat, &cnf, &out, "--core", &core, "--usage", &usage]);
+  let steps = parse_lrat(&d.read_str("a.lrat"));
+
+  // the usage counts are the references to each original clause in the hints
+  let mut uses = vec![0; n as usize];
+  for step in &steps {
+    if let LratStep::Add(_, _, hint) = step {
+      for &h in hint { if h.unsigned_abs() <= n { uses[h.unsigned_abs() as usize - 1] += 1 } }
+    }
+  }
+  let counts: Vec<(u64, usize)> = d.read_str("a.usage").lines().map(|l| {
+    let (i, k) = l.split_once(' ').unwrap();
+    (i.parse().unwrap(), k.parse().unwrap())
+  }).collect();
+  assert_eq!(counts, uses.iter().enumerate().map(|(j, &k)| (j as u64 + 1, k)).collect::<Vec<_>>());
+
+  // the core is the used clauses, in order
+  let (_, core_clauses) = parse_dimacs(&d.read_str("core.cnf"));
+  let used: Vec<_> = clauses.iter().zip(&uses).filter(|p| *p.1 > 0).map(|p| p.0.clone()).collect();
+  assert_eq!(core_clauses, used);
+  assert!(used.len() < clauses.len());
+
+  // the LRAT proof checks against the core, once the used clauses are renumbered
+  let mut new_id = vec![0; n as usize + 1];
+  let mut k = 0;
+  for (j, &u) in uses.iter().enumerate() { if u > 0 { k += 1; new_id[j + 1] = k } }
+  let rename = |i: u64| if i <= n { new_id[i as usize] } else { i };
+  let steps: Vec<_> = steps.into_iter().map(|step| match step {
+    LratStep::Add(i, lits, hint) => LratStep::Add(i, lits,
+      hint.iter().map(|&h| rename(h.unsigned_abs()) as i64 * h.signum()).collect()),
+    LratStep::Del(i, ids) => LratStep::Del(i,
+      ids.into_iter().filter(|&j| rename(j) != 0).map(rename).collect()),
+  }).collect();
+  let lrat2 = d.write("core.lrat", lrat(&steps));
+  ok(&["lratchk", &core, &lrat2]);
+}
@@ -0,0 +1,88 @@
+//! Malformed inputs are reported as errors with the exit code of their class,
+//! rather than panics (exit code 101).
+
+mod common;
+use common::*;
+
+#[test]
+fn pipeline_checks() {
+  let d = Dir::new();
+  let (cnf, proof) = unsat_instance(1);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let drat = d.write("a.drat", drat(&proof));
+  let frat = d.path("a.frat");
+  let lrat = d.path("a.lrat");
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat]);
+}
+
+#[test]
+fn dratchk_malformed_proof() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 1 2\n1 0\n-1 0\n");
+  let proof = d.write("a.drat", "x 1 0\n");
+  assert_eq!(code(&["dratchk", &cnf, &proof]), 4);
+  let proof = d.write("b.drat", b"a\x02");
+  assert_eq!(code(&["dratchk", &cnf, &proof]), 4);
+  let proof = d.write("c.drat", b"d\x04\x00");
+  assert_eq!(code(&["dratchk", &cnf, &proof]), 4);
+}
+
+#[test]
+fn drat_trim_illegal_literal() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 1 2\n1 0\n-2 0\n");
+  let proof = d.write("a.drat", "0\n");
+  assert_eq!(code(&["drat-trim", &cnf, &proof]), 4);
+}
+
+#[test]
+fn bad_options_print_usage() {
+  for args in [
+    &["elab", "a.frat", "--threads"][..],
+    &["elab", "a.frat", "a.cnf", "--core"],
+    &["elab", "a.frat", "a.cnf", "a.lrat", "--grat", "a.lemmas"],
+    &["lratchk", "a.cnf", "a.lrat", "--threads", "x"],
+    &["lratchk", "a.cnf", "a.lrat", "--bogus"],
+    &["mus", "a.cnf"],
+    &["interpolate", "a.cnf", "a.frat", "a.aag", "--pudlak"],
+  ] {
+    let out = run(args);
+    assert_eq!(out.status.code(), Some(2), "{:?}", args);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Expected:"), "{:?}", args);
+  }
+}
+
+#[test]
+fn bad_options_are_errors_in_the_library() {
+  let args = ["a.cnf", "a.lrat", "--bogus"].iter().map(|s| s.to_string());
+  match frat_rs::elab::lratchk(args) {
+    Err(e @ frat_rs::error::FratError::Usage(_)) => assert_eq!(e.exit_code(), 2),
+    res => panic!("expected a usage error, got {:?}", res),
+  }
+}
+
+#[test]
+fn missing_subcommand_prints_usage() {
+  for args in [&[][..], &["bogus"]] {
+    let out = run(args);
+    assert_eq!(out.status.code(), Some(2), "{:?}", args);
+    let msg = String::from_utf8_lossy(&out.stderr).into_owned();
+    let list = msg.split_once(":\n\n").expect("no subcommand list").1;
+    for sub in list.split(',').map(str::trim) {
+      // every listed subcommand exists, so it does not print the list again
+      let out = run(&[sub, "--bogus", "x", "y", "z", "w", "v"]);
+      assert!(!String::from_utf8_lossy(&out.stderr).contains("Expected a subcommand"), "{}", sub);
+    }
+  }
+}
+
+#[test]
+fn missing_arguments_print_usage() {
+  for args in [&["stat"][..], &["dratchk", "a.cnf"], &["to-cnf"], &["to-cnf", "a.frat", "x"]] {
+    let out = run(args);
+    assert_eq!(out.status.code(), Some(2), "{:?}", args);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Expected:"), "{:?}", args);
+  }
+}
@@ -0,0 +1,162 @@
+//! GRAT certificates, decoded and checked item by item against the grammar in
+//! `src/grat.rs`, and by `gratchk` if it is installed.
+
+mod common;
+use common::*;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use frat_rs::grat::write_grat;
+use frat_rs::parser::Ascii;
+
+/// The integers of a GRAT proof file, in the order `gratchk` reads them.
+fn decode(proof: &[u8]) -> Vec<i64> {
+  assert_eq!(proof.len() % 4, 0);
+  let mut words: Vec<i64> = proof.chunks(4)
+    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64).collect();
+  words.reverse();
+  words
+}
+
+/// The state of the checker: the live clauses, and the assumed literals.
+struct Checker {
+  db: HashMap<u64, Vec<i64>>,
+  tru: HashSet<i64>,
+}
+
+impl Checker {
+  fn is_false(&self, lit: i64) -> bool { self.tru.contains(&-lit) }
+
+  fn unit(&mut self, id: i64) {
+    let c = &self.db[&(id as u64)];
+    let open: Vec<i64> = c.iter().copied().filter(|&l| !self.is_false(l)).collect();
+    assert!(open.len() == 1 && !self.tru.contains(&open[0]), "clause {} {:?} is not unit", id, c);
+    self.tru.insert(open[0]);
+  }
+
+  fn conflict(&self, id: i64) {
+    let c = &self.db[&(id as u64)];
+    assert!(c.iter().all(|&l| self.is_false(l)), "clause {} {:?} is not falsified", id, c);
+  }
+}
+
+/// Check a GRAT certificate of `cnf`, returning the number of RUP and RAT lemmas,
+/// and of RAT candidates which needed a proof.
+fn check_grat(cnf: &[Vec<i64>], lemmas: &str, proof: &[u8]) -> (usize, usize, usize) {
+  let mut words = decode(proof).into_iter();
+  let mut next = || words.next().expect("unexpected end of proof");
+  let ids = |next: &mut dyn FnMut() -> i64| -> Vec<i64> {
+    std::iter::from_fn(|| Some(next()).filter(|&n| n != 0)).collect()
+  };
+  assert_eq!(next(), 6, "missing RAT counts");
+  let counts = ids(&mut next);
+  let counts: HashMap<i64, i64> = counts.chunks(2).map(|p| (p[0], p[1])).collect();
+  let mut lemmas = lemmas.lines().map(|l| {
+    let mut c: Vec<i64> = l.split_whitespace().map(|w| w.parse().unwrap()).collect();
+    assert_eq!(c.pop(), Some(0));
+    c
+  });
+  let mut ck = Checker { db: (1..).zip(cnf.iter().cloned()).collect(), tru: HashSet::new() };
+  let (mut rup, mut rat, mut cands) = (0, 0, 0);
+  let mut rat_counts: HashMap<i64, i64> = HashMap::new();
+  loop {
+    ck.tru.clear();
+    match next() {
+      1 => {
+        for u in ids(&mut next) { ck.unit(u) }
+        assert_eq!(next(), 5, "missing final conflict");
+        ck.conflict(next());
+        break
+      }
+      2 => for i in ids(&mut next) { assert!(ck.db.remove(&(i as u64)).is_some(), "{} is not live", i) },
+      3 => {
+        let id = next() as u64;
+        let lemma = lemmas.next().expect("missing lemma");
+        ck.tru.extend(lemma.iter().map(|&l| -l));
+        for u in ids(&mut next) { ck.unit(u) }
+        ck.conflict(next());
+        assert!(ck.db.insert(id, lemma).is_none(), "{} is not fresh", id);
+        rup += 1;
+      }
+      4 => {
+        let (pivot, id) = (next(), next() as u64);
+        let lemma = lemmas.next().expect("missing lemma");
+        assert_eq!(lemma[0], pivot, "the pivot is not the first literal of the lemma");
+        *rat_counts.entry(pivot).or_default() += 1;
+        ck.tru.extend(lemma.iter().map(|&l| -l));
+        for u in ids(&mut next) { ck.unit(u) }
+        let base = ck.tru.clone();
+        let mut listed = HashSet::new();
+        loop {
+          let c = next();
+          if c == 0 { break }
+          let cand = ck.db[&(c as u64)].clone();
+          assert!(cand.contains(&-pivot), "candidate {} does not contain {}", c, -pivot);
+          ck.tru.extend(cand.iter().filter(|&&l| l != -pivot).map(|&l| -l));
+          for u in ids(&mut next) { ck.unit(u) }
+          ck.conflict(next());
+          ck.tru = base.clone();
+          listed.insert(c as u64);
+          cands += 1;
+        }
+        // every other candidate has a tautological resolvent
+        for (&j, c) in &ck.db {
+          if c.contains(&-pivot) && !listed.contains(&j) {
+            assert!(c.iter().any(|&l| l != -pivot && lemma.contains(&-l)),
+              "candidate {} {:?} is missing", j, c);
+          }
+        }
+        assert!(ck.db.insert(id, lemma).is_none(), "{} is not fresh", id);
+        rat += 1;
+      }
+      n => panic!("unknown item {}", n),
+    }
+  }
+  assert!(words.next().is_none(), "trailing items after the conflict");
+  assert!(lemmas.next().is_none(), "unused lemmas");
+  assert_eq!(counts, rat_counts, "wrong RAT counts");
+  (rup, rat, cands)
+}
+
+/// Run `gratchk` on the certificate, if it is installed.
+fn gratchk(cnf: &str, lemmas: &str, proof: &str) {
+  if let Ok(out) = Command::new("gratchk").args(["unsat", cnf, lemmas, proof]).output() {
+    assert!(out.status.success(), "gratchk failed:\n{}", String::from_utf8_lossy(&out.stdout));
+  }
+}
+
+#[test]
+fn rat_candidates() {
+  let d = Dir::new();
+  let clauses = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2], vec![3, 1], vec![3, -1], vec![3, 2]];
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  // Step 8 is a RAT step on -3. After the units of its hint, 2 is true, and its
+  // candidates are 5, whose resolvent is not tautological, 6, whose resolvent is,
+  // and 7, whose resolvent is falsified by the unit 2.
+  let proof = "8 -3 1 0 1 -5 3 -6 -7 0\n9 2 0 1 2 0\n9 d 6 0\n10 -2 0 3 4 0\n11 0 9 10 0\n";
+  let lrat = d.write("a.lrat", proof);
+  ok(&["lratchk", &cnf, &lrat]);
+  let cnf_boxed: Vec<Box<[i64]>> = clauses.iter().map(|c| c.clone().into()).collect();
+  let (mut lemmas, mut grat) = (vec![], vec![]);
+  write_grat(&cnf_boxed, Ascii, proof.bytes().map(Ok), &mut lemmas, &mut grat).unwrap();
+  let lemmas = String::from_utf8(lemmas).unwrap();
+  assert_eq!(lemmas, "-3 1 0\n2 0\n-2 0\n");
+  assert_eq!(check_grat(&clauses, &lemmas, &grat), (2, 1, 2));
+  gratchk(&cnf, &d.write("a.lemmas", &lemmas), &d.write("a.grat", &grat));
+}
+
+#[test]
+fn elab_grat() {
+  let d = Dir::new();
+  for seed in [1, 2, 3] {
+    let (clauses, proof) = unsat_instance(seed);
+    let cnf = d.write("a.cnf", dimacs(&clauses));
+    let drat = d.write("a.drat", drat(&proof));
+    let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+    let (lemmas, grat) = (d.path("a.lemmas"), d.path("a.grat"));
+    ok(&["from-drat", &cnf, &drat, &frat]);
+    ok(&["elab", &frat, &cnf, &lrat, "--grat", &lemmas, &grat]);
+    let (rup, _, _) = check_grat(&clauses, &d.read_str("a.lemmas"), &d.read("a.grat"));
+    assert!(rup > 0);
+    gratchk(&cnf, &lemmas, &grat);
+  }
+}
@@ -0,0 +1,117 @@
+//! `interpolate`, checked by evaluating the interpolant on every assignment of
+//! small instances: it must hold wherever `A` does, and fail wherever `B` holds.
+
+mod common;
+use common::*;
+
+/// Small unsatisfiable instances over 10 variables, whose first half `A` is over
+/// the variables 1 to 7 and whose second half `B` is over 4 to 10, and which are
+/// both satisfiable on their own.
+fn instances() -> impl Iterator<Item=(Vec<Vec<i64>>, Drat)> {
+  (1..).filter_map(|seed| {
+    let a = random_cnf(seed, 7, 25);
+    let b: Vec<Vec<i64>> = random_cnf(seed + 1000, 7, 25).into_iter()
+      .map(|c| c.into_iter().map(|l| l + 3 * l.signum()).collect()).collect();
+    if refute(&a, 10).is_some() || refute(&b, 10).is_some() { return None }
+    let cnf = [a, b].concat();
+    refute(&cnf, 10).map(|proof| (cnf, proof))
+  }).take(5)
+}
+
+/// Evaluate an ASCII AIGER file with one output, whose inputs are named by their
+/// DIMACS variable, under `x` (indexed by variable). Also returns the input variables.
+fn eval_aiger(aag: &str, x: &[bool]) -> (bool, Vec<usize>) {
+  let mut lines = aag.lines();
+  let header: Vec<usize> = lines.next().unwrap().split_whitespace().skip(1)
+    .map(|w| w.parse().unwrap()).collect();
+  let (m, i, l, o, a) = (header[0], header[1], header[2], header[3], header[4]);
+  assert_eq!((l, o), (0, 1));
+  let inputs: Vec<usize> = (0..i).map(|_| lines.next().unwrap().parse().unwrap()).collect();
+  let out: usize = lines.next().unwrap().parse().unwrap();
+  let ands: Vec<Vec<usize>> = (0..a).map(|_|
+    lines.next().unwrap().split_whitespace().map(|w| w.parse().unwrap()).collect()).collect();
+  let mut vars = vec![0; i];
+  for line in lines.take(i) {
+    let (k, v) = line.strip_prefix('i').unwrap().split_once(' ').unwrap();
+    vars[k.parse::<usize>().unwrap()] = v.parse().unwrap();
+  }
+  let mut val = vec![false; m + 1];
+  for (&lit, &v) in inputs.iter().zip(&vars) { val[lit / 2] = x[v] }
+  let get = |val: &[bool], lit: usize| val[lit / 2] ^ (lit & 1 != 0);
+  for g in &ands {
+    assert!(g[1] < g[0] && g[2] < g[0], "gates are not in topological order");
+    val[g[0] / 2] = get(&val, g[1]) && get(&val, g[2]);
+  }
+  (get(&val, out), vars)
+}
+
+/// Whether the CNF output of `interpolate` is satisfiable with the inputs set to `x`:
+/// the Tseitin encoding determines the gates by unit propagation.
+fn eval_cnf(cnf: &[Vec<i64>], vars: i64, x: &[bool]) -> bool {
+  let mut assign = vec![0; vars as usize + 1];
+  for v in 1..x.len() { assign[v] = if x[v] { 1 } else { -1 } }
+  propagate(cnf, &mut assign)
+}
+
+/// Check that the interpolant `eval` holds whenever `a` holds, and fails whenever `b` holds.
+fn check_interpolant(a: &[Vec<i64>], b: &[Vec<i64>], vars: usize, eval: impl Fn(&[bool]) -> bool) {
+  let sat = |cnf: &[Vec<i64>], x: &[bool]| cnf.iter()
+    .all(|c| c.iter().any(|&l| x[l.unsigned_abs() as usize] == (l > 0)));
+  let (mut a_models, mut b_models) = (0, 0);
+  for bits in 0..1u32 << vars {
+    let x: Vec<bool> = (0..=vars).map(|v| v > 0 && bits >> (v - 1) & 1 != 0).collect();
+    let i = eval(&x);
+    if sat(a, &x) { a_models += 1; assert!(i, "A does not imply the interpolant at {:?}", x) }
+    if sat(b, &x) { b_models += 1; assert!(!i, "the interpolant is consistent with B at {:?}", x) }
+  }
+  assert!(a_models > 0 && b_models > 0, "A or B is unsatisfiable on its own");
+}
+
+#[test]
+fn interpolants() {
+  let d = Dir::new();
+  for (clauses, proof) in instances() {
+    let half = clauses.len() / 2;
+    let (a, b) = clauses.split_at(half);
+    let shared: Vec<usize> = (1..=10).filter(|&v| [a, b].iter().all(|p| p.iter().flatten()
+      .any(|l| l.unsigned_abs() as usize == v))).collect();
+    let cnf = d.write("a.cnf", dimacs(&clauses));
+    let text = dimacs(&clauses);
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines.insert(half + 1, "c end of A");
+    let marked = d.write("m.cnf", lines.join("\n") + "\n");
+    let drat = d.write("a.drat", drat(&proof));
+    let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+    ok(&["from-drat", &cnf, &drat, &frat]);
+    ok(&["elab", &frat, &cnf, &lrat]);
+    let ranges = format!("1-10,11-{},{}", half - 1, half);
+    for system in [&[][..], &["--pudlak"]] {
+      for (input, lrat_flag) in [(&frat, &[][..]), (&lrat, &["--lrat"])] {
+        for cnf_out in [&[][..], &["--cnf"]] {
+          let out = d.path("i.out");
+          let run_with = |dimacs: &str, part: &[&str]| {
+            let mut args = vec!["interpolate", dimacs, input, &out];
+            args.extend(lrat_flag);
+            args.extend(part);
+            args.extend(system);
+            args.extend(cnf_out);
+            ok(&args);
+            d.read_str("i.out")
+          };
+          let result = run_with(&cnf, &["--a-clauses", &ranges]);
+          assert_eq!(run_with(&marked, &["--marker", "end of A"]), result);
+          if cnf_out.is_empty() {
+            check_interpolant(a, b, 10, |x| {
+              let (i, vars) = eval_aiger(&result, x);
+              assert_eq!(vars, shared, "the inputs are not the shared variables");
+              i
+            });
+          } else {
+            let (vars, tseitin) = parse_dimacs(&result);
+            check_interpolant(a, b, 10, |x| eval_cnf(&tseitin, vars, x));
+          }
+        }
+      }
+    }
+  }
+}
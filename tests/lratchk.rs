@@ -0,0 +1,164 @@
+//! `lratchk` on ASCII and binary LRAT files.
+
+mod common;
+use common::*;
+
+/// A CNF, with an ASCII and a binary LRAT proof of it.
+fn setup(d: &Dir) -> (String, String, String) {
+  let (cnf, proof) = unsat_instance(2);
+  let cnf = d.write("a.cnf", dimacs(&cnf));
+  let drat = d.write("a.drat", drat(&proof));
+  let frat = d.path("a.frat");
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  let (ascii, bin) = (d.path("a.lrat"), d.path("b.lrat"));
+  ok(&["elab", &frat, &cnf, &ascii]);
+  ok(&["elab", &frat, &cnf, &bin, "--binary-lrat"]);
+  (cnf, ascii, bin)
+}
+
+#[test]
+fn detects_mode() {
+  let d = Dir::new();
+  let (cnf, ascii, bin) = setup(&d);
+  assert_ne!(d.read("a.lrat"), d.read("b.lrat"));
+  ok(&["lratchk", &cnf, &ascii]);
+  ok(&["lratchk", &cnf, &bin]);
+}
+
+#[test]
+fn leading_comments_and_whitespace() {
+  let d = Dir::new();
+  let (cnf, _, _) = setup(&d);
+  let mut lrat = b"\n  c a comment\nc another\n\n".to_vec();
+  lrat.extend(d.read("a.lrat"));
+  let lrat = d.write("c.lrat", lrat);
+  ok(&["lratchk", &cnf, &lrat]);
+}
+
+#[test]
+fn empty_file() {
+  let d = Dir::new();
+  let (cnf, _, _) = setup(&d);
+  let lrat = d.write("e.lrat", "");
+  // not a mode mismatch (4), just no proof of the empty clause
+  assert_eq!(code(&["lratchk", &cnf, &lrat]), 1);
+  let lrat = d.write("f.lrat", "c only a comment\n");
+  assert_eq!(code(&["lratchk", &cnf, &lrat]), 1);
+}
+
+#[test]
+fn mixed_modes() {
+  let d = Dir::new();
+  let (cnf, _, _) = setup(&d);
+  let mut lrat = d.read("b.lrat");
+  lrat.pop();
+  let lrat = d.write("t.lrat", lrat);
+  assert_eq!(code(&["lratchk", &cnf, &lrat]), 4);
+  let mut lrat = d.read("a.lrat");
+  lrat.extend(d.read("b.lrat"));
+  let lrat = d.write("m.lrat", lrat);
+  assert_eq!(code(&["lratchk", &cnf, &lrat]), 4);
+}
+
+/// Check `lrat` with and without `--threads`, and return the common exit code.
+/// The error message must be the same, so the same step is reported.
+fn same_verdict(cnf: &str, lrat: &str) -> i32 {
+  let seq = run(&["lratchk", cnf, lrat]);
+  for n in ["1", "3"] {
+    let par = run(&["lratchk", cnf, lrat, "--threads", n]);
+    assert_eq!(seq.status.code(), par.status.code(), "--threads {}", n);
+    assert_eq!(String::from_utf8_lossy(&seq.stderr), String::from_utf8_lossy(&par.stderr),
+      "--threads {}", n);
+  }
+  seq.status.code().unwrap()
+}
+
+/// Apply `f` to the hints of the `k`-th addition step of an ASCII LRAT proof.
+fn edit_hint(lrat: &str, k: usize, f: impl Fn(&mut Vec<String>)) -> String {
+  let mut adds = 0;
+  lrat.lines().map(|l| {
+    let mut words: Vec<String> = l.split_whitespace().map(|s| s.to_owned()).collect();
+    if words[1] != "d" {
+      adds += 1;
+      if adds == k {
+        let start = words.iter().skip(1).position(|w| w == "0").unwrap() + 2;
+        let mut hint = words.split_off(start);
+        hint.pop();
+        f(&mut hint);
+        words.extend(hint);
+        words.push("0".into());
+      }
+    }
+    words.join(" ") + "\n"
+  }).collect()
+}
+
+#[test]
+fn threads_same_verdict() {
+  let d = Dir::new();
+  let (cnf, ascii, bin) = setup(&d);
+  assert_eq!(same_verdict(&cnf, &ascii), 0);
+  assert_eq!(same_verdict(&cnf, &bin), 0);
+  let lrat = d.read_str("a.lrat");
+  let adds = lrat.lines().filter(|l| l.split_whitespace().nth(1) != Some("d")).count();
+
+  // a RUP failure: the last clause of the chain is missing
+  let rup = d.write("rup.lrat", edit_hint(&lrat, adds / 2, |h| { h.pop(); }));
+  assert_eq!(same_verdict(&cnf, &rup), 1);
+
+  // a hint for a clause which does not exist
+  let missing = d.write("missing.lrat", edit_hint(&lrat, adds / 2, |h| h[0] = "999999".into()));
+  assert_eq!(same_verdict(&cnf, &missing), 4);
+
+  // an early failure followed by later ones: the first is reported
+  let mut many = edit_hint(&lrat, 3, |h| { h.pop(); });
+  for k in (4..adds).step_by(5) { many = edit_hint(&many, k, |h| { h.pop(); }) }
+  many = edit_hint(&many, adds - 1, |h| h[0] = "999999".into());
+  let many = d.write("many.lrat", many);
+  assert_eq!(same_verdict(&cnf, &many), 1);
+  let err = String::from_utf8_lossy(&run(&["lratchk", &cnf, &many]).stderr).into_owned();
+  let first = edit_hint(&lrat, 3, |h| { h.pop(); });
+  let first = d.write("first.lrat", first);
+  assert_eq!(err, String::from_utf8_lossy(&run(&["lratchk", &cnf, &first]).stderr));
+}
+
+#[test]
+fn threads_same_verdict_rat() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 3 5\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n3 1 0\n");
+  // step 6 is a RAT step on -3, with a RAT hint for clause 5
+  let lrat = d.write("a.lrat", "6 -3 1 0 -5 1 3 0\n7 2 0 1 2 0\n8 -2 0 3 4 0\n9 0 7 8 0\n");
+  assert_eq!(same_verdict(&cnf, &lrat), 0);
+  // the RAT hint is incomplete
+  let lrat = d.write("b.lrat", "6 -3 1 0 -5 1 0\n7 2 0 1 2 0\n8 -2 0 3 4 0\n9 0 7 8 0\n");
+  assert_eq!(same_verdict(&cnf, &lrat), 1);
+  // a failure after the RAT step, which is checked by the main thread
+  let lrat = d.write("c.lrat", "6 -3 1 0 -5 1 3 0\n7 2 0 1 0\n8 -2 0 3 4 0\n9 0 7 8 0\n");
+  assert_eq!(same_verdict(&cnf, &lrat), 1);
+}
+
+/// All four clauses over two variables.
+const TINY: &str = "p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n";
+
+#[test]
+fn sparse_out_of_order_ids() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", TINY);
+  let lrat = d.write("a.lrat", "10 2 0 1 2 0\n7 -2 0 3 4 0\n7 d 3 4 0\n20 0 10 7 0\n");
+  ok(&["lratchk", &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat, "--reuse-ids"]);
+  assert_eq!(code(&["lratchk", &cnf, &lrat, "--strict"]), 4);
+}
+
+#[test]
+fn reused_ids() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", TINY);
+  // clause 1 is deleted, and its ID used again for the empty clause
+  let lrat = d.write("a.lrat", "5 2 0 1 2 0\n5 d 1 0\n6 -2 0 3 4 0\n1 0 5 6 0\n");
+  assert_eq!(code(&["lratchk", &cnf, &lrat]), 4);
+  ok(&["lratchk", &cnf, &lrat, "--reuse-ids"]);
+  // an ID can only be reused once its clause is deleted
+  let lrat = d.write("b.lrat", "5 2 0 1 2 0\n6 -2 0 3 4 0\n1 0 5 6 0\n");
+  assert_eq!(code(&["lratchk", &cnf, &lrat, "--reuse-ids"]), 4);
+}
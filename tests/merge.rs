@@ -0,0 +1,89 @@
+//! `merge` of cube-and-conquer proofs.
+
+mod common;
+use common::*;
+
+/// Write the CNF, the cube file and one proof per cube of `cnf` with the cubes `1` and `-1`,
+/// as FRAT proofs or, if `lrat` is set, LRAT proofs.
+fn setup(d: &Dir, lrat: bool) -> (String, String, Vec<String>) {
+  setup_cubes(d, lrat, &[&[1], &[-1]])
+}
+
+/// Like [`setup`], with the given cubes.
+fn setup_cubes(d: &Dir, lrat: bool, cubes: &[&[i64]]) -> (String, String, Vec<String>) {
+  let (clauses, _) = unsat_instance(9);
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  let mut icnf = "p inccnf\n".to_owned();
+  for cube in cubes {
+    icnf.push('a');
+    for lit in *cube { icnf += &format!(" {}", lit) }
+    icnf += " 0\n";
+  }
+  let cube_file = d.write("a.icnf", icnf);
+  let proofs = cubes.iter().enumerate().map(|(k, cube)| {
+    let mut cube_cnf = clauses.clone();
+    cube_cnf.extend(cube.iter().map(|&lit| vec![lit]));
+    let proof = refute(&cube_cnf, 20).unwrap();
+    let name = format!("cube{}", k + 1);
+    let c = d.write(&format!("{}.cnf", name), dimacs(&cube_cnf));
+    let drat = d.write(&format!("{}.drat", name), drat(&proof));
+    let frat = d.path(&format!("{}.frat", name));
+    ok(&["from-drat", &c, &drat, &frat]);
+    if !lrat { return frat }
+    let out = d.path(&format!("{}.lrat", name));
+    ok(&["elab", &frat, &c, &out]);
+    out
+  }).collect();
+  (cnf, cube_file, proofs)
+}
+
+#[test]
+fn merge_frat() {
+  let d = Dir::new();
+  let (cnf, cubes, proofs) = setup(&d, false);
+  let (frat, lrat) = (d.path("m.frat"), d.path("m.lrat"));
+  ok(&["merge", &cnf, &cubes, &frat, &proofs[0], &proofs[1]]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat]);
+}
+
+#[test]
+fn merge_lrat() {
+  let d = Dir::new();
+  let (cnf, cubes, proofs) = setup(&d, true);
+  let (frat, lrat) = (d.path("m.frat"), d.path("m.lrat"));
+  ok(&["merge", &cnf, &cubes, &frat, "--lrat", "--ascii", &proofs[0], &proofs[1]]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat]);
+}
+
+#[test]
+fn duplicate_cubes() {
+  let d = Dir::new();
+  let (cnf, _, proofs) = setup(&d, false);
+  let frat = d.path("m.frat");
+  let cubes = d.write("b.icnf", "a 1 0\na 1 0\n");
+  let out = run(&["merge", &cnf, &cubes, &frat, &proofs[0], &proofs[0]]);
+  assert_eq!(out.status.code(), Some(4));
+  assert!(String::from_utf8_lossy(&out.stderr).contains("repeats or extends"));
+  let cubes = d.write("c.icnf", "a 1 0\na 1 2 0\n");
+  let out = run(&["merge", &cnf, &cubes, &frat, &proofs[0], &proofs[0]]);
+  assert_eq!(out.status.code(), Some(4));
+  assert!(String::from_utf8_lossy(&out.stderr).contains("repeats or extends"));
+}
+
+#[test]
+fn cubes_must_form_a_decision_tree() {
+  let d = Dir::new();
+  let (frat, lrat) = (d.path("m.frat"), d.path("m.lrat"));
+  // a deeper tree
+  let (cnf, cubes, proofs) = setup_cubes(&d, false, &[&[1, 2], &[1, -2], &[-1]]);
+  ok(&["merge", &cnf, &cubes, &frat, &proofs[0], &proofs[1], &proofs[2]]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  ok(&["lratchk", &cnf, &lrat]);
+  // these cubes cover the search space, but do not form a decision tree
+  let (cnf, cubes, proofs) = setup_cubes(&d, false, &[&[1, 2], &[-1], &[-2]]);
+  let out = run(&["merge", &cnf, &cubes, &frat, &proofs[0], &proofs[1], &proofs[2]]);
+  assert_eq!(out.status.code(), Some(4));
+  assert!(String::from_utf8_lossy(&out.stderr).contains("decision tree"));
+}
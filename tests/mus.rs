@@ -0,0 +1,30 @@
+//! `mus`, which shrinks the core to a subset that is irredundant with respect to
+//! the proof lemmas.
+
+mod common;
+use common::*;
+
+#[test]
+fn core_is_irredundant() {
+  let d = Dir::new();
+  for seed in [1, 2, 3] {
+    let (clauses, proof) = unsat_instance(seed);
+    let cnf = d.write("a.cnf", dimacs(&clauses));
+    let drat = d.write("a.drat", drat(&proof));
+    let frat = d.path("a.frat");
+    ok(&["from-drat", &cnf, &drat, &frat]);
+    let (core, lrat) = (d.path("core.cnf"), d.path("core.lrat"));
+    let out = ok(&["mus", &cnf, &frat, &core, &lrat, "-v"]);
+    assert!(out.contains("irredundant with respect to the proof lemmas"), "{}", out);
+    ok(&["lratchk", &core, &lrat]);
+    let (vars, core_clauses) = parse_dimacs(&d.read_str("core.cnf"));
+    assert!(core_clauses.iter().all(|c| clauses.contains(c)));
+    assert!(refute(&core_clauses, vars).is_some());
+
+    // using the proof of the core itself, no clause can be dropped
+    let (frat2, core2) = (d.path("b.frat"), d.path("core2.cnf"));
+    ok(&["from-lrat", &core, &lrat, &frat2]);
+    ok(&["mus", &core, &frat2, &core2]);
+    assert_eq!(parse_dimacs(&d.read_str("core2.cnf")).1, core_clauses, "seed {}", seed);
+  }
+}
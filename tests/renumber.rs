@@ -0,0 +1,60 @@
+//! `renumber` on proofs with sparse clause IDs.
+
+mod common;
+use common::*;
+
+/// Multiply the IDs of the derived clauses of an ASCII LRAT proof by 7, keeping
+/// the `n` original clauses.
+fn spread(lrat: &str, n: u64) -> String {
+  let id = |s: &str| {
+    let i: i64 = s.parse().unwrap();
+    if i.unsigned_abs() > n { (i * 7).to_string() } else { s.to_owned() }
+  };
+  let mut out = String::new();
+  for line in lrat.lines() {
+    let mut words = line.split_whitespace();
+    out.push_str(&id(words.next().unwrap()));
+    let mut lits = words.clone().next() != Some("d");
+    for w in words {
+      out.push(' ');
+      if lits || w == "d" { out.push_str(w) } else { out.push_str(&id(w)) }
+      if w == "0" { lits = false }
+    }
+    out.push('\n');
+  }
+  out
+}
+
+/// The largest clause ID of an ASCII LRAT proof.
+fn max_id(lrat: &str) -> u64 {
+  lrat.lines().map(|l| l.split_whitespace().next().unwrap().parse::<u64>().unwrap()).max().unwrap()
+}
+
+#[test]
+fn renumber_lrat_and_frat() {
+  let d = Dir::new();
+  let (clauses, proof) = unsat_instance(8);
+  let n = clauses.len() as u64;
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  let drat = d.write("a.drat", drat(&proof));
+  let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  let dense = d.read_str("a.lrat");
+  let sparse = d.write("s.lrat", spread(&dense, n));
+  ok(&["lratchk", &cnf, &sparse]);
+  assert!(max_id(&d.read_str("s.lrat")) > 2 * max_id(&dense));
+
+  let out = d.path("r.lrat");
+  ok(&["renumber", &sparse, &out, "--lrat", &cnf]);
+  ok(&["lratchk", &cnf, &out, "--strict"]);
+  let adds = dense.lines().filter(|l| l.split_whitespace().nth(1) != Some("d")).count() as u64;
+  assert_eq!(max_id(&d.read_str("r.lrat")), n + adds);
+
+  // through FRAT, in binary
+  let (sfrat, rfrat, rlrat) = (d.path("s.frat"), d.path("r.frat"), d.path("r2.lrat"));
+  ok(&["from-lrat", &cnf, &sparse, &sfrat, "--binary"]);
+  ok(&["renumber", &sfrat, &rfrat]);
+  ok(&["elab", &rfrat, &cnf, &rlrat]);
+  ok(&["lratchk", &cnf, &rlrat]);
+}
@@ -0,0 +1,107 @@
+//! The `--tracecheck` and `--resolution` outputs of `elab`, checked by replaying
+//! every resolution chain.
+
+mod common;
+use common::*;
+use std::collections::{BTreeSet, HashMap};
+
+type Clause = BTreeSet<i64>;
+
+/// The lines of a trace, as `(id, clause, antecedents)`, checking the format
+/// `id lits 0 ants 0` of each line.
+fn parse_trace(s: &str) -> Vec<(u64, Vec<i64>, Vec<i64>)> {
+  s.lines().map(|line| {
+    let nums: Vec<i64> = line.split(' ').map(|w| w.parse().expect(line)).collect();
+    assert_eq!(nums.iter().filter(|&&n| n == 0).count(), 2, "{}", line);
+    assert_eq!(nums.last(), Some(&0), "{}", line);
+    let (id, rest) = nums.split_first().unwrap();
+    assert!(*id > 0, "{}", line);
+    let k = rest.iter().position(|&n| n == 0).unwrap();
+    (*id as u64, rest[..k].to_vec(), rest[k + 1..rest.len() - 1].to_vec())
+  }).collect()
+}
+
+/// Resolve `c` with `d`, which must clash on exactly one literal, returning the
+/// resolvent and the literal of `d` resolved on.
+fn resolve(c: &Clause, d: &Clause) -> (Clause, i64) {
+  let clash: Vec<i64> = d.iter().copied().filter(|l| c.contains(&-l)).collect();
+  assert_eq!(clash.len(), 1, "{:?} and {:?} do not clash on exactly one literal", c, d);
+  let p = clash[0];
+  (c.iter().chain(d).copied().filter(|&l| l != p && l != -p).collect(), p)
+}
+
+/// Set up a proof of an instance, and run `elab` with the given extra arguments.
+fn elab_with(d: &Dir, seed: u64, args: &[&str]) -> Vec<Vec<i64>> {
+  let (clauses, proof) = unsat_instance(seed);
+  let cnf = d.write("a.cnf", dimacs(&clauses));
+  let drat = d.write("a.drat", drat(&proof));
+  let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  let mut all = vec!["elab", &frat, &cnf, &lrat];
+  all.extend(args);
+  ok(&all);
+  clauses
+}
+
+/// Check a trace whose derived clauses are linear resolution chains of their
+/// antecedents, which come with their pivots if `pivots` is set.
+fn check_chains(clauses: &[Vec<i64>], trace: &str, pivots: bool) {
+  let lines = parse_trace(trace);
+  let n = clauses.len();
+  let mut db: HashMap<u64, Clause> = HashMap::new();
+  for (j, (id, lits, ants)) in lines.iter().enumerate() {
+    let lits: Clause = lits.iter().copied().collect();
+    if j < n {
+      // the original clauses come first, without antecedents
+      assert_eq!((*id, &lits, ants.len()), (j as u64 + 1, &clauses[j].iter().copied().collect(), 0));
+    } else {
+      assert!(!db.contains_key(id), "clause {} is defined twice", id);
+      let get = |a: i64| db.get(&(a as u64)).unwrap_or_else(|| panic!("{} is not defined", a));
+      let mut res = get(ants[0]).clone();
+      let links = &ants[1..];
+      if pivots {
+        assert_eq!(links.len() % 2, 0, "{:?}", ants);
+        for link in links.chunks(2) {
+          let (r, p) = resolve(&res, get(link[0]));
+          // the pivot is the literal in the link clause, whose negation is in the resolvent
+          assert_eq!(p, link[1], "wrong pivot for {} in clause {}", link[0], id);
+          res = r;
+        }
+      } else {
+        assert!(!links.is_empty(), "clause {} has a single antecedent", id);
+        for &a in links { res = resolve(&res, get(a)).0 }
+      }
+      assert_eq!(res, lits, "the chain of clause {} does not give the clause", id);
+    }
+    db.insert(*id, lits);
+  }
+  assert!(lines.len() > n + 1, "no clauses are derived");
+  assert!(lines.last().unwrap().1.is_empty(), "the trace does not end with the empty clause");
+}
+
+#[test]
+fn tracecheck_chains() {
+  let d = Dir::new();
+  for seed in [1, 2, 3] {
+    let trace = d.path("a.trace");
+    let clauses = elab_with(&d, seed, &["--tracecheck", &trace]);
+    check_chains(&clauses, &d.read_str("a.trace"), false);
+  }
+}
+
+#[test]
+fn resolution_pivots() {
+  let d = Dir::new();
+  for seed in [1, 2, 3] {
+    let (trace, res) = (d.path("a.trace"), d.path("a.res"));
+    let clauses = elab_with(&d, seed, &["--tracecheck", &trace, "--resolution", &res]);
+    check_chains(&clauses, &d.read_str("a.res"), true);
+    // the chains are those of the TraceCheck trace, with the pivots added
+    let without_pivots: Vec<_> = parse_trace(&d.read_str("a.res")).into_iter()
+      .map(|(i, lits, ants)| {
+        let ants = ants.iter().take(1).chain(ants.iter().skip(1).step_by(2)).copied().collect();
+        (i, lits, ants)
+      }).collect();
+    assert_eq!(without_pivots, parse_trace(&d.read_str("a.trace")));
+  }
+}
@@ -0,0 +1,68 @@
+//! `split` into segments, checked with `lratchk --partial` and `splitchk`.
+
+mod common;
+use common::*;
+
+fn lrat_of(d: &Dir, clauses: &[Vec<i64>], proof: &Drat) -> (String, String) {
+  let cnf = d.write("a.cnf", dimacs(clauses));
+  let drat = d.write("a.drat", drat(proof));
+  let (frat, lrat) = (d.path("a.frat"), d.path("a.lrat"));
+  ok(&["from-drat", &cnf, &drat, &frat]);
+  ok(&["elab", &frat, &cnf, &lrat]);
+  (cnf, lrat)
+}
+
+#[test]
+fn split_round_trip() {
+  let d = Dir::new();
+  let (clauses, proof) = unsat_instance(11);
+  let (cnf, lrat) = lrat_of(&d, &clauses, &proof);
+  for (prefix, mode) in [("s", "--ascii"), ("b", "--binary")] {
+    let prefix = d.path(prefix);
+    ok(&["split", &cnf, &lrat, "3", &prefix, mode]);
+    for k in 1..=3 {
+      let (c, l) = (format!("{}.{}.cnf", prefix, k), format!("{}.{}.lrat", prefix, k));
+      ok(&["lratchk", &c, &l, "--partial"]);
+    }
+    ok(&["splitchk", &cnf, &format!("{}.manifest", prefix)]);
+  }
+}
+
+#[test]
+fn rat_steps_are_rejected() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 3 5\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n3 1 0\n");
+  // step 6 is a RAT step on -3, with a RAT hint for clause 5
+  let lrat = d.write("a.lrat", "6 -3 1 0 -5 1 3 0\n7 2 0 1 2 0\n8 -2 0 3 4 0\n9 0 7 8 0\n");
+  ok(&["lratchk", &cnf, &lrat]);
+  for args in [&["--partial"][..], &["--partial", "--threads", "2"]] {
+    let mut cmd = vec!["lratchk", &*cnf, &*lrat];
+    cmd.extend(args);
+    let out = run(&cmd);
+    assert_eq!(out.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("RAT steps"));
+  }
+  assert_eq!(code(&["split", &cnf, &lrat, "2", &d.path("s")]), 4);
+  // PR steps are rejected too, even if their hint is a unit propagation chain
+  let lrat = d.write("b.lrat", "6 2 2 0 1 2 0\n7 -2 0 3 4 0\n8 0 6 7 0\n");
+  ok(&["lratchk", &cnf, &lrat]);
+  let out = run(&["lratchk", &cnf, &lrat, "--partial"]);
+  assert_eq!(out.status.code(), Some(4));
+  assert!(String::from_utf8_lossy(&out.stderr).contains("PR steps"));
+  assert_eq!(code(&["split", &cnf, &lrat, "2", &d.path("t")]), 4);
+}
+
+#[test]
+fn steps_without_hints_are_rejected() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 2 2\n1 0\n-1 0\n");
+  // `2` is blocked, so it is accepted as a RAT step without hints
+  let lrat = d.write("a.lrat", "3 2 0 0\n4 2 -2 0 0\n5 0 1 2 0\n");
+  ok(&["lratchk", &cnf, &lrat]);
+  assert_eq!(code(&["lratchk", &cnf, &lrat, "--partial"]), 4);
+  assert_eq!(code(&["split", &cnf, &lrat, "2", &d.path("s")]), 4);
+  // a tautology needs no hints
+  let lrat = d.write("b.lrat", "3 2 -2 0 0\n4 0 1 2 0\n");
+  ok(&["lratchk", &cnf, &lrat, "--partial"]);
+  ok(&["split", &cnf, &lrat, "2", &d.path("t")]);
+}
@@ -0,0 +1,154 @@
+//! The `FratWriter` solver API and its C bindings.
+
+mod common;
+use common::*;
+use std::ffi::CString;
+use frat_rs::capi::*;
+use frat_rs::error::FratError;
+use frat_rs::parser::Ascii;
+use frat_rs::writer::FratWriter;
+
+fn ascii(f: impl FnOnce(&mut FratWriter<Ascii, Vec<u8>>)) -> String {
+  let mut w = FratWriter::new(Ascii, vec![]);
+  f(&mut w);
+  // normalize the spacing of the ASCII output
+  String::from_utf8(w.finish().unwrap()).unwrap().lines()
+    .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ") + "\n").collect()
+}
+
+#[test]
+fn steps() {
+  let out = ascii(|w| {
+    let c1 = w.orig(&[1, 2]).unwrap();
+    let c2 = w.orig(&[-1, 2]).unwrap();
+    let c3 = w.add(&[2], Some(&[c1 as i64, c2 as i64]), None).unwrap();
+    assert_eq!(c3, 3);
+    w.del(c1).unwrap();
+    w.comment("done").unwrap();
+    assert_eq!(w.live(), 2);
+  });
+  assert_eq!(out, "o 1 1 2 0\no 2 -1 2 0\na 3 2 0 l 1 2 0\nd 1 1 2 0\nc done.\nf 3 2 0\nf 2 -1 2 0\n");
+}
+
+#[test]
+fn bad_witness() {
+  ascii(|w| {
+    assert!(matches!(w.add(&[1, 2], None, Some(&[2])), Err(FratError::Malformed(_))));
+    assert!(matches!(w.add(&[], None, Some(&[1])), Err(FratError::Malformed(_))));
+  });
+}
+
+#[test]
+fn bad_literals() {
+  let out = ascii(|w| {
+    let c1 = w.orig(&[1, 2]).unwrap();
+    assert!(matches!(w.orig(&[1, 0, 2]), Err(FratError::Malformed(_))));
+    assert!(matches!(w.orig(&[i64::MIN]), Err(FratError::Malformed(_))));
+    assert!(matches!(w.add(&[0], None, None), Err(FratError::Malformed(_))));
+    assert!(matches!(w.add(&[1, 2], None, Some(&[1, 0])), Err(FratError::Malformed(_))));
+    // nothing was written, and no ID was used up
+    assert_eq!(w.add(&[1], Some(&[c1 as i64]), None).unwrap(), 2);
+  });
+  assert_eq!(out, "o 1 1 2 0\na 2 1 0 l 1 0\nf 2 1 0\nf 1 1 2 0\n");
+}
+
+#[test]
+fn bad_hints() {
+  let out = ascii(|w| {
+    let c1 = w.orig(&[1, 2]).unwrap();
+    let c2 = w.orig(&[-1]).unwrap();
+    assert!(matches!(w.add(&[2], Some(&[c1 as i64, 0]), None), Err(FratError::Malformed(_))));
+    assert!(matches!(w.add(&[2], Some(&[i64::MIN]), None), Err(FratError::Malformed(_))));
+    assert!(matches!(w.add(&[2], Some(&[c1 as i64, 7]), None),
+      Err(FratError::MissingClause { step: 3, id: 7 })));
+    // RAT candidates are named by negative IDs, and must be live too
+    assert!(matches!(w.add(&[2], Some(&[-7]), None),
+      Err(FratError::MissingClause { step: 3, id: 7 })));
+    w.del(c2).unwrap();
+    assert!(matches!(w.add(&[2], Some(&[c1 as i64, c2 as i64]), None),
+      Err(FratError::MissingClause { step: 4, id: 2 })));
+  });
+  assert_eq!(out, "o 1 1 2 0\no 2 -1 0\nd 2 -1 0\nf 1 1 2 0\n");
+}
+
+#[test]
+fn del_missing() {
+  ascii(|w| {
+    w.orig(&[1]).unwrap();
+    assert!(matches!(w.del(5), Err(FratError::MissingClause { step: 2, id: 5 })));
+  });
+}
+
+#[test]
+fn reloc() {
+  let out = ascii(|w| {
+    let c1 = w.orig(&[1]).unwrap();
+    let c2 = w.orig(&[2]).unwrap();
+    // swapping two clauses is allowed
+    w.reloc(&[(c1, c2), (c2, c1)]).unwrap();
+    w.reloc(&[(c1, 10)]).unwrap();
+    // fresh IDs are larger than any relocated ID
+    assert_eq!(w.add(&[1, 2], None, None).unwrap(), 11);
+  });
+  assert_eq!(out, "o 1 1 0\no 2 2 0\nr\n1 2 2 1\n0\nr\n1 10\n0\na 11 1 2 0\nf 11 1 2 0\nf 10 2 0\nf 2 1 0\n");
+}
+
+#[test]
+fn reloc_failure_changes_nothing() {
+  let out = ascii(|w| {
+    let c1 = w.orig(&[1]).unwrap();
+    let c2 = w.orig(&[2]).unwrap();
+    let c3 = w.orig(&[3]).unwrap();
+    // `to` is live and not moved away
+    assert!(matches!(w.reloc(&[(c1, 10), (c2, c3)]),
+      Err(FratError::DuplicateClause { step: 4, id: 3 })));
+    // two clauses moved to the same ID
+    assert!(matches!(w.reloc(&[(c1, 10), (c2, 10)]),
+      Err(FratError::DuplicateClause { step: 4, id: 10 })));
+    // `from` is not live
+    assert!(matches!(w.reloc(&[(c1, 10), (7, 11)]),
+      Err(FratError::MissingClause { step: 4, id: 7 })));
+    // the same clause moved twice
+    assert!(matches!(w.reloc(&[(c1, 10), (c1, 11)]),
+      Err(FratError::MissingClause { step: 4, id: 1 })));
+    assert_eq!(w.live(), 3);
+    w.del(c1).unwrap();
+  });
+  assert_eq!(out, "o 1 1 0\no 2 2 0\no 3 3 0\nd 1 1 0\nf 3 3 0\nf 2 2 0\n");
+}
+
+#[test]
+fn c_api_proof_elaborates() {
+  let d = Dir::new();
+  let cnf = d.write("a.cnf", "p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n");
+  let frat = d.path("a.frat");
+  let lrat = d.path("a.lrat");
+  for &binary in &[0, 1] {
+    let path = CString::new(frat.clone()).unwrap();
+    unsafe {
+      let w = frat_writer_open(path.as_ptr(), binary);
+      assert!(!w.is_null());
+      let c1 = frat_orig(w, [1, 2].as_ptr(), 2);
+      let c2 = frat_orig(w, [-1, 2].as_ptr(), 2);
+      assert_eq!(frat_orig(w, [1, -2].as_ptr(), 2), 3);
+      assert_eq!(frat_orig(w, [-1, -2].as_ptr(), 2), 4);
+      let hint = [c1 as i64, c2 as i64];
+      let u = frat_add(w, [2].as_ptr(), 1, hint.as_ptr(), 2, std::ptr::null(), 0);
+      assert_eq!(u, 5);
+      assert_eq!(frat_reloc(w, [u, 9].as_ptr(), 1), 0);
+      // a failed reloc returns the exit code of the error, and changes nothing
+      assert_eq!(frat_reloc(w, [c1, 9].as_ptr(), 1), 4);
+      assert_eq!(frat_del(w, c1), 0);
+      assert_eq!(frat_del(w, c1), 4);
+      let comment = CString::new("unit 2").unwrap();
+      assert_eq!(frat_comment(w, comment.as_ptr()), 0);
+      // bad steps return 0, and are not written
+      assert_eq!(frat_orig(w, [1, 0].as_ptr(), 2), 0);
+      assert_eq!(frat_add(w, [1].as_ptr(), 1, [c1 as i64].as_ptr(), 1, std::ptr::null(), 0), 0);
+      assert_eq!(frat_add(w, std::ptr::null(), 0, std::ptr::null(), 0, std::ptr::null(), 0), 10);
+      assert_eq!(frat_finish(w), 0);
+    }
+    ok(&["elab", &frat, &cnf, &lrat]);
+    ok(&["lratchk", &cnf, &lrat]);
+  }
+}